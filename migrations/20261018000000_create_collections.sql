@@ -0,0 +1,39 @@
+CREATE TABLE IF NOT EXISTS user_credits
+(
+    id       TEXT PRIMARY KEY,
+    username TEXT    NOT NULL,
+    user_id  TEXT    NOT NULL UNIQUE,
+    credits  INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS user_lotteries
+(
+    id               TEXT PRIMARY KEY,
+    user_id          TEXT  NOT NULL UNIQUE,
+    next_daily_time  TEXT  NOT NULL,
+    next_weekly_time TEXT  NOT NULL,
+    lotteries        JSONB NOT NULL DEFAULT '[]'::jsonb
+);
+
+CREATE TABLE IF NOT EXISTS user_rolls
+(
+    id               TEXT PRIMARY KEY,
+    roll_id          INTEGER NOT NULL,
+    user_id          TEXT    NOT NULL,
+    mal_character_id INTEGER NOT NULL,
+    created_at       TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS user_rolls_user_id_idx ON user_rolls (user_id);
+
+CREATE TABLE IF NOT EXISTS mal_characters
+(
+    id           TEXT PRIMARY KEY,
+    character_id INTEGER NOT NULL UNIQUE,
+    url          TEXT    NOT NULL,
+    name         TEXT    NOT NULL,
+    name_kanji   TEXT    NOT NULL,
+    image_url    TEXT    NOT NULL,
+    created_at   TEXT    NOT NULL,
+    about        TEXT    NOT NULL
+);
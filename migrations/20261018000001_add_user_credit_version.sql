@@ -0,0 +1,2 @@
+ALTER TABLE user_credits
+    ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 0;
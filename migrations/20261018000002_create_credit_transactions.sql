@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS credit_transactions
+(
+    id            TEXT PRIMARY KEY,
+    user_id       TEXT        NOT NULL,
+    amount        INTEGER     NOT NULL,
+    reason        TEXT        NOT NULL,
+    balance_after INTEGER     NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL,
+    actor         TEXT        NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS credit_transactions_user_id_created_at_idx
+    ON credit_transactions (user_id, created_at);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS lottery_draws
+(
+    id              TEXT PRIMARY KEY,
+    winning_numbers JSONB       NOT NULL,
+    results         JSONB       NOT NULL,
+    drawn_at        TIMESTAMPTZ NOT NULL,
+    actor           TEXT        NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS lottery_draws_drawn_at_idx
+    ON lottery_draws (drawn_at);
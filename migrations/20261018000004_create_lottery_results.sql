@@ -0,0 +1,46 @@
+CREATE TABLE IF NOT EXISTS lottery_results
+(
+    id       TEXT PRIMARY KEY,
+    draw_id  TEXT        NOT NULL REFERENCES lottery_draws (id),
+    user_id  TEXT        NOT NULL,
+    ticket   JSONB       NOT NULL,
+    matches  SMALLINT    NOT NULL,
+    prize    INTEGER     NOT NULL,
+    drawn_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS lottery_results_user_id_drawn_at_idx
+    ON lottery_results (user_id, drawn_at);
+
+CREATE INDEX IF NOT EXISTS lottery_results_draw_id_idx
+    ON lottery_results (draw_id);
+
+ALTER TABLE lottery_draws
+    ADD COLUMN IF NOT EXISTS total_tickets INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS tiers         JSONB   NOT NULL DEFAULT '[]',
+    ADD COLUMN IF NOT EXISTS jackpot       INTEGER NOT NULL DEFAULT 0;
+
+-- Move the results that were embedded into the draws so far into their own table.
+INSERT INTO lottery_results (id, draw_id, user_id, ticket, matches, prize, drawn_at)
+SELECT gen_random_uuid()::TEXT,
+       d.id,
+       r ->> 'user_id',
+       r -> 'ticket',
+       (r ->> 'matches')::SMALLINT,
+       (r ->> 'prize')::INTEGER,
+       d.drawn_at
+FROM lottery_draws d,
+     jsonb_array_elements(d.results) r;
+
+UPDATE lottery_draws d
+SET total_tickets = jsonb_array_length(d.results),
+    tiers         = COALESCE((SELECT jsonb_agg(jsonb_build_object('matches', t.matches, 'winners', t.winners,
+                                                               'prize', t.prize) ORDER BY t.matches DESC)
+                              FROM (SELECT matches, COUNT(*) AS winners, MAX(prize) AS prize
+                                    FROM lottery_results
+                                    WHERE draw_id = d.id
+                                      AND prize > 0
+                                    GROUP BY matches) t), '[]');
+
+ALTER TABLE lottery_draws
+    DROP COLUMN IF EXISTS results;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS lottery_jackpot
+(
+    id         TEXT PRIMARY KEY,
+    amount     INTEGER     NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
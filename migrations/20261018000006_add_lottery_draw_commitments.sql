@@ -0,0 +1,13 @@
+ALTER TABLE lottery_draws
+    ADD COLUMN IF NOT EXISTS seed_hash    TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS seed         TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS committed_at TIMESTAMPTZ;
+
+CREATE TABLE IF NOT EXISTS lottery_commitments
+(
+    id           TEXT PRIMARY KEY,
+    draw_id      TEXT        NOT NULL,
+    seed         TEXT        NOT NULL,
+    seed_hash    TEXT        NOT NULL,
+    committed_at TIMESTAMPTZ NOT NULL
+);
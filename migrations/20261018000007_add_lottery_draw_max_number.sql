@@ -0,0 +1,2 @@
+ALTER TABLE lottery_draws
+    ADD COLUMN IF NOT EXISTS max_number SMALLINT NOT NULL DEFAULT 49;
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS user_rewards
+(
+    id               TEXT PRIMARY KEY,
+    user_id          TEXT        NOT NULL UNIQUE,
+    next_daily_time  TIMESTAMPTZ NOT NULL,
+    next_weekly_time TIMESTAMPTZ NOT NULL,
+    version          BIGINT      NOT NULL DEFAULT 0
+);
+
+-- Reward cooldowns used to live on the lottery documents, which only exist once a user bought a ticket.
+INSERT INTO user_rewards (id, user_id, next_daily_time, next_weekly_time)
+SELECT user_id,
+       user_id,
+       COALESCE(NULLIF(next_daily_time, '')::TIMESTAMPTZ, 'epoch'),
+       COALESCE(NULLIF(next_weekly_time, '')::TIMESTAMPTZ, 'epoch')
+FROM user_lotteries
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE user_lotteries
+    DROP COLUMN IF EXISTS next_daily_time,
+    DROP COLUMN IF EXISTS next_weekly_time;
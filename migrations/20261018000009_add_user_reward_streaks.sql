@@ -0,0 +1,2 @@
+ALTER TABLE user_rewards
+    ADD COLUMN IF NOT EXISTS daily_streak INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE mal_characters
+    ADD COLUMN IF NOT EXISTS rarity TEXT NOT NULL DEFAULT 'Common';
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS user_roll_pities
+(
+    id      TEXT PRIMARY KEY,
+    user_id TEXT    NOT NULL UNIQUE,
+    misses  INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS banners
+(
+    id                     TEXT PRIMARY KEY,
+    name                   TEXT             NOT NULL,
+    start_time             TIMESTAMPTZ      NOT NULL,
+    end_time               TIMESTAMPTZ      NOT NULL,
+    featured_character_ids INTEGER[]        NOT NULL DEFAULT '{}',
+    rate_up                DOUBLE PRECISION NOT NULL DEFAULT 1,
+    roll_cost              INTEGER          NOT NULL
+);
+
+ALTER TABLE user_rolls
+    ADD COLUMN IF NOT EXISTS banner_id TEXT;
@@ -0,0 +1,12 @@
+ALTER TABLE user_rolls
+    ADD COLUMN IF NOT EXISTS consumed BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS character_progresses
+(
+    id               TEXT PRIMARY KEY,
+    user_id          TEXT    NOT NULL,
+    mal_character_id INTEGER NOT NULL,
+    level            INTEGER NOT NULL DEFAULT 1,
+    affinity         INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (user_id, mal_character_id)
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS trade_offers
+(
+    id                 TEXT PRIMARY KEY,
+    from_user_id       TEXT        NOT NULL,
+    to_user_id         TEXT        NOT NULL,
+    offered_roll_ids   INTEGER[]   NOT NULL DEFAULT '{}',
+    requested_roll_ids INTEGER[]   NOT NULL DEFAULT '{}',
+    offered_credits    INTEGER     NOT NULL DEFAULT 0,
+    status             TEXT        NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL,
+    expires_at         TIMESTAMPTZ NOT NULL,
+    resolved_at        TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS trade_offers_from_user_id_idx ON trade_offers (from_user_id);
+CREATE INDEX IF NOT EXISTS trade_offers_to_user_id_idx ON trade_offers (to_user_id);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS market_listings
+(
+    id                TEXT PRIMARY KEY,
+    seller_id         TEXT        NOT NULL,
+    roll_id           INTEGER     NOT NULL,
+    mal_character_id  INTEGER     NOT NULL,
+    listing_type      TEXT        NOT NULL,
+    price             INTEGER     NOT NULL,
+    highest_bid       INTEGER,
+    highest_bidder_id TEXT,
+    status            TEXT        NOT NULL,
+    created_at        TIMESTAMPTZ NOT NULL,
+    ends_at           TIMESTAMPTZ,
+    buyer_id          TEXT,
+    sale_price        INTEGER,
+    fee               INTEGER,
+    settled_at        TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS market_listings_status_idx ON market_listings (status);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS user_rolls_mal_character_id_idx ON user_rolls (mal_character_id);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS user_rolls_user_id_roll_id_idx ON user_rolls (user_id, roll_id);
@@ -0,0 +1,20 @@
+-- Rolls that were stored with a roll ID the user already had are moved after the user's last roll.
+WITH copies AS (
+    SELECT id, user_id, roll_id,
+           ROW_NUMBER() OVER (PARTITION BY user_id, roll_id ORDER BY created_at, id) AS copy
+    FROM user_rolls
+),
+renumbered AS (
+    SELECT c.id,
+           (SELECT MAX(u.roll_id) FROM user_rolls u WHERE u.user_id = c.user_id)
+               + ROW_NUMBER() OVER (PARTITION BY c.user_id ORDER BY c.roll_id, c.copy) AS roll_id
+    FROM copies c
+    WHERE c.copy > 1
+)
+UPDATE user_rolls
+SET roll_id = renumbered.roll_id
+FROM renumbered
+WHERE user_rolls.id = renumbered.id;
+
+DROP INDEX IF EXISTS user_rolls_user_id_roll_id_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS user_rolls_user_id_roll_id_key ON user_rolls (user_id, roll_id);
@@ -0,0 +1,5 @@
+ALTER TABLE lottery_results
+    ADD COLUMN IF NOT EXISTS paid BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE INDEX IF NOT EXISTS lottery_results_unpaid_idx
+    ON lottery_results (user_id) WHERE NOT paid;
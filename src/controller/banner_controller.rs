@@ -0,0 +1,91 @@
+use crate::model::app_state::AppState;
+use crate::model::banner::{Banner, BannerQuery};
+use crate::model::claim::Claim;
+use crate::model::errors::ServerError;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub async fn get_banners(
+    _claim: Claim,
+    Query(banner_query): Query<BannerQuery>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_banners().await {
+        Ok(mut banners) => {
+            let now = OffsetDateTime::now_utc();
+            banners.retain(|banner| !banner_query.active || banner.is_active(now));
+            banners.sort_by_key(|banner| banner.start_time);
+            (StatusCode::OK, Json(banners)).into_response()
+        }
+        Err(e) => {
+            let error_message = format!("Failed to retrieve banners: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_banner(
+    _claim: Claim,
+    Path(banner_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_banner(&banner_id).await {
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The specified banner is not found.",
+            )),
+        )
+            .into_response(),
+        Ok(Some(banner)) => (StatusCode::OK, Json(banner)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve banner: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn post_banner(
+    _claim: Claim,
+    State(state): State<AppState>,
+    Json(mut payload): Json<Banner>,
+) -> Response {
+    if let Err(error_message) = payload.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ServerError::with_message(error_message)),
+        )
+            .into_response();
+    }
+
+    if payload.id.is_empty() {
+        payload.id = Uuid::new_v4().to_string();
+    }
+
+    match state.repository.upsert_banner(payload.clone()).await {
+        Ok(_) => (StatusCode::CREATED, Json(payload)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to insert banner into database: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
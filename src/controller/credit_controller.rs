@@ -1,32 +1,47 @@
 use crate::model::app_state::AppState;
 use crate::model::claim::Claim;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransactionReason};
 use crate::model::errors::ServerError;
+use crate::model::page::PageRequest;
 use crate::model::user_credit::{UserCredit, UserCreditUpdateInfo, UserCreditUpdateOpt};
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use azure_data_cosmos::prelude::{Param, Query};
 
-use crate::shared::util::{
-    add_document, adjust_credit, get_documents, query_document, query_document_within_collection,
-};
+use crate::shared::credit::{adjust_credit, CreditChange};
 
-pub const USER_CREDITS: &str = "UserCredits";
-
-pub async fn get_all_user_credits(_claim: Claim, State(state): State<AppState>) -> Response {
-    let cosmos_db = state.cosmos_db;
-
-    if let Some(credits) = get_documents::<UserCredit, _>(&cosmos_db.database, USER_CREDITS).await {
-        (StatusCode::OK, Json(credits)).into_response()
+pub async fn get_all_user_credits(
+    _claim: Claim,
+    Query(page_request): Query<PageRequest>,
+    State(state): State<AppState>,
+) -> Response {
+    let result = if page_request.is_paged() {
+        state
+            .repository
+            .get_user_credits_page(&page_request)
+            .await
+            .map(IntoResponse::into_response)
     } else {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ServerError {
-                error_message: "Failed to retrieve user credits.".to_string(),
-            }),
-        )
-            .into_response()
+        state
+            .repository
+            .get_all_user_credits()
+            .await
+            .map(|credits| (StatusCode::OK, Json(credits)).into_response())
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to retrieve user credits: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError {
+                    error_message: "Failed to retrieve user credits.".to_string(),
+                }),
+            )
+                .into_response()
+        }
     }
 }
 
@@ -35,32 +50,24 @@ pub async fn get_single_user_credits(
     Path(user_id): Path<String>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_CREDITS
-        ),
-        vec![Param::new("@user_id".into(), user_id)],
-    );
-
-    if let Some(query_result) =
-        query_document::<UserCredit, _, _>(&cosmos_db.database, USER_CREDITS, query, true).await
-    {
-        (
-            StatusCode::OK,
-            Json(query_result.first().cloned().unwrap_or_default()),
-        )
-            .into_response()
-    } else {
-        (
+    match state.repository.get_user_credit(&user_id).await {
+        Ok(Some(user_credit)) => (StatusCode::OK, Json(user_credit)).into_response(),
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError {
                 error_message: "The specified user's credit info is not found.".into(),
             }),
         )
-            .into_response()
+            .into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's credit info: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
 
@@ -87,29 +94,33 @@ pub async fn add_user(
             .into_response();
     }
 
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_CREDITS
-        ),
-        vec![Param::new("@user_id".into(), user_credit.user_id.clone())],
-    );
-
-    let cosmos_db = state.cosmos_db;
-
-    let query_result =
-        query_document::<UserCredit, _, _>(&cosmos_db.database, USER_CREDITS, query, true).await;
-    if query_result.is_some() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ServerError::with_message(
-                "Specified user already exists. Use PATCH to update user's information.",
-            )),
-        )
-            .into_response();
+    match state.repository.get_user_credit(&user_credit.user_id).await {
+        Ok(Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ServerError::with_message(
+                    "Specified user already exists. Use PATCH to update user's information.",
+                )),
+            )
+                .into_response();
+        }
+        Ok(None) => {}
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's credit info: {}", e);
+            tracing::error!("{}", &error_message);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response();
+        }
     }
 
-    match add_document(&cosmos_db.database, USER_CREDITS, user_credit.clone()).await {
+    match state
+        .repository
+        .upsert_user_credit(user_credit.clone())
+        .await
+    {
         Ok(_) => (StatusCode::CREATED, Json(user_credit)).into_response(),
         Err(e) => {
             let error_message = format!("{}", e);
@@ -124,73 +135,83 @@ pub async fn add_user(
 }
 
 pub async fn add_credit(
-    _claim: Claim,
+    claim: Claim,
     Path(user_id): Path<String>,
     State(state): State<AppState>,
     Json(user_credit): Json<UserCreditUpdateInfo>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
     adjust_credit(
-        &cosmos_db.database,
+        state.repository.as_ref(),
         user_id,
         user_credit,
         UserCreditUpdateOpt::Plus,
+        CreditChange::new(claim.sub, CreditTransactionReason::Adjustment),
     )
     .await
 }
 
 pub async fn reduce_credit(
-    _claim: Claim,
+    claim: Claim,
     Path(user_id): Path<String>,
     State(state): State<AppState>,
     Json(user_credit): Json<UserCreditUpdateInfo>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
     adjust_credit(
-        &cosmos_db.database,
+        state.repository.as_ref(),
         user_id,
         user_credit,
         UserCreditUpdateOpt::Minus,
+        CreditChange::new(claim.sub, CreditTransactionReason::Adjustment),
     )
     .await
 }
 
-pub async fn delete_user(
+pub async fn get_credit_history(
     _claim: Claim,
     Path(user_id): Path<String>,
+    Query(history_query): Query<CreditHistoryQuery>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let collection = cosmos_db.database.collection_client(USER_CREDITS);
+    if let (Some(from), Some(to)) = (history_query.from, history_query.to) {
+        if from > to {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ServerError::with_message(
+                    "The start of the date range has to be before its end.",
+                )),
+            )
+                .into_response();
+        }
+    }
 
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_CREDITS
-        ),
-        vec![Param::new("@user_id".into(), user_id)],
-    );
-    let query_result = query_document_within_collection::<UserCredit, _>(&collection, query, true)
+    match state
+        .repository
+        .get_credit_transactions(&user_id, &history_query)
         .await
-        .and_then(|result| result.first().cloned());
+    {
+        Ok(transactions) => (StatusCode::OK, Json(transactions)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's credit history: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
 
-    if let Some(result) = query_result {
-        let document = collection.document_client(result.id.clone(), &result.id);
-        match document {
-            Ok(doc) => match doc.delete_document().into_future().await {
-                Ok(_) => StatusCode::NO_CONTENT.into_response(),
-                Err(e) => {
-                    let error_message = format!("Failed to delete user credit: {}", e);
-                    tracing::error!("{}", &error_message);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ServerError::with_message(error_message)),
-                    )
-                        .into_response()
-                }
-            },
+pub async fn delete_user(
+    _claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_user_credit(&user_id).await {
+        Ok(Some(result)) => match state.repository.delete_user_credit(&result).await {
+            Ok(_) => StatusCode::NO_CONTENT.into_response(),
             Err(e) => {
-                let error_message = format!("{}", e);
+                let error_message = format!("Failed to delete user credit: {}", e);
                 tracing::error!("{}", &error_message);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -198,14 +219,22 @@ pub async fn delete_user(
                 )
                     .into_response()
             }
-        }
-    } else {
-        (
+        },
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError::with_message(
                 "The specified user doesn't exist.",
             )),
         )
-            .into_response()
+            .into_response(),
+        Err(e) => {
+            let error_message = format!("{}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
@@ -1,257 +1,432 @@
-use crate::controller::credit_controller::USER_CREDITS;
+use crate::db::repository::Repository;
 use crate::model::app_state::AppState;
 use crate::model::claim::Claim;
-use crate::model::cosmos_db::CosmosDb;
+use crate::model::credit_transaction::CreditTransactionReason;
 use crate::model::errors::ServerError;
-use crate::model::lottery::{UserLottery, UserLotteryUpdateInfo};
-use crate::model::user_credit::{UserCredit, UserCreditUpdateInfo, UserCreditUpdateOpt};
-use crate::shared::util::{
-    add_document, add_document_into_collection, adjust_credit, adjust_credit_in_collection,
-    get_documents, query_document, query_document_within_collection,
-};
-use axum::extract::{Path, State};
+use crate::model::lottery::draw::{LotteryDrawDetails, NextLotteryDraw};
+use crate::model::lottery::{QuickPickInfo, QuickPickResult, UserLottery, UserLotteryUpdateInfo};
+use crate::model::page::PageRequest;
+use crate::model::user_credit::UserCredit;
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::{pay, update_credit, CreditChange, CreditUpdateError};
+use crate::shared::lottery;
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use azure_data_cosmos::prelude::{Param, Query};
-use std::ops::Add;
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
 use uuid::Uuid;
 
-const USER_LOTTERIES: &str = "UserLotteries";
+pub async fn get_all_lotteries(
+    _claim: Claim,
+    Query(page_request): Query<PageRequest>,
+    State(state): State<AppState>,
+) -> Response {
+    let result = if page_request.is_paged() {
+        state
+            .repository
+            .get_user_lotteries_page(&page_request)
+            .await
+            .map(IntoResponse::into_response)
+    } else {
+        state
+            .repository
+            .get_all_user_lotteries()
+            .await
+            .map(|lotteries| (StatusCode::OK, Json(lotteries)).into_response())
+    };
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum RewardType {
-    Daily,
-    Weekly,
+    match result {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to retrieve all users' lotteries: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(
+                    "Failed to retrieve all users' lotteries.",
+                )),
+            )
+                .into_response()
+        }
+    }
 }
 
-pub async fn get_daily_reward(
+pub async fn get_user_lotteries(
     _claim: Claim,
     Path(user_id): Path<String>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    get_reward(user_id, RewardType::Daily, cosmos_db).await
+    match state.repository.get_user_lottery(&user_id).await {
+        Ok(Some(user_lottery)) => (StatusCode::OK, Json(user_lottery)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The corresponding user's lottery info is not found.",
+            )),
+        )
+            .into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's lottery info: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
 }
 
-pub async fn get_weekly_reward(
+pub async fn draw_lottery(claim: Claim, State(state): State<AppState>) -> Response {
+    match lottery::draw_lottery(state.repository.as_ref(), &claim.sub).await {
+        Ok(lottery_draw) => (StatusCode::CREATED, Json(lottery_draw)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to draw the lottery: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError { error_message }),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_jackpot(_claim: Claim, State(state): State<AppState>) -> Response {
+    match lottery::get_jackpot(state.repository.as_ref()).await {
+        Ok(jackpot) => (StatusCode::OK, Json(jackpot)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve the jackpot pool: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_lottery_draws(
     _claim: Claim,
-    Path(user_id): Path<String>,
+    Query(page_request): Query<PageRequest>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    get_reward(user_id, RewardType::Weekly, cosmos_db).await
+    let result = if page_request.is_paged() {
+        state
+            .repository
+            .get_lottery_draws_page(&page_request)
+            .await
+            .map(IntoResponse::into_response)
+    } else {
+        state
+            .repository
+            .get_all_lottery_draws()
+            .await
+            .map(|lottery_draws| (StatusCode::OK, Json(lottery_draws)).into_response())
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to retrieve lottery draws: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(
+                    "Failed to retrieve lottery draws.",
+                )),
+            )
+                .into_response()
+        }
+    }
 }
 
-pub async fn get_all_lotteries(_claim: Claim, State(state): State<AppState>) -> Response {
-    let cosmos_db = state.cosmos_db;
-    if let Some(lotteries) =
-        get_documents::<UserLottery, _>(&cosmos_db.database, USER_LOTTERIES).await
-    {
-        (StatusCode::OK, Json(lotteries)).into_response()
-    } else {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ServerError::with_message(
-                "Failed to retrieve all users' lotteries.",
-            )),
+pub async fn get_lottery_draw(
+    _claim: Claim,
+    Path(draw_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    let repository = state.repository;
+    let lottery_draw = match repository.get_lottery_draw(&draw_id).await {
+        Ok(Some(lottery_draw)) => lottery_draw,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ServerError::with_message(
+                    "The specified lottery draw is not found.",
+                )),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            let error_message = format!("Failed to retrieve lottery draw: {}", e);
+            tracing::error!("{}", &error_message);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response();
+        }
+    };
+
+    match repository.get_lottery_draw_results(&draw_id).await {
+        Ok(results) => (
+            StatusCode::OK,
+            Json(LotteryDrawDetails {
+                draw: lottery_draw,
+                results,
+            }),
         )
-            .into_response()
+            .into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve lottery draw results: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
 
-pub async fn get_user_lotteries(
+pub async fn get_next_lottery_draw(_claim: Claim, State(state): State<AppState>) -> Response {
+    match lottery::get_lottery_commitment(state.repository.as_ref()).await {
+        Ok(commitment) => (StatusCode::OK, Json(NextLotteryDraw::from(commitment))).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve the next lottery draw: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn verify_lottery_draw(
     _claim: Claim,
-    Path(user_id): Path<String>,
+    Path(draw_id): Path<String>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_LOTTERIES
-        ),
-        vec![Param::new("@user_id".into(), user_id)],
-    );
-
-    if let Some(query_result) =
-        query_document::<UserLottery, _, _>(&cosmos_db.database, USER_LOTTERIES, query, true).await
-    {
-        let user_lottery = query_result.first().cloned().unwrap_or_default();
-        (StatusCode::OK, Json(user_lottery)).into_response()
-    } else {
-        (
+    match state.repository.get_lottery_draw(&draw_id).await {
+        Ok(Some(lottery_draw)) => match lottery::verify_lottery_draw(&lottery_draw) {
+            Some(verification) => (StatusCode::OK, Json(verification)).into_response(),
+            None => (
+                StatusCode::NOT_FOUND,
+                Json(ServerError::with_message(
+                    "The specified lottery draw wasn't drawn from a committed seed.",
+                )),
+            )
+                .into_response(),
+        },
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError::with_message(
-                "The corresponding user's lottery info is not found.",
+                "The specified lottery draw is not found.",
             )),
         )
-            .into_response()
+            .into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve lottery draw: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
 
-pub async fn add_lottery(
+pub async fn get_user_lottery_results(
     _claim: Claim,
     Path(user_id): Path<String>,
     State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_user_lottery_results(&user_id).await {
+        Ok(results) => (StatusCode::OK, Json(results)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's lottery results: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn add_lottery(
+    claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
     Json(mut payload): Json<UserLotteryUpdateInfo>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    if payload
-        .lotteries
-        .iter()
-        .any(|lottery| lottery.len() != 6 || lottery.iter().any(|n| *n < 1 || *n > 49))
+    let repository = state.repository;
+    let rules = &CONFIGURATION.lottery_rules;
+    if payload.lotteries.iter().any(|lottery| {
+        lottery.len() != rules.numbers_per_ticket as usize
+            || lottery.iter().any(|n| *n < 1 || *n > rules.max_number)
+    }) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ServerError::with_message(format!(
+                "Each lottery has to contain exactly {} numbers, each of which is between 1 and {}.",
+                rules.numbers_per_ticket, rules.max_number
+            ))),
+        )
+            .into_response();
+    }
+
+    for lottery in payload.lotteries.iter_mut() {
+        lottery.sort_unstable();
+    }
+
+    match purchase_lotteries(
+        repository.as_ref(),
+        &user_id,
+        &payload.username,
+        payload.lotteries,
+        claim.sub,
+    )
+    .await
     {
+        Ok(user_lottery) => (StatusCode::CREATED, Json(user_lottery)).into_response(),
+        Err(response) => response,
+    }
+}
+
+pub async fn add_quick_pick_lottery(
+    claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<QuickPickInfo>,
+) -> Response {
+    let max_quick_pick_count = CONFIGURATION.lottery_rules.max_quick_pick_count;
+    if payload.count < 1 || payload.count > max_quick_pick_count {
         return (
             StatusCode::BAD_REQUEST,
-            Json(ServerError::with_message(
-                "Each lottery has to contain exactly 6 numbers, each of which is between 1 and 49.",
-            )),
+            Json(ServerError::with_message(format!(
+                "The number of quick-pick lotteries has to be between 1 and {}.",
+                max_quick_pick_count
+            ))),
         )
             .into_response();
     }
 
-    let lottery_count = payload.lotteries.len();
-    let lottery_collection = cosmos_db.database.collection_client(USER_LOTTERIES);
-    let credit_collection = cosmos_db.database.collection_client(USER_CREDITS);
+    let tickets = (0..payload.count)
+        .map(|_| lottery::generate_numbers())
+        .collect::<Vec<_>>();
+
+    match purchase_lotteries(
+        state.repository.as_ref(),
+        &user_id,
+        &payload.username,
+        tickets.clone(),
+        claim.sub,
+    )
+    .await
+    {
+        Ok(user_lottery) => (
+            StatusCode::CREATED,
+            Json(QuickPickResult {
+                tickets,
+                user_lottery,
+            }),
+        )
+            .into_response(),
+        Err(response) => response,
+    }
+}
 
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_CREDITS
-        ),
-        vec![Param::new("@user_id".into(), user_id.clone())],
-    );
+/// Charges the user for the tickets and stores them. The purchase is a saga: credits are
+/// deducted first, together with the username update, and refunded if the tickets cannot be
+/// stored afterwards.
+async fn purchase_lotteries(
+    repository: &dyn Repository,
+    user_id: &str,
+    username: &str,
+    lotteries: Vec<Vec<u8>>,
+    actor: String,
+) -> Result<UserLottery, Response> {
+    let cost = CONFIGURATION.lottery_rules.ticket_price * lotteries.len() as i32;
 
-    let user_credit =
-        query_document_within_collection::<UserCredit, _>(&credit_collection, query, true)
-            .await
-            .and_then(|res| res.first().cloned());
+    let purchase = CreditChange::new(actor.clone(), CreditTransactionReason::LotteryPurchase);
+    let deduction = update_credit(repository, user_id, &purchase, |credit| {
+        if credit.credits < cost {
+            return Err(CreditUpdateError::InsufficientCredits);
+        }
 
-    match user_credit {
-        None => {
-            return (
+        Ok(UserCredit {
+            credits: credit.credits - cost,
+            username: username.to_string(),
+            ..credit
+        })
+    })
+    .await;
+
+    match deduction {
+        Ok(_) => {}
+        Err(CreditUpdateError::NotFound) => {
+            return Err((
                 StatusCode::NOT_FOUND,
                 Json(ServerError::with_message(
                     "The specified user's credit info is not found.",
                 )),
             )
-                .into_response();
-        }
-        Some(credit) => {
-            if credit.credits - (10 * lottery_count as i32) < 0 {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ServerError::with_message(
-                        "The specified user doesn't have enough credits.",
-                    )),
-                )
-                    .into_response();
-            }
-
-            if credit.username.as_str() != payload.username.as_str() {
-                let new_document = UserCredit {
-                    username: payload.username.clone(),
-                    ..credit
-                };
-
-                if let Err(e) = add_document_into_collection(&credit_collection, new_document).await
-                {
-                    tracing::error!(
-                        "Failed to update user's name during lottery purchase: {}",
-                        e
-                    );
-                }
-            }
+                .into_response());
         }
+        Err(e) => return Err(e.into_response()),
     }
 
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_LOTTERIES
-        ),
-        vec![Param::new("@user_id".into(), user_id.clone())],
-    );
-
-    for lottery in payload.lotteries.iter_mut() {
-        lottery.sort_unstable();
-    }
+    let new_document = UserLottery {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        lotteries,
+    };
 
-    match query_document_within_collection::<UserLottery, _>(&lottery_collection, query, true).await
-    {
-        None => {
-            let new_document = UserLottery {
-                id: Uuid::new_v4().to_string(),
-                user_id: user_id.clone(),
-                next_daily_time: OffsetDateTime::now_utc()
-                    .add(time::Duration::days(1))
-                    .format(&Rfc3339)
-                    .unwrap_or_default(),
-                next_weekly_time: OffsetDateTime::now_utc()
-                    .add(time::Duration::days(7))
-                    .format(&Rfc3339)
-                    .unwrap_or_default(),
-                lotteries: payload.lotteries,
-            };
+    match repository.add_user_lotteries(new_document).await {
+        Ok(user_lottery) => {
+            lottery::contribute_to_jackpot(repository, cost).await;
+            Ok(user_lottery)
+        }
+        Err(e) => {
+            tracing::error!("Failed to add a new lottery: {}", e);
+            let refund_result = pay(
+                repository,
+                user_id,
+                cost,
+                CreditTransactionReason::LotteryRefund,
+                &actor,
+            )
+            .await;
 
-            match add_document_into_collection(&lottery_collection, new_document.clone()).await {
-                Ok(_) => {
-                    adjust_credit_in_collection(
-                        &credit_collection,
-                        user_id.clone(),
-                        UserCreditUpdateInfo {
-                            credit: (10 * lottery_count) as i32,
-                        },
-                        UserCreditUpdateOpt::Minus,
-                    )
-                    .await;
-                    (StatusCode::CREATED, Json(new_document)).into_response()
-                }
-                Err(e) => {
-                    let error_message = format!("Failed to add a new lottery: {}", e);
-                    tracing::error!("{}", &error_message);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ServerError::with_message(error_message)),
+            let error_message = match refund_result {
+                Ok(_) => format!(
+                    "Failed to add a new lottery: {}. The credits have been refunded.",
+                    e
+                ),
+                Err(refund_error) => {
+                    tracing::error!(
+                        "Failed to refund {} credits to user {} after a failed lottery purchase: {:?}",
+                        cost,
+                        user_id,
+                        refund_error
+                    );
+                    format!(
+                        "Failed to add a new lottery: {}. The credits could not be refunded.",
+                        e
                     )
-                        .into_response()
                 }
-            }
-        }
-        Some(res) => {
-            let mut user_lottery = res.first().cloned().unwrap_or_default();
-            user_lottery.lotteries.append(&mut payload.lotteries);
-            let new_document = UserLottery {
-                lotteries: user_lottery.lotteries,
-                ..user_lottery
             };
 
-            match add_document_into_collection(&lottery_collection, new_document.clone()).await {
-                Ok(_) => {
-                    adjust_credit_in_collection(
-                        &credit_collection,
-                        user_id.clone(),
-                        UserCreditUpdateInfo {
-                            credit: (10 * lottery_count) as i32,
-                        },
-                        UserCreditUpdateOpt::Minus,
-                    )
-                    .await;
-                    (StatusCode::CREATED, Json(new_document)).into_response()
-                }
-                Err(e) => {
-                    let error_message = format!("Failed to add a new lottery: {}", e);
-                    tracing::error!("{}", &error_message);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ServerError::with_message(error_message)),
-                    )
-                        .into_response()
-                }
-            }
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response())
         }
     }
 }
@@ -261,35 +436,19 @@ pub async fn delete_lotteries(
     Path(user_id): Path<String>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_LOTTERIES
-        ),
-        vec![Param::new("@user_id".into(), user_id.clone())],
-    );
-
-    let query_result =
-        query_document::<UserLottery, _, _>(&cosmos_db.database, USER_LOTTERIES, query, true)
-            .await
-            .and_then(|v| v.first().cloned());
-
-    match query_result {
-        None => (
+    let repository = state.repository;
+    match repository.get_user_lottery(&user_id).await {
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError::with_message(
                 "The specified user is not found.",
             )),
         )
             .into_response(),
-        Some(user_lottery) => {
-            let new_document = UserLottery {
-                lotteries: vec![],
-                ..user_lottery
-            };
-
-            match add_document(&cosmos_db.database, USER_LOTTERIES, new_document).await {
+        Ok(Some(_)) => {
+            // Only the tickets that exist right now are removed, so a concurrent purchase
+            // isn't lost.
+            match repository.take_user_lotteries(&user_id).await {
                 Ok(_) => StatusCode::NO_CONTENT.into_response(),
                 Err(e) => {
                     let error_message = format!(
@@ -305,94 +464,14 @@ pub async fn delete_lotteries(
                 }
             }
         }
-    }
-}
-
-async fn get_reward(user_id: String, reward_type: RewardType, cosmos_db: CosmosDb) -> Response {
-    let query = Query::with_params(
-        format!(
-            "SELECT * FROM {} u WHERE u.user_id = @user_id",
-            USER_LOTTERIES
-        ),
-        vec![Param::new("@user_id".into(), user_id.clone())],
-    );
-
-    match query_document::<UserLottery, _, _>(&cosmos_db.database, "UserLotteries", query, true)
-        .await
-    {
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(ServerError::with_message(
-                "The specified user is not found.",
-            )),
-        )
-            .into_response(),
-        Some(result) => {
-            let user_lottery = result.first().cloned().unwrap_or_default();
-            let next_reward_time = match reward_type {
-                RewardType::Daily => user_lottery.next_daily_time.clone(),
-                RewardType::Weekly => user_lottery.next_weekly_time.clone(),
-            };
-            if OffsetDateTime::now_utc()
-                > OffsetDateTime::parse(&next_reward_time, &Rfc3339)
-                    .unwrap_or(OffsetDateTime::UNIX_EPOCH)
-            {
-                let response = update_credits(&user_lottery, reward_type, &cosmos_db).await;
-
-                let new_document = UserLottery {
-                    next_daily_time: if reward_type == RewardType::Daily {
-                        OffsetDateTime::now_utc()
-                            .add(time::Duration::days(1))
-                            .format(&Rfc3339)
-                            .unwrap_or_default()
-                    } else {
-                        user_lottery.next_daily_time.clone()
-                    },
-                    next_weekly_time: if reward_type == RewardType::Weekly {
-                        OffsetDateTime::now_utc()
-                            .add(time::Duration::days(7))
-                            .format(&Rfc3339)
-                            .unwrap_or_default()
-                    } else {
-                        user_lottery.next_weekly_time.clone()
-                    },
-                    ..user_lottery
-                };
-
-                match add_document(&cosmos_db.database, USER_LOTTERIES, new_document).await {
-                    Ok(_) => response,
-                    Err(e) => {
-                        let error_message = format!("Failed to update next reward time: {}", e);
-                        tracing::error!("{}", &error_message);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ServerError::with_message(error_message)),
-                        )
-                            .into_response()
-                    }
-                }
-            } else {
-                (StatusCode::ACCEPTED, Json(user_lottery)).into_response()
-            }
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's lottery info: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
         }
     }
 }
-
-async fn update_credits(
-    user_lottery: &UserLottery,
-    reward_type: RewardType,
-    cosmos_db: &CosmosDb,
-) -> Response {
-    adjust_credit(
-        &cosmos_db.database,
-        user_lottery.user_id.clone(),
-        UserCreditUpdateInfo {
-            credit: match reward_type {
-                RewardType::Daily => 10,
-                RewardType::Weekly => 70,
-            },
-        },
-        UserCreditUpdateOpt::Plus,
-    )
-    .await
-}
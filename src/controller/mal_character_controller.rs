@@ -1,29 +1,39 @@
 use crate::model::app_state::AppState;
 use crate::model::claim::Claim;
 use crate::model::errors::ServerError;
-use crate::model::mal_character::MalCharacter;
-use crate::shared::util::{add_document, query_document, query_document_within_collection};
+use crate::model::mal_character::{MalCharacter, RarityAssignment, RarityAssignmentResult};
+use crate::shared::{collection, roll};
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use azure_data_cosmos::prelude::{CollectionClient, Param, Query};
 use uuid::Uuid;
 
-pub const MAL_CHARACTERS: &str = "MalCharacters";
-
-pub async fn inner_get_all_mal_characters(collection: &CollectionClient) -> Vec<MalCharacter> {
-    let query = Query::new(format!("SELECT * FROM {} m", MAL_CHARACTERS));
-    query_document_within_collection::<MalCharacter, _>(collection, query, true)
+pub async fn get_all_mal_characters(_claim: Claim, State(state): State<AppState>) -> Response {
+    let query_result = state
+        .repository
+        .get_all_mal_characters()
         .await
-        .unwrap_or_default()
+        .map_err(|e| tracing::error!("Failed to retrieve mal characters: {}", e))
+        .unwrap_or_default();
+    (StatusCode::OK, Json(query_result)).into_response()
 }
 
-pub async fn get_all_mal_characters(_claim: Claim, State(state): State<AppState>) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let collection = cosmos_db.database.collection_client(MAL_CHARACTERS);
-    let query_result = inner_get_all_mal_characters(&collection).await;
-    (StatusCode::OK, Json(query_result)).into_response()
+pub async fn get_roll_rates(_claim: Claim, State(state): State<AppState>) -> Response {
+    match state.repository.get_all_mal_characters().await {
+        Ok(mal_characters) => {
+            (StatusCode::OK, Json(roll::get_roll_rates(&mal_characters))).into_response()
+        }
+        Err(e) => {
+            let error_message = format!("Failed to retrieve mal characters: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
 }
 
 pub async fn get_mal_character(
@@ -31,26 +41,50 @@ pub async fn get_mal_character(
     Path(id): Path<i32>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let query = Query::with_params(
-        "SELECT * FROM MalCharacters m WHERE m.Id = @id".into(),
-        vec![Param::new("@id".into(), id)],
-    );
-
-    let query_result =
-        query_document::<MalCharacter, _, _>(&cosmos_db.database, MAL_CHARACTERS, query, true)
-            .await
-            .and_then(|v| v.first().cloned());
+    match state.repository.get_mal_character(id).await {
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The specified mal character is not found.",
+            )),
+        )
+            .into_response(),
+        Ok(Some(mal_character)) => (StatusCode::OK, Json(mal_character)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve mal character: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
 
-    match query_result {
-        None => (
+pub async fn get_mal_character_owners(
+    _claim: Claim,
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Response {
+    match collection::get_character_owners(state.repository.as_ref(), id).await {
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError::with_message(
                 "The specified mal character is not found.",
             )),
         )
             .into_response(),
-        Some(mal_character) => (StatusCode::OK, Json(mal_character)).into_response(),
+        Ok(Some(owners)) => (StatusCode::OK, Json(owners)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve mal character owners: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
 
@@ -59,12 +93,11 @@ pub async fn post_mal_character(
     State(state): State<AppState>,
     Json(mut payload): Json<MalCharacter>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
     if payload.id.is_empty() {
         payload.id = Uuid::new_v4().to_string()
     }
 
-    match add_document(&cosmos_db.database, MAL_CHARACTERS, payload.clone()).await {
+    match state.repository.add_mal_character(payload.clone()).await {
         Ok(_) => (StatusCode::CREATED, Json(payload)).into_response(),
         Err(e) => {
             let error_message = format!("Failed to insert mal character into database: {}", e);
@@ -77,3 +110,33 @@ pub async fn post_mal_character(
         }
     }
 }
+
+pub async fn assign_rarities(
+    _claim: Claim,
+    State(state): State<AppState>,
+    Json(payload): Json<Vec<RarityAssignment>>,
+) -> Response {
+    match state.repository.set_mal_character_rarities(&payload).await {
+        Ok(updated) => {
+            let not_found = payload
+                .iter()
+                .map(|assignment| assignment.character_id)
+                .filter(|character_id| !updated.contains(character_id))
+                .collect();
+            (
+                StatusCode::OK,
+                Json(RarityAssignmentResult { updated, not_found }),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            let error_message = format!("Failed to assign mal character rarities: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
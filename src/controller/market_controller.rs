@@ -0,0 +1,112 @@
+use crate::model::app_state::AppState;
+use crate::model::claim::Claim;
+use crate::model::errors::ServerError;
+use crate::model::market::{BidRequest, ListingRequest, MarketActionRequest};
+use crate::shared::market;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+pub async fn get_market_listings(_claim: Claim, State(state): State<AppState>) -> Response {
+    match state.repository.get_active_market_listings().await {
+        Ok(listings) => (StatusCode::OK, Json(listings)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve market listings: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn post_market_listing(
+    _claim: Claim,
+    State(state): State<AppState>,
+    Json(payload): Json<ListingRequest>,
+) -> Response {
+    match market::create_listing(state.repository.as_ref(), payload).await {
+        Ok(listing) => (StatusCode::CREATED, Json(listing)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn get_market_listing(
+    _claim: Claim,
+    Path(listing_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_market_listing(&listing_id).await {
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The specified listing is not found.",
+            )),
+        )
+            .into_response(),
+        Ok(Some(listing)) => (StatusCode::OK, Json(listing)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve market listing: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn buy_market_listing(
+    claim: Claim,
+    Path(listing_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<MarketActionRequest>,
+) -> Response {
+    match market::buy(
+        state.repository.as_ref(),
+        &listing_id,
+        &payload.user_id,
+        &claim.sub,
+    )
+    .await
+    {
+        Ok(listing) => (StatusCode::OK, Json(listing)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn bid_on_market_listing(
+    claim: Claim,
+    Path(listing_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<BidRequest>,
+) -> Response {
+    match market::bid(
+        state.repository.as_ref(),
+        &listing_id,
+        &payload.user_id,
+        payload.amount,
+        &claim.sub,
+    )
+    .await
+    {
+        Ok(listing) => (StatusCode::OK, Json(listing)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn cancel_market_listing(
+    _claim: Claim,
+    Path(listing_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<MarketActionRequest>,
+) -> Response {
+    match market::cancel(state.repository.as_ref(), &listing_id, &payload.user_id).await {
+        Ok(listing) => (StatusCode::OK, Json(listing)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -1,6 +1,10 @@
+pub mod banner_controller;
 pub mod credit_controller;
 pub mod dialog_controller;
 pub mod login_controller;
 pub mod lottery_controller;
 pub mod mal_character_controller;
+pub mod market_controller;
+pub mod reward_controller;
 pub mod roll_controller;
+pub mod trade_controller;
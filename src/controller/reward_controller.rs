@@ -0,0 +1,143 @@
+use crate::db::repository::Repository;
+use crate::model::app_state::AppState;
+use crate::model::claim::Claim;
+use crate::model::errors::ServerError;
+use crate::model::rewards::{RewardClaimResult, RewardType, Rewards};
+use crate::model::user_credit::UserCredit;
+use crate::shared::credit::{update_credit, CreditChange};
+use crate::shared::reward;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use time::OffsetDateTime;
+
+pub async fn get_rewards(
+    _claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    let repository = state.repository.as_ref();
+    if let Err(response) = ensure_user_credit(repository, &user_id).await {
+        return response;
+    }
+
+    match repository.get_rewards(&user_id).await {
+        Ok(rewards) => {
+            let rewards = rewards.unwrap_or_else(|| Rewards::new(&user_id));
+            (
+                StatusCode::OK,
+                Json(reward::get_reward_status(
+                    &rewards,
+                    OffsetDateTime::now_utc(),
+                )),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's rewards: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_daily_reward(
+    claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    get_reward(
+        user_id,
+        RewardType::Daily,
+        state.repository.as_ref(),
+        claim.sub,
+    )
+    .await
+}
+
+pub async fn get_weekly_reward(
+    claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    get_reward(
+        user_id,
+        RewardType::Weekly,
+        state.repository.as_ref(),
+        claim.sub,
+    )
+    .await
+}
+
+async fn get_reward(
+    user_id: String,
+    reward_type: RewardType,
+    repository: &dyn Repository,
+    actor: String,
+) -> Response {
+    if let Err(response) = ensure_user_credit(repository, &user_id).await {
+        return response;
+    }
+
+    let claimed = match reward::claim_reward(repository, &user_id, reward_type).await {
+        Ok(claimed) => claimed,
+        Err(e) => return e.into_response(),
+    };
+
+    let streak_bonus = reward_type.get_streak_bonus(&claimed.rewards);
+    let amount = reward_type.get_credits() + streak_bonus;
+    let change = CreditChange::new(actor, reward_type.get_reason());
+    let payment = update_credit(repository, &user_id, &change, |credit| {
+        Ok(UserCredit {
+            credits: credit.credits + amount,
+            ..credit
+        })
+    })
+    .await;
+
+    match payment {
+        Ok(user_credit) => (
+            StatusCode::OK,
+            Json(RewardClaimResult {
+                user_credit,
+                amount,
+                streak_bonus,
+                daily_streak: claimed.rewards.daily_streak,
+                streak_expires_at: reward::get_streak_expiry(claimed.rewards.next_daily_time),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            reward::revert_reward_claim(repository, &user_id, reward_type, &claimed.previous).await;
+            e.into_response()
+        }
+    }
+}
+
+/// Rewards are only tracked for users who have a credit to pay them into.
+async fn ensure_user_credit(repository: &dyn Repository, user_id: &str) -> Result<(), Response> {
+    match repository.get_user_credit(user_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The specified user's credit info is not found.",
+            )),
+        )
+            .into_response()),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's credit info: {}", e);
+            tracing::error!("{}", &error_message);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response())
+        }
+    }
+}
@@ -1,32 +1,31 @@
-use crate::controller::mal_character_controller::{inner_get_all_mal_characters, MAL_CHARACTERS};
+use crate::db::repository::RepositoryError;
 use crate::model::app_state::AppState;
 use crate::model::claim::Claim;
-use crate::model::cosmos_db::CosmosDb;
+use crate::model::collection::DuplicateRequest;
 use crate::model::errors::ServerError;
-use crate::model::user_roll::{GetRollResult, UserRoll};
-use crate::shared::util::{add_document, get_documents, query_document_within_collection};
-use axum::extract::{Path, State};
+use crate::model::page::PageRequest;
+use crate::model::user_roll::{RollHistoryQuery, RollRequest, UserRoll};
+use crate::shared::{collection, roll};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use azure_data_cosmos::prelude::{CollectionClient, Param, Query};
-use uuid::Uuid;
-
-const USER_ROLLS: &str = "UserRolls";
 
 pub async fn post_user_roll(
     _claim: Claim,
-    _user_id: Path<String>,
+    Path(user_id): Path<String>,
     State(state): State<AppState>,
-    Json(mut payload): Json<UserRoll>,
+    Json(payload): Json<UserRoll>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    if payload.id.is_empty() {
-        payload.id = Uuid::new_v4().to_string();
-    }
-
-    match add_document(&cosmos_db.database, USER_ROLLS, payload.clone()).await {
-        Ok(_) => (StatusCode::CREATED, Json(payload)).into_response(),
+    match roll::import_roll(state.repository.as_ref(), &user_id, payload).await {
+        Ok(user_roll) => (StatusCode::CREATED, Json(user_roll)).into_response(),
+        Err(RepositoryError::Conflict) => (
+            StatusCode::CONFLICT,
+            Json(ServerError::with_message(
+                "The user's rolls were modified by another request. Please try again.",
+            )),
+        )
+            .into_response(),
         Err(e) => {
             let error_message = format!("Failed to insert user roll into database: {}", e);
             tracing::error!("{}", &error_message);
@@ -39,10 +38,44 @@ pub async fn post_user_roll(
     }
 }
 
-pub async fn get_all_rolls(_claim: Claim, State(state): State<AppState>) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let query_result = get_documents::<UserRoll, _>(&cosmos_db.database, USER_ROLLS)
+pub async fn roll_character(
+    claim: Claim,
+    Path(user_id): Path<String>,
+    Query(roll_request): Query<RollRequest>,
+    State(state): State<AppState>,
+) -> Response {
+    let banner_id = roll_request.banner_id.as_deref();
+    match roll::roll(state.repository.as_ref(), &user_id, &claim.sub, banner_id).await {
+        Ok(roll_result) => (StatusCode::CREATED, Json(roll_result)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn get_all_rolls(
+    _claim: Claim,
+    Query(page_request): Query<PageRequest>,
+    State(state): State<AppState>,
+) -> Response {
+    if page_request.is_paged() {
+        return match state.repository.get_user_rolls_page(&page_request).await {
+            Ok(page) => page.into_response(),
+            Err(e) => {
+                let error_message = format!("Failed to retrieve user rolls: {}", e);
+                tracing::error!("{}", &error_message);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ServerError::with_message(error_message)),
+                )
+                    .into_response()
+            }
+        };
+    }
+
+    let query_result = state
+        .repository
+        .get_all_user_rolls()
         .await
+        .map_err(|e| tracing::error!("Failed to retrieve all user rolls: {}", e))
         .unwrap_or_default();
     (StatusCode::OK, Json(query_result)).into_response()
 }
@@ -50,65 +83,140 @@ pub async fn get_all_rolls(_claim: Claim, State(state): State<AppState>) -> Resp
 pub async fn get_all_user_rolls(
     _claim: Claim,
     Path(user_id): Path<String>,
+    Query(page_request): Query<PageRequest>,
+    Query(history_query): Query<RollHistoryQuery>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let query_result = inner_get_all_user_rolls_with_names(user_id, cosmos_db).await;
-    (StatusCode::OK, Json(query_result)).into_response()
+    if let (Some(from), Some(to)) = (history_query.from, history_query.to) {
+        if from > to {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ServerError::with_message(
+                    "The start of the date range has to be before its end.",
+                )),
+            )
+                .into_response();
+        }
+    }
+    if page_request
+        .continuation
+        .as_deref()
+        .is_some_and(|continuation| continuation.parse::<i32>().is_err())
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ServerError::with_message(
+                "The continuation token is invalid.",
+            )),
+        )
+            .into_response();
+    }
+
+    let repository = state.repository.as_ref();
+    let result = if page_request.is_paged() {
+        roll::get_roll_history(repository, &user_id, &history_query, &page_request)
+            .await
+            .map(|page| page.into_response())
+    } else {
+        roll::get_roll_results(repository, &user_id, &history_query)
+            .await
+            .map(|roll_results| (StatusCode::OK, Json(roll_results)).into_response())
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user rolls: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
 }
 
-pub async fn get_user_roll_by_id(
+pub async fn get_roll_summary(
     _claim: Claim,
     Path(user_id): Path<String>,
-    Path(roll_id): Path<i32>,
     State(state): State<AppState>,
 ) -> Response {
-    let cosmos_db = state.cosmos_db;
-    let all_user_rolls_with_names = inner_get_all_user_rolls_with_names(user_id, cosmos_db).await;
-    let result = all_user_rolls_with_names
-        .into_iter()
-        .find(|res| res.user_roll.roll_id == roll_id);
+    match collection::get_roll_summary(state.repository.as_ref(), &user_id).await {
+        Ok(summary) => (StatusCode::OK, Json(summary)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's roll summary: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
 
-    match result {
-        Some(res) => (StatusCode::OK, Json(res)).into_response(),
-        None => (
+pub async fn get_collection(
+    _claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match collection::get_collection(state.repository.as_ref(), &user_id).await {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's collection: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn use_duplicate(
+    claim: Claim,
+    Path((user_id, roll_id)): Path<(String, i32)>,
+    State(state): State<AppState>,
+    Json(payload): Json<DuplicateRequest>,
+) -> Response {
+    match collection::use_duplicate(
+        state.repository.as_ref(),
+        &user_id,
+        roll_id,
+        payload.action,
+        &claim.sub,
+    )
+    .await
+    {
+        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn get_user_roll_by_id(
+    _claim: Claim,
+    Path((user_id, roll_id)): Path<(String, i32)>,
+    State(state): State<AppState>,
+) -> Response {
+    match roll::get_roll_result(state.repository.as_ref(), &user_id, roll_id).await {
+        Ok(Some(result)) => (StatusCode::OK, Json(result)).into_response(),
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(ServerError::with_message(
                 "Cannot find the specified roll within user's rolls.",
             )),
         )
             .into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user roll: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
     }
 }
-
-async fn inner_get_all_user_rolls_with_names(
-    user_id: String,
-    cosmos_db: CosmosDb,
-) -> Vec<GetRollResult> {
-    let roll_collection = cosmos_db.database.collection_client(USER_ROLLS);
-    let mal_character_collection = cosmos_db.database.collection_client(MAL_CHARACTERS);
-    let query_result = inner_get_all_user_rolls(&roll_collection, user_id).await;
-    let mal_characters = inner_get_all_mal_characters(&mal_character_collection).await;
-    query_result
-        .into_iter()
-        .map(|roll| GetRollResult {
-            user_roll: roll.clone(),
-            mal_character: mal_characters
-                .iter()
-                .find(|character| character.character_id == roll.mal_character_id)
-                .cloned()
-                .unwrap_or_default(),
-        })
-        .collect::<Vec<_>>()
-}
-
-async fn inner_get_all_user_rolls(collection: &CollectionClient, user_id: String) -> Vec<UserRoll> {
-    let query = Query::with_params(
-        "SELECT * FROM UserRolls u WHERE u.UserId = @user_id".into(),
-        vec![Param::new("@user_id".into(), user_id)],
-    );
-
-    query_document_within_collection::<UserRoll, _>(collection, query, true)
-        .await
-        .unwrap_or_default()
-}
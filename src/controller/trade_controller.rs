@@ -0,0 +1,114 @@
+use crate::model::app_state::AppState;
+use crate::model::claim::Claim;
+use crate::model::errors::ServerError;
+use crate::model::trade::{TradeOfferRequest, TradeResponseRequest};
+use crate::shared::trade;
+use crate::shared::trade::TradeResponse;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+pub async fn post_trade_offer(
+    _claim: Claim,
+    State(state): State<AppState>,
+    Json(payload): Json<TradeOfferRequest>,
+) -> Response {
+    match trade::create_trade_offer(state.repository.as_ref(), payload).await {
+        Ok(trade_offer) => (StatusCode::CREATED, Json(trade_offer)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn get_trade_offer(
+    _claim: Claim,
+    Path(offer_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.repository.get_trade_offer(&offer_id).await {
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::with_message(
+                "The specified trade offer is not found.",
+            )),
+        )
+            .into_response(),
+        Ok(Some(trade_offer)) => (StatusCode::OK, Json(trade_offer)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve trade offer: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_pending_trade_offers(
+    _claim: Claim,
+    Path(user_id): Path<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match trade::get_pending_trade_offers(state.repository.as_ref(), &user_id).await {
+        Ok(pending) => (StatusCode::OK, Json(pending)).into_response(),
+        Err(e) => {
+            let error_message = format!("Failed to retrieve user's trade offers: {}", e);
+            tracing::error!("{}", &error_message);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ServerError::with_message(error_message)),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn accept_trade_offer(
+    claim: Claim,
+    Path(offer_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<TradeResponseRequest>,
+) -> Response {
+    respond(claim, offer_id, state, payload, TradeResponse::Accept).await
+}
+
+pub async fn reject_trade_offer(
+    claim: Claim,
+    Path(offer_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<TradeResponseRequest>,
+) -> Response {
+    respond(claim, offer_id, state, payload, TradeResponse::Reject).await
+}
+
+pub async fn cancel_trade_offer(
+    claim: Claim,
+    Path(offer_id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<TradeResponseRequest>,
+) -> Response {
+    respond(claim, offer_id, state, payload, TradeResponse::Cancel).await
+}
+
+async fn respond(
+    claim: Claim,
+    offer_id: String,
+    state: AppState,
+    payload: TradeResponseRequest,
+    response: TradeResponse,
+) -> Response {
+    match trade::respond_to_trade_offer(
+        state.repository.as_ref(),
+        &offer_id,
+        &payload.user_id,
+        response,
+        &claim.sub,
+    )
+    .await
+    {
+        Ok(trade_offer) => (StatusCode::OK, Json(trade_offer)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,1298 @@
+use crate::db::mal_character_cache::MalCharacterCache;
+use crate::db::repository::{
+    BannerRepository, CollectionRepository, CreditRepository, JackpotRepository, LedgerRepository,
+    LotteryDrawRepository, LotteryRepository, MalCharacterRepository, MarketRepository,
+    RepositoryError, RepositoryResult, RewardRepository, RollRepository, TradeRepository,
+    Versioned,
+};
+use crate::model::banner::Banner;
+use crate::model::collection::CharacterProgress;
+use crate::model::cosmos_db::CosmosDb;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransaction};
+use crate::model::lottery::draw::{
+    LotteryCommitment, LotteryDraw, LotteryDrawResult, PENDING_COMMITMENT_ID,
+};
+use crate::model::lottery::jackpot::{LotteryJackpot, JACKPOT_ID};
+use crate::model::lottery::UserLottery;
+use crate::model::mal_character::{MalCharacter, RarityAssignment};
+use crate::model::market::{ListingStatus, MarketListing};
+use crate::model::page::{Page, PageRequest};
+use crate::model::rewards::Rewards;
+use crate::model::trade::TradeOffer;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RollHistoryQuery, RollPity, UserRoll};
+use crate::shared::util::{
+    add_document, add_document_into_collection, get_documents, get_documents_page, query_document,
+};
+use axum::async_trait;
+use azure_core::request_options::IfMatchCondition;
+use azure_data_cosmos::prelude::{Param, Query};
+use azure_data_cosmos::resources::document::DocumentAttributes;
+use futures::TryStreamExt;
+use serde::Deserialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+pub const USER_CREDITS: &str = "UserCredits";
+pub const CREDIT_TRANSACTIONS: &str = "CreditTransactions";
+pub const USER_LOTTERIES: &str = "UserLotteries";
+pub const LOTTERY_DRAWS: &str = "LotteryDraws";
+pub const LOTTERY_RESULTS: &str = "LotteryResults";
+pub const LOTTERY_COMMITMENTS: &str = "LotteryCommitments";
+pub const LOTTERY_JACKPOT: &str = "LotteryJackpot";
+pub const USER_REWARDS: &str = "UserRewards";
+pub const USER_ROLLS: &str = "UserRolls";
+pub const USER_ROLL_PITIES: &str = "UserRollPities";
+pub const CHARACTER_PROGRESSES: &str = "CharacterProgresses";
+pub const TRADE_OFFERS: &str = "TradeOffers";
+pub const MARKET_LISTINGS: &str = "MarketListings";
+pub const BANNERS: &str = "Banners";
+pub const MAL_CHARACTERS: &str = "MalCharacters";
+
+const MAX_CONDITIONAL_WRITE_ATTEMPTS: u32 = 5;
+
+#[derive(Clone)]
+pub struct CosmosRepository {
+    cosmos_db: CosmosDb,
+    mal_character_cache: MalCharacterCache,
+}
+
+impl CosmosRepository {
+    pub fn new(cosmos_db: CosmosDb, mal_character_cache: MalCharacterCache) -> Self {
+        CosmosRepository {
+            cosmos_db,
+            mal_character_cache,
+        }
+    }
+
+    async fn delete_lottery_results(&self, results: &[LotteryDrawResult]) {
+        let collection = self.cosmos_db.database.collection_client(LOTTERY_RESULTS);
+        for result in results {
+            let deleted = match collection.document_client(result.id.clone(), &result.user_id) {
+                Ok(document) => document.delete_document().into_future().await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = deleted {
+                tracing::error!(
+                    "Failed to remove result {} of an unfinished lottery draw: {}",
+                    &result.id,
+                    e
+                );
+            }
+        }
+    }
+
+    async fn delete_user_roll(&self, user_roll: &UserRoll) -> RepositoryResult<()> {
+        self.cosmos_db
+            .database
+            .collection_client(USER_ROLLS)
+            .document_client(user_roll.id.clone(), &user_roll.roll_id)?
+            .delete_document()
+            .into_future()
+            .await?;
+        Ok(())
+    }
+
+    /// Rolls are partitioned by their roll ID, which changes hands with the roll, so a roll can't be
+    /// moved in one write. Each roll is copied to its new owner before its previous copy is deleted,
+    /// and every roll moved so far is put back if any step fails, so a roll is never lost.
+    async fn move_user_rolls(&self, transfers: &[(UserRoll, UserRoll)]) -> RepositoryResult<()> {
+        for (previous, _) in transfers {
+            let current = self
+                .get_user_roll(&previous.user_id, previous.roll_id)
+                .await?;
+            let is_unchanged = current.is_some_and(|current| {
+                current.id == previous.id
+                    && current.mal_character_id == previous.mal_character_id
+                    && current.consumed == previous.consumed
+            });
+            if !is_unchanged {
+                return Err(RepositoryError::Conflict);
+            }
+        }
+
+        let collection = self.cosmos_db.database.collection_client(USER_ROLLS);
+        let mut moved = 0;
+        let mut result = Ok(());
+        for (previous, user_roll) in transfers {
+            if let Err(e) = collection
+                .create_document(user_roll.clone())
+                .into_future()
+                .await
+            {
+                result = Err(e.into());
+                break;
+            }
+            if let Err(e) = self.delete_user_roll(previous).await {
+                if let Err(e) = self.delete_user_roll(user_roll).await {
+                    tracing::error!(
+                        "Failed to remove the copy of roll {} given to {}: {}",
+                        &user_roll.id,
+                        &user_roll.user_id,
+                        e
+                    );
+                }
+                result = Err(e);
+                break;
+            }
+            moved += 1;
+        }
+
+        if result.is_err() {
+            self.restore_user_rolls(&transfers[..moved]).await;
+        }
+        result
+    }
+
+    /// Gives moved rolls back to their previous owners.
+    async fn restore_user_rolls(&self, transfers: &[(UserRoll, UserRoll)]) {
+        let collection = self.cosmos_db.database.collection_client(USER_ROLLS);
+        for (previous, user_roll) in transfers {
+            let restored = match add_document_into_collection(&collection, previous.clone()).await {
+                Ok(_) => self.delete_user_roll(user_roll).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = restored {
+                tracing::error!(
+                    "Failed to give roll {} back to {}: {}",
+                    &previous.id,
+                    &previous.user_id,
+                    e
+                );
+            }
+        }
+    }
+
+    async fn query_rewards(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<(Rewards, Option<DocumentAttributes>)>> {
+        let collection = self.cosmos_db.database.collection_client(USER_REWARDS);
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} r WHERE r.user_id = @user_id",
+                USER_REWARDS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        Ok(collection
+            .query_documents(query)
+            .query_cross_partition(true)
+            .into_stream::<Rewards>()
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .flat_map(|response| response.results)
+            .next())
+    }
+
+    /// Reward cooldowns used to be stored on the lottery documents, which drop them the next time
+    /// they are written. They are carried over into their own documents before the server starts
+    /// taking requests, and users who already have a rewards document keep it.
+    pub async fn migrate_legacy_reward_times(&self) -> RepositoryResult<()> {
+        let query = Query::new(format!(
+            "SELECT u.user_id, u.next_daily_time, u.next_weekly_time FROM {} u \
+            WHERE IS_DEFINED(u.next_daily_time)",
+            USER_LOTTERIES
+        ));
+        let legacy = query_document::<LegacyRewardTimes, _, _>(
+            &self.cosmos_db.database,
+            USER_LOTTERIES,
+            query,
+            true,
+        )
+        .await?;
+
+        let parse = |time: &str| {
+            OffsetDateTime::parse(time, &Rfc3339).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        };
+        for legacy in legacy {
+            let rewards = Rewards {
+                next_daily_time: parse(&legacy.next_daily_time),
+                next_weekly_time: parse(&legacy.next_weekly_time),
+                ..Rewards::new(&legacy.user_id)
+            };
+            match self.add_rewards(rewards).await {
+                Ok(_) | Err(RepositoryError::Conflict) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct LegacyRewardTimes {
+    user_id: String,
+    next_daily_time: String,
+    next_weekly_time: String,
+}
+
+/// The fields of a roll needed to count the user's rolls of each character.
+#[derive(Deserialize, Clone)]
+struct RollCountRow {
+    #[serde(rename = "MalCharacterId")]
+    mal_character_id: i32,
+    #[serde(rename = "Consumed", default)]
+    consumed: bool,
+}
+
+#[async_trait]
+impl CreditRepository for CosmosRepository {
+    async fn get_all_user_credits(&self) -> RepositoryResult<Vec<UserCredit>> {
+        Ok(get_documents::<UserCredit, _>(&self.cosmos_db.database, USER_CREDITS).await?)
+    }
+
+    async fn get_user_credits_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserCredit>> {
+        Ok(get_documents_page::<UserCredit, _>(
+            &self.cosmos_db.database,
+            USER_CREDITS,
+            page_request,
+        )
+        .await?)
+    }
+
+    async fn get_user_credit(&self, user_id: &str) -> RepositoryResult<Option<UserCredit>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.user_id = @user_id",
+                USER_CREDITS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let query_result =
+            query_document::<UserCredit, _, _>(&self.cosmos_db.database, USER_CREDITS, query, true)
+                .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_user_credit(&self, user_credit: UserCredit) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, USER_CREDITS, user_credit).await?;
+        Ok(())
+    }
+
+    async fn get_versioned_user_credit(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<UserCredit>>> {
+        let collection = self.cosmos_db.database.collection_client(USER_CREDITS);
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.user_id = @user_id",
+                USER_CREDITS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let responses = collection
+            .query_documents(query)
+            .query_cross_partition(true)
+            .into_stream::<UserCredit>()
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(responses
+            .into_iter()
+            .flat_map(|response| response.results)
+            .find_map(|(document, attributes)| {
+                attributes.map(|attributes| Versioned {
+                    document,
+                    version: attributes.etag().to_string(),
+                })
+            }))
+    }
+
+    async fn replace_user_credit(
+        &self,
+        user_credit: UserCredit,
+        version: &str,
+    ) -> RepositoryResult<()> {
+        let collection = self.cosmos_db.database.collection_client(USER_CREDITS);
+        collection
+            .create_document(user_credit)
+            .is_upsert(true)
+            .if_match_condition(IfMatchCondition::Match(version.to_string()))
+            .into_future()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user_credit(&self, user_credit: &UserCredit) -> RepositoryResult<()> {
+        let collection = self.cosmos_db.database.collection_client(USER_CREDITS);
+        collection
+            .document_client(user_credit.id.clone(), &user_credit.id)?
+            .delete_document()
+            .into_future()
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for CosmosRepository {
+    async fn add_credit_transaction(&self, transaction: CreditTransaction) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, CREDIT_TRANSACTIONS, transaction).await?;
+        Ok(())
+    }
+
+    async fn get_credit_transactions(
+        &self,
+        user_id: &str,
+        query: &CreditHistoryQuery,
+    ) -> RepositoryResult<Vec<CreditTransaction>> {
+        // `_ts` only has a precision of seconds, so the exact bounds are applied afterwards.
+        let from = query.from.map(|from| from.unix_timestamp()).unwrap_or(0);
+        let to = query
+            .to
+            .map(|to| to.unix_timestamp() + 1)
+            .unwrap_or(i64::MAX);
+        let cosmos_query = Query::with_params(
+            format!(
+                "SELECT * FROM {} t WHERE t.user_id = @user_id AND t._ts >= @from AND t._ts <= @to",
+                CREDIT_TRANSACTIONS
+            ),
+            vec![
+                Param::new("@user_id".into(), user_id.to_string()),
+                Param::new("@from".into(), from),
+                Param::new("@to".into(), to),
+            ],
+        );
+
+        let mut transactions = query_document::<CreditTransaction, _, _>(
+            &self.cosmos_db.database,
+            CREDIT_TRANSACTIONS,
+            cosmos_query,
+            true,
+        )
+        .await?;
+        transactions.retain(|transaction| query.contains(transaction.created_at));
+        transactions.sort_by_key(|transaction| transaction.created_at);
+        Ok(transactions)
+    }
+}
+
+#[async_trait]
+impl LotteryRepository for CosmosRepository {
+    async fn get_all_user_lotteries(&self) -> RepositoryResult<Vec<UserLottery>> {
+        Ok(get_documents::<UserLottery, _>(&self.cosmos_db.database, USER_LOTTERIES).await?)
+    }
+
+    async fn get_user_lotteries_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserLottery>> {
+        Ok(get_documents_page::<UserLottery, _>(
+            &self.cosmos_db.database,
+            USER_LOTTERIES,
+            page_request,
+        )
+        .await?)
+    }
+
+    async fn get_user_lottery(&self, user_id: &str) -> RepositoryResult<Option<UserLottery>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.user_id = @user_id",
+                USER_LOTTERIES
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let query_result = query_document::<UserLottery, _, _>(
+            &self.cosmos_db.database,
+            USER_LOTTERIES,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn add_user_lotteries(&self, user_lottery: UserLottery) -> RepositoryResult<UserLottery> {
+        let collection = self.cosmos_db.database.collection_client(USER_LOTTERIES);
+
+        for _ in 0..MAX_CONDITIONAL_WRITE_ATTEMPTS {
+            let query = Query::with_params(
+                format!(
+                    "SELECT * FROM {} u WHERE u.user_id = @user_id",
+                    USER_LOTTERIES
+                ),
+                vec![Param::new("@user_id".into(), user_lottery.user_id.clone())],
+            );
+            let existing = collection
+                .query_documents(query)
+                .query_cross_partition(true)
+                .into_stream::<UserLottery>()
+                .try_collect::<Vec<_>>()
+                .await?
+                .into_iter()
+                .flat_map(|response| response.results)
+                .next();
+
+            // Creating without upsert and replacing with an ETag both fail with a conflict
+            // when another purchase got there first, in which case the document is re-read.
+            let result = match existing {
+                None => collection
+                    .create_document(user_lottery.clone())
+                    .into_future()
+                    .await
+                    .map(|_| user_lottery.clone()),
+                Some((mut document, attributes)) => {
+                    document
+                        .lotteries
+                        .extend(user_lottery.lotteries.iter().cloned());
+                    let mut create_document =
+                        collection.create_document(document.clone()).is_upsert(true);
+                    if let Some(attributes) = attributes {
+                        create_document = create_document.if_match_condition(
+                            IfMatchCondition::Match(attributes.etag().to_string()),
+                        );
+                    }
+                    create_document.into_future().await.map(|_| document)
+                }
+            };
+
+            match result.map_err(RepositoryError::from) {
+                Err(RepositoryError::Conflict) => continue,
+                result => return result,
+            }
+        }
+
+        Err(RepositoryError::Conflict)
+    }
+
+    async fn take_user_lotteries(&self, user_id: &str) -> RepositoryResult<Vec<Vec<u8>>> {
+        let collection = self.cosmos_db.database.collection_client(USER_LOTTERIES);
+
+        for _ in 0..MAX_CONDITIONAL_WRITE_ATTEMPTS {
+            let query = Query::with_params(
+                format!(
+                    "SELECT * FROM {} u WHERE u.user_id = @user_id",
+                    USER_LOTTERIES
+                ),
+                vec![Param::new("@user_id".into(), user_id.to_string())],
+            );
+            let existing = collection
+                .query_documents(query)
+                .query_cross_partition(true)
+                .into_stream::<UserLottery>()
+                .try_collect::<Vec<_>>()
+                .await?
+                .into_iter()
+                .flat_map(|response| response.results)
+                .next();
+
+            let Some((mut document, attributes)) = existing else {
+                return Ok(vec![]);
+            };
+
+            let lotteries = std::mem::take(&mut document.lotteries);
+            if lotteries.is_empty() {
+                return Ok(lotteries);
+            }
+
+            // A purchase racing with the draw changes the ETag, so the tickets are re-read
+            // instead of dropping the ones that were just bought.
+            let mut create_document = collection.create_document(document).is_upsert(true);
+            if let Some(attributes) = attributes {
+                create_document = create_document
+                    .if_match_condition(IfMatchCondition::Match(attributes.etag().to_string()));
+            }
+
+            match create_document
+                .into_future()
+                .await
+                .map_err(RepositoryError::from)
+            {
+                Ok(_) => return Ok(lotteries),
+                Err(RepositoryError::Conflict) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(RepositoryError::Conflict)
+    }
+}
+
+#[async_trait]
+impl LotteryDrawRepository for CosmosRepository {
+    async fn get_all_lottery_draws(&self) -> RepositoryResult<Vec<LotteryDraw>> {
+        let mut lottery_draws =
+            get_documents::<LotteryDraw, _>(&self.cosmos_db.database, LOTTERY_DRAWS).await?;
+        lottery_draws.sort_by_key(|lottery_draw| lottery_draw.drawn_at);
+        Ok(lottery_draws)
+    }
+
+    async fn get_lottery_draws_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<LotteryDraw>> {
+        Ok(get_documents_page::<LotteryDraw, _>(
+            &self.cosmos_db.database,
+            LOTTERY_DRAWS,
+            page_request,
+        )
+        .await?)
+    }
+
+    async fn get_lottery_draw(&self, draw_id: &str) -> RepositoryResult<Option<LotteryDraw>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} d WHERE d.id = @id", LOTTERY_DRAWS),
+            vec![Param::new("@id".into(), draw_id.to_string())],
+        );
+
+        let query_result = query_document::<LotteryDraw, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_DRAWS,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn add_lottery_draw(
+        &self,
+        lottery_draw: LotteryDraw,
+        results: Vec<LotteryDrawResult>,
+    ) -> RepositoryResult<()> {
+        // Results are partitioned by user and can't share a transaction with the draw, so the draw
+        // is only written once all of its results are stored. The results written so far are
+        // removed if anything fails, and as result IDs are derived from the draw, a retry of the
+        // same draw overwrites any that couldn't be removed.
+        let collection = self.cosmos_db.database.collection_client(LOTTERY_RESULTS);
+        let mut written = Vec::with_capacity(results.len());
+        for result in results {
+            if let Err(e) = add_document_into_collection(&collection, result.clone()).await {
+                self.delete_lottery_results(&written).await;
+                return Err(e.into());
+            }
+            written.push(result);
+        }
+        if let Err(e) = add_document(&self.cosmos_db.database, LOTTERY_DRAWS, lottery_draw).await {
+            self.delete_lottery_results(&written).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn get_lottery_draw_results(
+        &self,
+        draw_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} r WHERE r.draw_id = @draw_id",
+                LOTTERY_RESULTS
+            ),
+            vec![Param::new("@draw_id".into(), draw_id.to_string())],
+        );
+
+        let mut results = query_document::<LotteryDrawResult, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_RESULTS,
+            query,
+            true,
+        )
+        .await?;
+        results.sort_by(|a, b| (&a.user_id, &a.id).cmp(&(&b.user_id, &b.id)));
+        Ok(results)
+    }
+
+    async fn get_user_lottery_results(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} r WHERE r.user_id = @user_id",
+                LOTTERY_RESULTS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let mut results = query_document::<LotteryDrawResult, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_RESULTS,
+            query,
+            true,
+        )
+        .await?;
+        results.sort_by(|a, b| (a.drawn_at, &a.id).cmp(&(b.drawn_at, &b.id)));
+        Ok(results)
+    }
+
+    async fn get_unpaid_lottery_results(&self) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        // Results archived before payments were tracked have no `paid` property and were paid.
+        let query = Query::new(format!(
+            "SELECT * FROM {} r WHERE r.paid = false",
+            LOTTERY_RESULTS
+        ));
+
+        Ok(query_document::<LotteryDrawResult, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_RESULTS,
+            query,
+            true,
+        )
+        .await?)
+    }
+
+    async fn set_lottery_results_paid(
+        &self,
+        results: Vec<LotteryDrawResult>,
+        paid: bool,
+    ) -> RepositoryResult<()> {
+        let collection = self.cosmos_db.database.collection_client(LOTTERY_RESULTS);
+        for result in results {
+            add_document_into_collection(&collection, LotteryDrawResult { paid, ..result }).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_lottery_commitment(&self) -> RepositoryResult<Option<LotteryCommitment>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} c WHERE c.id = @id", LOTTERY_COMMITMENTS),
+            vec![Param::new("@id".into(), PENDING_COMMITMENT_ID.to_string())],
+        );
+
+        let query_result = query_document::<LotteryCommitment, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_COMMITMENTS,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_lottery_commitment(
+        &self,
+        commitment: LotteryCommitment,
+    ) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, LOTTERY_COMMITMENTS, commitment).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JackpotRepository for CosmosRepository {
+    async fn get_jackpot(&self) -> RepositoryResult<Option<LotteryJackpot>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} j WHERE j.id = @id", LOTTERY_JACKPOT),
+            vec![Param::new("@id".into(), JACKPOT_ID.to_string())],
+        );
+
+        let query_result = query_document::<LotteryJackpot, _, _>(
+            &self.cosmos_db.database,
+            LOTTERY_JACKPOT,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn add_to_jackpot(&self, amount: i32, seed: i32) -> RepositoryResult<LotteryJackpot> {
+        let collection = self.cosmos_db.database.collection_client(LOTTERY_JACKPOT);
+
+        for _ in 0..MAX_CONDITIONAL_WRITE_ATTEMPTS {
+            let query = Query::with_params(
+                format!("SELECT * FROM {} j WHERE j.id = @id", LOTTERY_JACKPOT),
+                vec![Param::new("@id".into(), JACKPOT_ID.to_string())],
+            );
+            let existing = collection
+                .query_documents(query)
+                .query_cross_partition(true)
+                .into_stream::<LotteryJackpot>()
+                .try_collect::<Vec<_>>()
+                .await?
+                .into_iter()
+                .flat_map(|response| response.results)
+                .next();
+
+            let result = match existing {
+                None => {
+                    let jackpot = LotteryJackpot {
+                        id: JACKPOT_ID.to_string(),
+                        amount: seed + amount,
+                        updated_at: OffsetDateTime::now_utc(),
+                    };
+                    collection
+                        .create_document(jackpot.clone())
+                        .into_future()
+                        .await
+                        .map(|_| jackpot)
+                }
+                Some((jackpot, attributes)) => {
+                    let jackpot = LotteryJackpot {
+                        amount: jackpot.amount + amount,
+                        updated_at: OffsetDateTime::now_utc(),
+                        ..jackpot
+                    };
+                    let mut create_document =
+                        collection.create_document(jackpot.clone()).is_upsert(true);
+                    if let Some(attributes) = attributes {
+                        create_document = create_document.if_match_condition(
+                            IfMatchCondition::Match(attributes.etag().to_string()),
+                        );
+                    }
+                    create_document.into_future().await.map(|_| jackpot)
+                }
+            };
+
+            match result.map_err(RepositoryError::from) {
+                Err(RepositoryError::Conflict) => continue,
+                result => return result,
+            }
+        }
+
+        Err(RepositoryError::Conflict)
+    }
+}
+
+#[async_trait]
+impl RewardRepository for CosmosRepository {
+    async fn get_rewards(&self, user_id: &str) -> RepositoryResult<Option<Rewards>> {
+        Ok(self
+            .query_rewards(user_id)
+            .await?
+            .map(|(rewards, _)| rewards))
+    }
+
+    async fn get_versioned_rewards(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<Rewards>>> {
+        Ok(self
+            .query_rewards(user_id)
+            .await?
+            .and_then(|(document, attributes)| {
+                attributes.map(|attributes| Versioned {
+                    document,
+                    version: attributes.etag().to_string(),
+                })
+            }))
+    }
+
+    async fn add_rewards(&self, rewards: Rewards) -> RepositoryResult<()> {
+        let collection = self.cosmos_db.database.collection_client(USER_REWARDS);
+        collection.create_document(rewards).into_future().await?;
+        Ok(())
+    }
+
+    async fn replace_rewards(&self, rewards: Rewards, version: &str) -> RepositoryResult<()> {
+        let collection = self.cosmos_db.database.collection_client(USER_REWARDS);
+        collection
+            .create_document(rewards)
+            .is_upsert(true)
+            .if_match_condition(IfMatchCondition::Match(version.to_string()))
+            .into_future()
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RollRepository for CosmosRepository {
+    async fn get_all_user_rolls(&self) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(get_documents::<UserRoll, _>(&self.cosmos_db.database, USER_ROLLS).await?)
+    }
+
+    async fn get_user_rolls_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        Ok(
+            get_documents_page::<UserRoll, _>(&self.cosmos_db.database, USER_ROLLS, page_request)
+                .await?,
+        )
+    }
+
+    async fn get_user_rolls(&self, user_id: &str) -> RepositoryResult<Vec<UserRoll>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} u WHERE u.UserId = @user_id", USER_ROLLS),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        Ok(
+            query_document::<UserRoll, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?,
+        )
+    }
+
+    async fn get_user_roll(
+        &self,
+        user_id: &str,
+        roll_id: i32,
+    ) -> RepositoryResult<Option<UserRoll>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.UserId = @user_id AND u.Id = @roll_id",
+                USER_ROLLS
+            ),
+            vec![
+                Param::new("@user_id".into(), user_id.to_string()),
+                Param::new("@roll_id".into(), roll_id),
+            ],
+        );
+
+        let query_result =
+            query_document::<UserRoll, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn get_user_roll_history(
+        &self,
+        user_id: &str,
+        query: &RollHistoryQuery,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        let mut after = page_request
+            .continuation
+            .as_deref()
+            .and_then(|continuation| continuation.parse::<i32>().ok())
+            .unwrap_or(i32::MIN);
+        let page_size = page_request.page_size();
+        let batch_size = page_size as usize + 1;
+
+        // Creation times are stored as text, so the time range is applied here, fetching further
+        // batches until the page is full or the user's rolls run out.
+        let mut user_rolls = Vec::with_capacity(batch_size);
+        loop {
+            let cosmos_query = Query::with_params(
+                format!(
+                    "SELECT * FROM {} u WHERE u.UserId = @user_id AND u.Id > @after ORDER BY u.Id OFFSET 0 LIMIT @limit",
+                    USER_ROLLS
+                ),
+                vec![
+                    Param::new("@user_id".into(), user_id.to_string()),
+                    Param::new("@after".into(), after),
+                    Param::new("@limit".into(), batch_size),
+                ],
+            );
+            let batch = query_document::<UserRoll, _, _>(
+                &self.cosmos_db.database,
+                USER_ROLLS,
+                cosmos_query,
+                true,
+            )
+            .await?;
+
+            let is_last_batch = batch.len() < batch_size;
+            if let Some(last) = batch.last() {
+                after = last.roll_id;
+            }
+            user_rolls.extend(
+                batch
+                    .into_iter()
+                    .filter(|user_roll| query.contains(user_roll)),
+            );
+            if is_last_batch || user_rolls.len() >= batch_size {
+                break;
+            }
+        }
+        user_rolls.truncate(batch_size);
+        Ok(Page::from_overfetched(user_rolls, page_size, |item| {
+            item.roll_id.to_string()
+        }))
+    }
+
+    async fn get_user_character_rolls(
+        &self,
+        user_id: &str,
+        mal_character_ids: &[i32],
+    ) -> RepositoryResult<Vec<UserRoll>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.UserId = @user_id AND ARRAY_CONTAINS(@mal_character_ids, u.MalCharacterId)",
+                USER_ROLLS
+            ),
+            vec![
+                Param::new("@user_id".into(), user_id.to_string()),
+                Param::new("@mal_character_ids".into(), mal_character_ids.to_vec()),
+            ],
+        );
+
+        Ok(
+            query_document::<UserRoll, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?,
+        )
+    }
+
+    async fn get_max_roll_id(&self, user_id: &str) -> RepositoryResult<i32> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.UserId = @user_id ORDER BY u.Id DESC OFFSET 0 LIMIT 1",
+                USER_ROLLS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let query_result =
+            query_document::<UserRoll, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?;
+        Ok(query_result
+            .first()
+            .map(|user_roll| user_roll.roll_id)
+            .unwrap_or_default())
+    }
+
+    async fn get_character_roll_counts(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterRollCount>> {
+        // Aggregates across partitions need a query plan the SDK doesn't support, so only the
+        // fields needed for counting are fetched and counted here.
+        let query = Query::with_params(
+            format!(
+                "SELECT u.MalCharacterId, u.Consumed FROM {} u WHERE u.UserId = @user_id",
+                USER_ROLLS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        let query_result =
+            query_document::<RollCountRow, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?;
+        Ok(CharacterRollCount::count(
+            query_result
+                .into_iter()
+                .map(|row| (row.mal_character_id, row.consumed)),
+        ))
+    }
+
+    async fn get_character_rolls(&self, mal_character_id: i32) -> RepositoryResult<Vec<UserRoll>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} u WHERE u.MalCharacterId = @mal_character_id",
+                USER_ROLLS
+            ),
+            vec![Param::new("@mal_character_id".into(), mal_character_id)],
+        );
+
+        Ok(
+            query_document::<UserRoll, _, _>(&self.cosmos_db.database, USER_ROLLS, query, true)
+                .await?,
+        )
+    }
+
+    async fn add_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        // Roll documents are identified by their user and roll ID within the roll ID's partition,
+        // so creating a roll with a taken roll ID conflicts.
+        let collection = self.cosmos_db.database.collection_client(USER_ROLLS);
+        collection.create_document(user_roll).into_future().await?;
+        Ok(())
+    }
+
+    async fn update_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, USER_ROLLS, user_roll).await?;
+        Ok(())
+    }
+
+    async fn get_roll_pity(&self, user_id: &str) -> RepositoryResult<Option<RollPity>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} p WHERE p.id = @id", USER_ROLL_PITIES),
+            vec![Param::new("@id".into(), user_id.to_string())],
+        );
+
+        let query_result = query_document::<RollPity, _, _>(
+            &self.cosmos_db.database,
+            USER_ROLL_PITIES,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_roll_pity(&self, roll_pity: RollPity) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, USER_ROLL_PITIES, roll_pity).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CollectionRepository for CosmosRepository {
+    async fn get_character_progresses(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterProgress>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} c WHERE c.user_id = @user_id",
+                CHARACTER_PROGRESSES
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        Ok(query_document::<CharacterProgress, _, _>(
+            &self.cosmos_db.database,
+            CHARACTER_PROGRESSES,
+            query,
+            true,
+        )
+        .await?)
+    }
+
+    async fn get_character_progress(
+        &self,
+        user_id: &str,
+        mal_character_id: i32,
+    ) -> RepositoryResult<Option<CharacterProgress>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} c WHERE c.user_id = @user_id AND c.mal_character_id = @mal_character_id",
+                CHARACTER_PROGRESSES
+            ),
+            vec![
+                Param::new("@user_id".into(), user_id.to_string()),
+                Param::new("@mal_character_id".into(), mal_character_id),
+            ],
+        );
+
+        let query_result = query_document::<CharacterProgress, _, _>(
+            &self.cosmos_db.database,
+            CHARACTER_PROGRESSES,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_character_progress(&self, progress: CharacterProgress) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, CHARACTER_PROGRESSES, progress).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TradeRepository for CosmosRepository {
+    async fn get_trade_offer(&self, offer_id: &str) -> RepositoryResult<Option<TradeOffer>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} t WHERE t.id = @id", TRADE_OFFERS),
+            vec![Param::new("@id".into(), offer_id.to_string())],
+        );
+
+        let query_result =
+            query_document::<TradeOffer, _, _>(&self.cosmos_db.database, TRADE_OFFERS, query, true)
+                .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn get_trade_offers(&self, user_id: &str) -> RepositoryResult<Vec<TradeOffer>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} t WHERE t.from_user_id = @user_id OR t.to_user_id = @user_id",
+                TRADE_OFFERS
+            ),
+            vec![Param::new("@user_id".into(), user_id.to_string())],
+        );
+
+        Ok(
+            query_document::<TradeOffer, _, _>(&self.cosmos_db.database, TRADE_OFFERS, query, true)
+                .await?,
+        )
+    }
+
+    async fn upsert_trade_offer(&self, trade_offer: TradeOffer) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, TRADE_OFFERS, trade_offer).await?;
+        Ok(())
+    }
+
+    async fn complete_trade_offer(
+        &self,
+        trade_offer: TradeOffer,
+        transfers: Vec<(UserRoll, UserRoll)>,
+    ) -> RepositoryResult<()> {
+        self.move_user_rolls(&transfers).await?;
+        if let Err(e) = add_document(&self.cosmos_db.database, TRADE_OFFERS, trade_offer).await {
+            self.restore_user_rolls(&transfers).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MarketRepository for CosmosRepository {
+    async fn get_active_market_listings(&self) -> RepositoryResult<Vec<MarketListing>> {
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} m WHERE m.status = @status",
+                MARKET_LISTINGS
+            ),
+            vec![Param::new(
+                "@status".into(),
+                ListingStatus::Active.as_str().to_string(),
+            )],
+        );
+
+        Ok(query_document::<MarketListing, _, _>(
+            &self.cosmos_db.database,
+            MARKET_LISTINGS,
+            query,
+            true,
+        )
+        .await?)
+    }
+
+    async fn get_market_listing(
+        &self,
+        listing_id: &str,
+    ) -> RepositoryResult<Option<MarketListing>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} m WHERE m.id = @id", MARKET_LISTINGS),
+            vec![Param::new("@id".into(), listing_id.to_string())],
+        );
+
+        let query_result = query_document::<MarketListing, _, _>(
+            &self.cosmos_db.database,
+            MARKET_LISTINGS,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_market_listing(&self, listing: MarketListing) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, MARKET_LISTINGS, listing).await?;
+        Ok(())
+    }
+
+    async fn complete_market_listing(
+        &self,
+        listing: MarketListing,
+        transfer: (UserRoll, UserRoll),
+    ) -> RepositoryResult<()> {
+        let transfers = [transfer];
+        self.move_user_rolls(&transfers).await?;
+        if let Err(e) = add_document(&self.cosmos_db.database, MARKET_LISTINGS, listing).await {
+            self.restore_user_rolls(&transfers).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BannerRepository for CosmosRepository {
+    async fn get_banners(&self) -> RepositoryResult<Vec<Banner>> {
+        Ok(get_documents::<Banner, _>(&self.cosmos_db.database, BANNERS).await?)
+    }
+
+    async fn get_banner(&self, banner_id: &str) -> RepositoryResult<Option<Banner>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} b WHERE b.id = @id", BANNERS),
+            vec![Param::new("@id".into(), banner_id.to_string())],
+        );
+
+        let query_result =
+            query_document::<Banner, _, _>(&self.cosmos_db.database, BANNERS, query, true).await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn upsert_banner(&self, banner: Banner) -> RepositoryResult<()> {
+        add_document(&self.cosmos_db.database, BANNERS, banner).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MalCharacterRepository for CosmosRepository {
+    async fn get_all_mal_characters(&self) -> RepositoryResult<Vec<MalCharacter>> {
+        if let Some(mal_characters) = self.mal_character_cache.get_all() {
+            return Ok(mal_characters);
+        }
+
+        let query = Query::new(format!("SELECT * FROM {} m", MAL_CHARACTERS));
+        let mal_characters = query_document::<MalCharacter, _, _>(
+            &self.cosmos_db.database,
+            MAL_CHARACTERS,
+            query,
+            true,
+        )
+        .await?;
+        self.mal_character_cache.insert_all(&mal_characters);
+        Ok(mal_characters)
+    }
+
+    async fn get_mal_character(&self, character_id: i32) -> RepositoryResult<Option<MalCharacter>> {
+        let query = Query::with_params(
+            format!("SELECT * FROM {} m WHERE m.Id = @id", MAL_CHARACTERS),
+            vec![Param::new("@id".into(), character_id)],
+        );
+
+        let query_result = query_document::<MalCharacter, _, _>(
+            &self.cosmos_db.database,
+            MAL_CHARACTERS,
+            query,
+            true,
+        )
+        .await?;
+        Ok(query_result.into_iter().next())
+    }
+
+    async fn add_mal_character(&self, mal_character: MalCharacter) -> RepositoryResult<()> {
+        let character_id = mal_character.character_id;
+        add_document(&self.cosmos_db.database, MAL_CHARACTERS, mal_character).await?;
+        self.mal_character_cache.evict(&[character_id]);
+        Ok(())
+    }
+
+    async fn get_mal_characters(
+        &self,
+        character_ids: &[i32],
+    ) -> RepositoryResult<Vec<MalCharacter>> {
+        let (mut mal_characters, missing) = self.mal_character_cache.get(character_ids);
+        if missing.is_empty() {
+            return Ok(mal_characters);
+        }
+
+        let query = Query::with_params(
+            format!(
+                "SELECT * FROM {} m WHERE ARRAY_CONTAINS(@ids, m.Id)",
+                MAL_CHARACTERS
+            ),
+            vec![Param::new("@ids".into(), missing)],
+        );
+
+        let loaded = query_document::<MalCharacter, _, _>(
+            &self.cosmos_db.database,
+            MAL_CHARACTERS,
+            query,
+            true,
+        )
+        .await?;
+        self.mal_character_cache.insert(&loaded);
+        mal_characters.extend(loaded);
+        Ok(mal_characters)
+    }
+
+    async fn set_mal_character_rarities(
+        &self,
+        assignments: &[RarityAssignment],
+    ) -> RepositoryResult<Vec<i32>> {
+        let mut updated = Vec::new();
+        for assignment in assignments {
+            if let Some(mal_character) = self.get_mal_character(assignment.character_id).await? {
+                let mal_character = MalCharacter {
+                    rarity: assignment.rarity,
+                    ..mal_character
+                };
+                add_document(&self.cosmos_db.database, MAL_CHARACTERS, mal_character).await?;
+                self.mal_character_cache.evict(&[assignment.character_id]);
+                updated.push(assignment.character_id);
+            }
+        }
+        Ok(updated)
+    }
+}
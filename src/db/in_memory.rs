@@ -0,0 +1,763 @@
+use crate::db::repository::{
+    BannerRepository, CollectionRepository, CreditRepository, JackpotRepository, LedgerRepository,
+    LotteryDrawRepository, LotteryRepository, MalCharacterRepository, MarketRepository,
+    RepositoryError, RepositoryResult, RewardRepository, RollRepository, TradeRepository,
+    Versioned,
+};
+use crate::model::banner::Banner;
+use crate::model::collection::CharacterProgress;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransaction};
+use crate::model::lottery::draw::{
+    LotteryCommitment, LotteryDraw, LotteryDrawResult, PENDING_COMMITMENT_ID,
+};
+use crate::model::lottery::jackpot::{LotteryJackpot, JACKPOT_ID};
+use crate::model::lottery::UserLottery;
+use crate::model::mal_character::{MalCharacter, RarityAssignment};
+use crate::model::market::{ListingStatus, MarketListing};
+use crate::model::page::{Page, PageRequest};
+use crate::model::rewards::Rewards;
+use crate::model::trade::TradeOffer;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RollHistoryQuery, RollPity, UserRoll};
+use axum::async_trait;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// A storage backend that keeps everything in process memory.
+/// Nothing is persisted, so it is only meant for local development and tests.
+#[derive(Clone, Default)]
+pub struct InMemoryRepository {
+    /// Credits are stored alongside a version counter for conditional replacement.
+    user_credits: Arc<DashMap<String, (UserCredit, u64)>>,
+    credit_transactions: Arc<DashMap<String, Vec<CreditTransaction>>>,
+    user_lotteries: Arc<DashMap<String, UserLottery>>,
+    lottery_draws: Arc<DashMap<String, LotteryDraw>>,
+    lottery_results: Arc<DashMap<String, LotteryDrawResult>>,
+    lottery_commitments: Arc<DashMap<String, LotteryCommitment>>,
+    lottery_jackpot: Arc<DashMap<String, LotteryJackpot>>,
+    /// Rewards are stored alongside a version counter for conditional replacement.
+    rewards: Arc<DashMap<String, (Rewards, u64)>>,
+    user_rolls: Arc<DashMap<String, UserRoll>>,
+    roll_pities: Arc<DashMap<String, RollPity>>,
+    character_progresses: Arc<DashMap<String, CharacterProgress>>,
+    trade_offers: Arc<DashMap<String, TradeOffer>>,
+    market_listings: Arc<DashMap<String, MarketListing>>,
+    banners: Arc<DashMap<String, Banner>>,
+    mal_characters: Arc<DashMap<i32, MalCharacter>>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        InMemoryRepository::default()
+    }
+
+    /// Whether the roll's document ID or the user's roll ID is taken already.
+    fn has_user_roll(&self, user_roll: &UserRoll) -> bool {
+        self.user_rolls.contains_key(&user_roll.id)
+            || self.user_rolls.iter().any(|entry| {
+                let current = entry.value();
+                current.user_id == user_roll.user_id && current.roll_id == user_roll.roll_id
+            })
+    }
+
+    /// Hands the rolls over to their new owners, unless one of the new roll IDs is taken.
+    fn move_user_rolls(&self, transfers: Vec<(UserRoll, UserRoll)>) -> RepositoryResult<()> {
+        if transfers
+            .iter()
+            .any(|(_, user_roll)| self.has_user_roll(user_roll))
+        {
+            return Err(RepositoryError::Conflict);
+        }
+        for (previous, user_roll) in transfers {
+            self.user_rolls.remove(&previous.id);
+            self.user_rolls.insert(user_roll.id.clone(), user_roll);
+        }
+        Ok(())
+    }
+}
+
+/// Pages through items in document ID order, mirroring the keyset paging of the Postgres backend.
+fn get_page<T, I, F>(items: I, page_request: &PageRequest, id: F) -> Page<T>
+where
+    I: Iterator<Item = T>,
+    F: Fn(&T) -> String,
+{
+    let page_size = page_request.page_size();
+    let mut items = items
+        .filter(|item| match page_request.continuation.as_ref() {
+            Some(continuation) => id(item).as_str() > continuation.as_str(),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| id(item));
+    items.truncate(page_size as usize + 1);
+    Page::from_overfetched(items, page_size, id)
+}
+
+#[async_trait]
+impl CreditRepository for InMemoryRepository {
+    async fn get_all_user_credits(&self) -> RepositoryResult<Vec<UserCredit>> {
+        Ok(self
+            .user_credits
+            .iter()
+            .map(|entry| entry.value().0.clone())
+            .collect())
+    }
+
+    async fn get_user_credits_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserCredit>> {
+        let items = self
+            .user_credits
+            .iter()
+            .map(|entry| entry.value().0.clone());
+        Ok(get_page(items, page_request, |item| item.id.clone()))
+    }
+
+    async fn get_user_credit(&self, user_id: &str) -> RepositoryResult<Option<UserCredit>> {
+        Ok(self
+            .user_credits
+            .get(user_id)
+            .map(|entry| entry.value().0.clone()))
+    }
+
+    async fn upsert_user_credit(&self, user_credit: UserCredit) -> RepositoryResult<()> {
+        let mut entry = self
+            .user_credits
+            .entry(user_credit.user_id.clone())
+            .or_insert_with(|| (user_credit.clone(), 0));
+        *entry = (user_credit, entry.1 + 1);
+        Ok(())
+    }
+
+    async fn get_versioned_user_credit(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<UserCredit>>> {
+        Ok(self.user_credits.get(user_id).map(|entry| Versioned {
+            document: entry.value().0.clone(),
+            version: entry.value().1.to_string(),
+        }))
+    }
+
+    async fn replace_user_credit(
+        &self,
+        user_credit: UserCredit,
+        version: &str,
+    ) -> RepositoryResult<()> {
+        match self.user_credits.get_mut(&user_credit.user_id) {
+            Some(mut entry) if entry.1.to_string() == version => {
+                *entry = (user_credit, entry.1 + 1);
+                Ok(())
+            }
+            _ => Err(RepositoryError::Conflict),
+        }
+    }
+
+    async fn delete_user_credit(&self, user_credit: &UserCredit) -> RepositoryResult<()> {
+        self.user_credits.remove(&user_credit.user_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for InMemoryRepository {
+    async fn add_credit_transaction(&self, transaction: CreditTransaction) -> RepositoryResult<()> {
+        self.credit_transactions
+            .entry(transaction.user_id.clone())
+            .or_default()
+            .push(transaction);
+        Ok(())
+    }
+
+    async fn get_credit_transactions(
+        &self,
+        user_id: &str,
+        query: &CreditHistoryQuery,
+    ) -> RepositoryResult<Vec<CreditTransaction>> {
+        let mut transactions = self
+            .credit_transactions
+            .get(user_id)
+            .map(|entry| {
+                entry
+                    .value()
+                    .iter()
+                    .filter(|transaction| query.contains(transaction.created_at))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        transactions.sort_by_key(|transaction| transaction.created_at);
+        Ok(transactions)
+    }
+}
+
+#[async_trait]
+impl LotteryRepository for InMemoryRepository {
+    async fn get_all_user_lotteries(&self) -> RepositoryResult<Vec<UserLottery>> {
+        Ok(self
+            .user_lotteries
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_user_lotteries_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserLottery>> {
+        let items = self
+            .user_lotteries
+            .iter()
+            .map(|entry| entry.value().clone());
+        Ok(get_page(items, page_request, |item| item.id.clone()))
+    }
+
+    async fn get_user_lottery(&self, user_id: &str) -> RepositoryResult<Option<UserLottery>> {
+        Ok(self
+            .user_lotteries
+            .get(user_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn add_user_lotteries(&self, user_lottery: UserLottery) -> RepositoryResult<UserLottery> {
+        let mut entry = self
+            .user_lotteries
+            .entry(user_lottery.user_id.clone())
+            .or_insert_with(|| UserLottery {
+                lotteries: vec![],
+                ..user_lottery.clone()
+            });
+        entry.lotteries.extend(user_lottery.lotteries);
+        Ok(entry.clone())
+    }
+
+    async fn take_user_lotteries(&self, user_id: &str) -> RepositoryResult<Vec<Vec<u8>>> {
+        Ok(self
+            .user_lotteries
+            .get_mut(user_id)
+            .map(|mut entry| std::mem::take(&mut entry.lotteries))
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl LotteryDrawRepository for InMemoryRepository {
+    async fn get_all_lottery_draws(&self) -> RepositoryResult<Vec<LotteryDraw>> {
+        let mut lottery_draws = self
+            .lottery_draws
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+        lottery_draws.sort_by_key(|lottery_draw| lottery_draw.drawn_at);
+        Ok(lottery_draws)
+    }
+
+    async fn get_lottery_draws_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<LotteryDraw>> {
+        let items = self.lottery_draws.iter().map(|entry| entry.value().clone());
+        Ok(get_page(items, page_request, |item| item.id.clone()))
+    }
+
+    async fn get_lottery_draw(&self, draw_id: &str) -> RepositoryResult<Option<LotteryDraw>> {
+        Ok(self
+            .lottery_draws
+            .get(draw_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn add_lottery_draw(
+        &self,
+        lottery_draw: LotteryDraw,
+        results: Vec<LotteryDrawResult>,
+    ) -> RepositoryResult<()> {
+        for result in results {
+            self.lottery_results.insert(result.id.clone(), result);
+        }
+        self.lottery_draws
+            .insert(lottery_draw.id.clone(), lottery_draw);
+        Ok(())
+    }
+
+    async fn get_lottery_draw_results(
+        &self,
+        draw_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        let mut results = self
+            .lottery_results
+            .iter()
+            .filter(|entry| entry.value().draw_id.as_str() == draw_id)
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| (&a.user_id, &a.id).cmp(&(&b.user_id, &b.id)));
+        Ok(results)
+    }
+
+    async fn get_user_lottery_results(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        let mut results = self
+            .lottery_results
+            .iter()
+            .filter(|entry| entry.value().user_id.as_str() == user_id)
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| (a.drawn_at, &a.id).cmp(&(b.drawn_at, &b.id)));
+        Ok(results)
+    }
+
+    async fn get_unpaid_lottery_results(&self) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        Ok(self
+            .lottery_results
+            .iter()
+            .filter(|entry| !entry.value().paid)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn set_lottery_results_paid(
+        &self,
+        results: Vec<LotteryDrawResult>,
+        paid: bool,
+    ) -> RepositoryResult<()> {
+        for result in results {
+            if let Some(mut entry) = self.lottery_results.get_mut(&result.id) {
+                entry.value_mut().paid = paid;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_lottery_commitment(&self) -> RepositoryResult<Option<LotteryCommitment>> {
+        Ok(self
+            .lottery_commitments
+            .get(PENDING_COMMITMENT_ID)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn upsert_lottery_commitment(
+        &self,
+        commitment: LotteryCommitment,
+    ) -> RepositoryResult<()> {
+        self.lottery_commitments
+            .insert(commitment.id.clone(), commitment);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JackpotRepository for InMemoryRepository {
+    async fn get_jackpot(&self) -> RepositoryResult<Option<LotteryJackpot>> {
+        Ok(self
+            .lottery_jackpot
+            .get(JACKPOT_ID)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn add_to_jackpot(&self, amount: i32, seed: i32) -> RepositoryResult<LotteryJackpot> {
+        let mut entry = self
+            .lottery_jackpot
+            .entry(JACKPOT_ID.to_string())
+            .or_insert_with(|| LotteryJackpot {
+                id: JACKPOT_ID.to_string(),
+                amount: seed,
+                updated_at: OffsetDateTime::now_utc(),
+            });
+        entry.amount += amount;
+        entry.updated_at = OffsetDateTime::now_utc();
+        Ok(entry.clone())
+    }
+}
+
+#[async_trait]
+impl RewardRepository for InMemoryRepository {
+    async fn get_rewards(&self, user_id: &str) -> RepositoryResult<Option<Rewards>> {
+        Ok(self
+            .rewards
+            .get(user_id)
+            .map(|entry| entry.value().0.clone()))
+    }
+
+    async fn get_versioned_rewards(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<Rewards>>> {
+        Ok(self.rewards.get(user_id).map(|entry| Versioned {
+            document: entry.value().0.clone(),
+            version: entry.value().1.to_string(),
+        }))
+    }
+
+    async fn add_rewards(&self, rewards: Rewards) -> RepositoryResult<()> {
+        match self.rewards.entry(rewards.user_id.clone()) {
+            Entry::Occupied(_) => Err(RepositoryError::Conflict),
+            Entry::Vacant(entry) => {
+                entry.insert((rewards, 0));
+                Ok(())
+            }
+        }
+    }
+
+    async fn replace_rewards(&self, rewards: Rewards, version: &str) -> RepositoryResult<()> {
+        match self.rewards.get_mut(&rewards.user_id) {
+            Some(mut entry) if entry.1.to_string() == version => {
+                *entry = (rewards, entry.1 + 1);
+                Ok(())
+            }
+            _ => Err(RepositoryError::Conflict),
+        }
+    }
+}
+
+#[async_trait]
+impl RollRepository for InMemoryRepository {
+    async fn get_all_user_rolls(&self) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_user_rolls_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        let items = self.user_rolls.iter().map(|entry| entry.value().clone());
+        Ok(get_page(items, page_request, |item| item.id.clone()))
+    }
+
+    async fn get_user_rolls(&self, user_id: &str) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .filter(|entry| entry.value().user_id.as_str() == user_id)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_user_roll(
+        &self,
+        user_id: &str,
+        roll_id: i32,
+    ) -> RepositoryResult<Option<UserRoll>> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .find(|entry| {
+                entry.value().user_id.as_str() == user_id && entry.value().roll_id == roll_id
+            })
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn get_user_roll_history(
+        &self,
+        user_id: &str,
+        query: &RollHistoryQuery,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        let after = page_request
+            .continuation
+            .as_deref()
+            .and_then(|continuation| continuation.parse::<i32>().ok());
+        let mut user_rolls = self
+            .user_rolls
+            .iter()
+            .filter(|entry| {
+                let user_roll = entry.value();
+                user_roll.user_id.as_str() == user_id
+                    && after.map(|after| user_roll.roll_id > after).unwrap_or(true)
+                    && query.contains(user_roll)
+            })
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+        user_rolls.sort_by_key(|user_roll| user_roll.roll_id);
+        let page_size = page_request.page_size();
+        user_rolls.truncate(page_size as usize + 1);
+        Ok(Page::from_overfetched(user_rolls, page_size, |item| {
+            item.roll_id.to_string()
+        }))
+    }
+
+    async fn get_user_character_rolls(
+        &self,
+        user_id: &str,
+        mal_character_ids: &[i32],
+    ) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .filter(|entry| {
+                entry.value().user_id.as_str() == user_id
+                    && mal_character_ids.contains(&entry.value().mal_character_id)
+            })
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_max_roll_id(&self, user_id: &str) -> RepositoryResult<i32> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .filter(|entry| entry.value().user_id.as_str() == user_id)
+            .map(|entry| entry.value().roll_id)
+            .max()
+            .unwrap_or_default())
+    }
+
+    async fn get_character_roll_counts(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterRollCount>> {
+        Ok(CharacterRollCount::count(
+            self.user_rolls
+                .iter()
+                .filter(|entry| entry.value().user_id.as_str() == user_id)
+                .map(|entry| (entry.value().mal_character_id, entry.value().consumed)),
+        ))
+    }
+
+    async fn get_character_rolls(&self, mal_character_id: i32) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(self
+            .user_rolls
+            .iter()
+            .filter(|entry| entry.value().mal_character_id == mal_character_id)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn add_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        if self.has_user_roll(&user_roll) {
+            return Err(RepositoryError::Conflict);
+        }
+        self.user_rolls.insert(user_roll.id.clone(), user_roll);
+        Ok(())
+    }
+
+    async fn update_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        self.user_rolls.insert(user_roll.id.clone(), user_roll);
+        Ok(())
+    }
+
+    async fn get_roll_pity(&self, user_id: &str) -> RepositoryResult<Option<RollPity>> {
+        Ok(self
+            .roll_pities
+            .get(user_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn upsert_roll_pity(&self, roll_pity: RollPity) -> RepositoryResult<()> {
+        self.roll_pities
+            .insert(roll_pity.user_id.clone(), roll_pity);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CollectionRepository for InMemoryRepository {
+    async fn get_character_progresses(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterProgress>> {
+        Ok(self
+            .character_progresses
+            .iter()
+            .filter(|entry| entry.value().user_id.as_str() == user_id)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_character_progress(
+        &self,
+        user_id: &str,
+        mal_character_id: i32,
+    ) -> RepositoryResult<Option<CharacterProgress>> {
+        Ok(self
+            .character_progresses
+            .get(&CharacterProgress::new(user_id, mal_character_id).id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn upsert_character_progress(&self, progress: CharacterProgress) -> RepositoryResult<()> {
+        self.character_progresses
+            .insert(progress.id.clone(), progress);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TradeRepository for InMemoryRepository {
+    async fn get_trade_offer(&self, offer_id: &str) -> RepositoryResult<Option<TradeOffer>> {
+        Ok(self
+            .trade_offers
+            .get(offer_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn get_trade_offers(&self, user_id: &str) -> RepositoryResult<Vec<TradeOffer>> {
+        Ok(self
+            .trade_offers
+            .iter()
+            .filter(|entry| {
+                entry.value().from_user_id.as_str() == user_id
+                    || entry.value().to_user_id.as_str() == user_id
+            })
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn upsert_trade_offer(&self, trade_offer: TradeOffer) -> RepositoryResult<()> {
+        self.trade_offers
+            .insert(trade_offer.id.clone(), trade_offer);
+        Ok(())
+    }
+
+    async fn complete_trade_offer(
+        &self,
+        trade_offer: TradeOffer,
+        transfers: Vec<(UserRoll, UserRoll)>,
+    ) -> RepositoryResult<()> {
+        let unchanged = transfers.iter().all(|(previous, _)| {
+            self.user_rolls.get(&previous.id).is_some_and(|entry| {
+                let current = entry.value();
+                current.user_id == previous.user_id
+                    && current.roll_id == previous.roll_id
+                    && !current.consumed
+            })
+        });
+        if !unchanged {
+            return Err(RepositoryError::Conflict);
+        }
+
+        self.move_user_rolls(transfers)?;
+        self.trade_offers
+            .insert(trade_offer.id.clone(), trade_offer);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MarketRepository for InMemoryRepository {
+    async fn get_active_market_listings(&self) -> RepositoryResult<Vec<MarketListing>> {
+        Ok(self
+            .market_listings
+            .iter()
+            .filter(|entry| entry.value().status == ListingStatus::Active)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_market_listing(
+        &self,
+        listing_id: &str,
+    ) -> RepositoryResult<Option<MarketListing>> {
+        Ok(self
+            .market_listings
+            .get(listing_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn upsert_market_listing(&self, listing: MarketListing) -> RepositoryResult<()> {
+        self.market_listings.insert(listing.id.clone(), listing);
+        Ok(())
+    }
+
+    async fn complete_market_listing(
+        &self,
+        listing: MarketListing,
+        transfer: (UserRoll, UserRoll),
+    ) -> RepositoryResult<()> {
+        let (previous, _) = &transfer;
+        let unchanged = self.user_rolls.get(&previous.id).is_some_and(|entry| {
+            let current = entry.value();
+            current.user_id == previous.user_id
+                && current.roll_id == previous.roll_id
+                && !current.consumed
+        });
+        if !unchanged {
+            return Err(RepositoryError::Conflict);
+        }
+
+        self.move_user_rolls(vec![transfer])?;
+        self.market_listings.insert(listing.id.clone(), listing);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BannerRepository for InMemoryRepository {
+    async fn get_banners(&self) -> RepositoryResult<Vec<Banner>> {
+        Ok(self
+            .banners
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_banner(&self, banner_id: &str) -> RepositoryResult<Option<Banner>> {
+        Ok(self
+            .banners
+            .get(banner_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn upsert_banner(&self, banner: Banner) -> RepositoryResult<()> {
+        self.banners.insert(banner.id.clone(), banner);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MalCharacterRepository for InMemoryRepository {
+    async fn get_all_mal_characters(&self) -> RepositoryResult<Vec<MalCharacter>> {
+        Ok(self
+            .mal_characters
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn get_mal_character(&self, character_id: i32) -> RepositoryResult<Option<MalCharacter>> {
+        Ok(self
+            .mal_characters
+            .get(&character_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    async fn get_mal_characters(
+        &self,
+        character_ids: &[i32],
+    ) -> RepositoryResult<Vec<MalCharacter>> {
+        Ok(character_ids
+            .iter()
+            .filter_map(|character_id| self.mal_characters.get(character_id))
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn add_mal_character(&self, mal_character: MalCharacter) -> RepositoryResult<()> {
+        self.mal_characters
+            .insert(mal_character.character_id, mal_character);
+        Ok(())
+    }
+
+    async fn set_mal_character_rarities(
+        &self,
+        assignments: &[RarityAssignment],
+    ) -> RepositoryResult<Vec<i32>> {
+        let mut updated = Vec::new();
+        for assignment in assignments {
+            if let Some(mut mal_character) = self.mal_characters.get_mut(&assignment.character_id) {
+                mal_character.rarity = assignment.rarity;
+                updated.push(assignment.character_id);
+            }
+        }
+        Ok(updated)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::model::mal_character::MalCharacter;
+use dashmap::DashMap;
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Characters rarely change once they are added, so repositories keep the ones they load in memory
+/// for a while instead of loading them for every roll. Characters written through the repository
+/// are evicted right away, while changes made directly in the database show up once the cached
+/// copies expire.
+#[derive(Clone)]
+pub struct MalCharacterCache {
+    time_to_live: Duration,
+    characters: Arc<DashMap<i32, Cached<MalCharacter>>>,
+    /// Every character at once, as rolls need them.
+    all_characters: Arc<RwLock<Option<Cached<Vec<MalCharacter>>>>>,
+}
+
+struct Cached<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+impl<T: Clone> Cached<T> {
+    fn get(&self) -> Option<T> {
+        (self.expires_at > Instant::now()).then(|| self.value.clone())
+    }
+}
+
+impl MalCharacterCache {
+    pub fn new(time_to_live: Duration) -> Self {
+        MalCharacterCache {
+            time_to_live,
+            characters: Arc::new(DashMap::new()),
+            all_characters: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// The cached characters with the given IDs, along with the IDs that have to be loaded.
+    pub fn get(&self, character_ids: &[i32]) -> (Vec<MalCharacter>, Vec<i32>) {
+        let mut mal_characters = Vec::new();
+        let mut missing = Vec::new();
+        for character_id in character_ids.iter().copied().collect::<BTreeSet<_>>() {
+            let cached = self
+                .characters
+                .get(&character_id)
+                .and_then(|entry| entry.value().get());
+            match cached {
+                Some(mal_character) => mal_characters.push(mal_character),
+                None => missing.push(character_id),
+            }
+        }
+        (mal_characters, missing)
+    }
+
+    pub fn insert(&self, mal_characters: &[MalCharacter]) {
+        let expires_at = Instant::now() + self.time_to_live;
+        for mal_character in mal_characters {
+            self.characters.insert(
+                mal_character.character_id,
+                Cached {
+                    value: mal_character.clone(),
+                    expires_at,
+                },
+            );
+        }
+    }
+
+    pub fn get_all(&self) -> Option<Vec<MalCharacter>> {
+        let all_characters = self.all_characters.read().ok()?;
+        all_characters.as_ref().and_then(Cached::get)
+    }
+
+    pub fn insert_all(&self, mal_characters: &[MalCharacter]) {
+        self.insert(mal_characters);
+        if let Ok(mut all_characters) = self.all_characters.write() {
+            *all_characters = Some(Cached {
+                value: mal_characters.to_vec(),
+                expires_at: Instant::now() + self.time_to_live,
+            });
+        }
+    }
+
+    /// Drops the given characters, along with the list of every character, which includes them.
+    pub fn evict(&self, character_ids: &[i32]) {
+        for character_id in character_ids {
+            self.characters.remove(character_id);
+        }
+        if let Ok(mut all_characters) = self.all_characters.write() {
+            *all_characters = None;
+        }
+    }
+}
@@ -1,10 +1,46 @@
-#![allow(dead_code)]
+use crate::db::cosmos::CosmosRepository;
+use crate::db::in_memory::InMemoryRepository;
+use crate::db::mal_character_cache::MalCharacterCache;
+use crate::db::postgres::PostgresRepository;
+use crate::db::repository::Repository;
+use crate::model::configuration::StorageBackend;
 use crate::shared::configuration::CONFIGURATION;
+use crate::shared::util::initialize_clients;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod cosmos;
+pub mod in_memory;
+pub mod mal_character_cache;
+pub mod postgres;
+pub mod repository;
 
 pub fn initialize_db() -> anyhow::Result<Pool<Postgres>> {
     let connection_string = &CONFIGURATION.database_url;
     let pool = PgPoolOptions::new().connect_lazy(connection_string)?;
     Ok(pool)
 }
+
+pub async fn initialize_repository() -> anyhow::Result<Arc<dyn Repository>> {
+    let mal_character_cache = MalCharacterCache::new(Duration::from_secs(
+        CONFIGURATION.roll_rules.character_cache_seconds,
+    ));
+    match CONFIGURATION.storage_backend {
+        StorageBackend::Cosmos => {
+            let repository = CosmosRepository::new(initialize_clients(), mal_character_cache);
+            repository.migrate_legacy_reward_times().await?;
+            Ok(Arc::new(repository))
+        }
+        StorageBackend::Postgres => {
+            let pool = initialize_db()?;
+            sqlx::migrate!().run(&pool).await?;
+            Ok(Arc::new(PostgresRepository::new(pool, mal_character_cache)))
+        }
+        StorageBackend::InMemory => {
+            tracing::warn!("Using the in-memory storage backend. Nothing will be persisted.");
+            Ok(Arc::new(InMemoryRepository::new()))
+        }
+    }
+}
@@ -0,0 +1,1371 @@
+use crate::db::mal_character_cache::MalCharacterCache;
+use crate::db::repository::{
+    BannerRepository, CollectionRepository, CreditRepository, JackpotRepository, LedgerRepository,
+    LotteryDrawRepository, LotteryRepository, MalCharacterRepository, MarketRepository,
+    RepositoryError, RepositoryResult, RewardRepository, RollRepository, TradeRepository,
+    Versioned,
+};
+use crate::model::banner::Banner;
+use crate::model::collection::CharacterProgress;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransaction};
+use crate::model::lottery::draw::{
+    LotteryCommitment, LotteryDraw, LotteryDrawResult, PENDING_COMMITMENT_ID,
+};
+use crate::model::lottery::jackpot::{LotteryJackpot, JACKPOT_ID};
+use crate::model::lottery::UserLottery;
+use crate::model::mal_character::{MalCharacter, RarityAssignment};
+use crate::model::market::{ListingStatus, MarketListing};
+use crate::model::page::{Page, PageRequest};
+use crate::model::rewards::Rewards;
+use crate::model::trade::TradeOffer;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RollHistoryQuery, RollPity, UserRoll};
+use axum::async_trait;
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres, Row};
+
+#[derive(Clone)]
+pub struct PostgresRepository {
+    pool: Pool<Postgres>,
+    mal_character_cache: MalCharacterCache,
+}
+
+impl PostgresRepository {
+    pub fn new(pool: Pool<Postgres>, mal_character_cache: MalCharacterCache) -> Self {
+        PostgresRepository {
+            pool,
+            mal_character_cache,
+        }
+    }
+}
+
+#[async_trait]
+impl CreditRepository for PostgresRepository {
+    async fn get_all_user_credits(&self) -> RepositoryResult<Vec<UserCredit>> {
+        Ok(
+            sqlx::query_as::<_, UserCredit>("SELECT * FROM user_credits")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_user_credits_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserCredit>> {
+        let page_size = page_request.page_size();
+        let items = sqlx::query_as::<_, UserCredit>(
+            "SELECT * FROM user_credits WHERE ($1::TEXT IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(&page_request.continuation)
+        .bind(page_size as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Page::from_overfetched(items, page_size, |item| {
+            item.id.clone()
+        }))
+    }
+
+    async fn get_user_credit(&self, user_id: &str) -> RepositoryResult<Option<UserCredit>> {
+        Ok(
+            sqlx::query_as::<_, UserCredit>("SELECT * FROM user_credits WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn upsert_user_credit(&self, user_credit: UserCredit) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO user_credits (id, username, user_id, credits)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE
+            SET username = EXCLUDED.username, user_id = EXCLUDED.user_id, credits = EXCLUDED.credits,
+                version = user_credits.version + 1"#,
+        )
+        .bind(&user_credit.id)
+        .bind(&user_credit.username)
+        .bind(&user_credit.user_id)
+        .bind(user_credit.credits)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_versioned_user_credit(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<UserCredit>>> {
+        let row = sqlx::query(
+            "SELECT *, version::TEXT AS version_tag FROM user_credits WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(Versioned {
+                document: UserCredit::from_row(&row)?,
+                version: row.try_get("version_tag")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn replace_user_credit(
+        &self,
+        user_credit: UserCredit,
+        version: &str,
+    ) -> RepositoryResult<()> {
+        let result = sqlx::query(
+            r#"UPDATE user_credits
+            SET username = $2, user_id = $3, credits = $4, version = version + 1
+            WHERE id = $1 AND version::TEXT = $5"#,
+        )
+        .bind(&user_credit.id)
+        .bind(&user_credit.username)
+        .bind(&user_credit.user_id)
+        .bind(user_credit.credits)
+        .bind(version)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            Err(RepositoryError::Conflict)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn delete_user_credit(&self, user_credit: &UserCredit) -> RepositoryResult<()> {
+        sqlx::query("DELETE FROM user_credits WHERE id = $1")
+            .bind(&user_credit.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for PostgresRepository {
+    async fn add_credit_transaction(&self, transaction: CreditTransaction) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO credit_transactions (id, user_id, amount, reason, balance_after, created_at, actor)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+        )
+        .bind(&transaction.id)
+        .bind(&transaction.user_id)
+        .bind(transaction.amount)
+        .bind(transaction.reason.as_str())
+        .bind(transaction.balance_after)
+        .bind(transaction.created_at)
+        .bind(&transaction.actor)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_credit_transactions(
+        &self,
+        user_id: &str,
+        query: &CreditHistoryQuery,
+    ) -> RepositoryResult<Vec<CreditTransaction>> {
+        Ok(sqlx::query_as::<_, CreditTransaction>(
+            r#"SELECT * FROM credit_transactions
+            WHERE user_id = $1
+              AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR created_at <= $3)
+            ORDER BY created_at"#,
+        )
+        .bind(user_id)
+        .bind(query.from)
+        .bind(query.to)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[async_trait]
+impl LotteryRepository for PostgresRepository {
+    async fn get_all_user_lotteries(&self) -> RepositoryResult<Vec<UserLottery>> {
+        Ok(
+            sqlx::query_as::<_, UserLottery>("SELECT * FROM user_lotteries")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_user_lotteries_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserLottery>> {
+        let page_size = page_request.page_size();
+        let items = sqlx::query_as::<_, UserLottery>(
+            "SELECT * FROM user_lotteries WHERE ($1::TEXT IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(&page_request.continuation)
+        .bind(page_size as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Page::from_overfetched(items, page_size, |item| {
+            item.id.clone()
+        }))
+    }
+
+    async fn get_user_lottery(&self, user_id: &str) -> RepositoryResult<Option<UserLottery>> {
+        Ok(
+            sqlx::query_as::<_, UserLottery>("SELECT * FROM user_lotteries WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn add_user_lotteries(&self, user_lottery: UserLottery) -> RepositoryResult<UserLottery> {
+        Ok(sqlx::query_as::<_, UserLottery>(
+            r#"INSERT INTO user_lotteries (id, user_id, lotteries)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE
+            SET lotteries = user_lotteries.lotteries || EXCLUDED.lotteries
+            RETURNING *"#,
+        )
+        .bind(&user_lottery.id)
+        .bind(&user_lottery.user_id)
+        .bind(Json(&user_lottery.lotteries))
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn take_user_lotteries(&self, user_id: &str) -> RepositoryResult<Vec<Vec<u8>>> {
+        let lotteries = sqlx::query_scalar::<_, Json<Vec<Vec<u8>>>>(
+            r#"UPDATE user_lotteries u SET lotteries = '[]'::JSONB
+            FROM (SELECT id, lotteries FROM user_lotteries WHERE user_id = $1 FOR UPDATE) previous
+            WHERE u.id = previous.id
+            RETURNING previous.lotteries"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(lotteries.map(|lotteries| lotteries.0).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl LotteryDrawRepository for PostgresRepository {
+    async fn get_all_lottery_draws(&self) -> RepositoryResult<Vec<LotteryDraw>> {
+        Ok(
+            sqlx::query_as::<_, LotteryDraw>("SELECT * FROM lottery_draws ORDER BY drawn_at")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_lottery_draws_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<LotteryDraw>> {
+        let page_size = page_request.page_size();
+        let items = sqlx::query_as::<_, LotteryDraw>(
+            "SELECT * FROM lottery_draws WHERE ($1::TEXT IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(&page_request.continuation)
+        .bind(page_size as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Page::from_overfetched(items, page_size, |item| {
+            item.id.clone()
+        }))
+    }
+
+    async fn get_lottery_draw(&self, draw_id: &str) -> RepositoryResult<Option<LotteryDraw>> {
+        Ok(
+            sqlx::query_as::<_, LotteryDraw>("SELECT * FROM lottery_draws WHERE id = $1")
+                .bind(draw_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn add_lottery_draw(
+        &self,
+        lottery_draw: LotteryDraw,
+        results: Vec<LotteryDrawResult>,
+    ) -> RepositoryResult<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            r#"INSERT INTO lottery_draws (id, winning_numbers, drawn_at, actor, total_tickets, tiers, jackpot,
+                seed_hash, seed, committed_at, max_number)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#,
+        )
+        .bind(&lottery_draw.id)
+        .bind(Json(&lottery_draw.winning_numbers))
+        .bind(lottery_draw.drawn_at)
+        .bind(&lottery_draw.actor)
+        .bind(lottery_draw.total_tickets)
+        .bind(Json(&lottery_draw.tiers))
+        .bind(lottery_draw.jackpot)
+        .bind(&lottery_draw.seed_hash)
+        .bind(&lottery_draw.seed)
+        .bind(lottery_draw.committed_at)
+        .bind(lottery_draw.max_number as i16)
+        .execute(&mut *transaction)
+        .await?;
+
+        for result in results.iter() {
+            sqlx::query(
+                r#"INSERT INTO lottery_results (id, draw_id, user_id, ticket, matches, prize, drawn_at, paid)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            )
+            .bind(&result.id)
+            .bind(&result.draw_id)
+            .bind(&result.user_id)
+            .bind(Json(&result.ticket))
+            .bind(result.matches as i16)
+            .bind(result.prize)
+            .bind(result.drawn_at)
+            .bind(result.paid)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn get_lottery_draw_results(
+        &self,
+        draw_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        Ok(sqlx::query_as::<_, LotteryDrawResult>(
+            "SELECT * FROM lottery_results WHERE draw_id = $1 ORDER BY user_id, id",
+        )
+        .bind(draw_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_user_lottery_results(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        Ok(sqlx::query_as::<_, LotteryDrawResult>(
+            "SELECT * FROM lottery_results WHERE user_id = $1 ORDER BY drawn_at, id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_unpaid_lottery_results(&self) -> RepositoryResult<Vec<LotteryDrawResult>> {
+        Ok(sqlx::query_as::<_, LotteryDrawResult>(
+            "SELECT * FROM lottery_results WHERE NOT paid ORDER BY drawn_at, id",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn set_lottery_results_paid(
+        &self,
+        results: Vec<LotteryDrawResult>,
+        paid: bool,
+    ) -> RepositoryResult<()> {
+        let result_ids = results
+            .into_iter()
+            .map(|result| result.id)
+            .collect::<Vec<_>>();
+        sqlx::query("UPDATE lottery_results SET paid = $1 WHERE id = ANY($2)")
+            .bind(paid)
+            .bind(&result_ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_lottery_commitment(&self) -> RepositoryResult<Option<LotteryCommitment>> {
+        Ok(sqlx::query_as::<_, LotteryCommitment>(
+            "SELECT * FROM lottery_commitments WHERE id = $1",
+        )
+        .bind(PENDING_COMMITMENT_ID)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_lottery_commitment(
+        &self,
+        commitment: LotteryCommitment,
+    ) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO lottery_commitments (id, draw_id, seed, seed_hash, committed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE
+            SET draw_id = EXCLUDED.draw_id, seed = EXCLUDED.seed, seed_hash = EXCLUDED.seed_hash,
+                committed_at = EXCLUDED.committed_at"#,
+        )
+        .bind(&commitment.id)
+        .bind(&commitment.draw_id)
+        .bind(&commitment.seed)
+        .bind(&commitment.seed_hash)
+        .bind(commitment.committed_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JackpotRepository for PostgresRepository {
+    async fn get_jackpot(&self) -> RepositoryResult<Option<LotteryJackpot>> {
+        Ok(
+            sqlx::query_as::<_, LotteryJackpot>("SELECT * FROM lottery_jackpot WHERE id = $1")
+                .bind(JACKPOT_ID)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn add_to_jackpot(&self, amount: i32, seed: i32) -> RepositoryResult<LotteryJackpot> {
+        Ok(sqlx::query_as::<_, LotteryJackpot>(
+            r#"INSERT INTO lottery_jackpot (id, amount, updated_at)
+            VALUES ($1, $2 + $3, now())
+            ON CONFLICT (id) DO UPDATE
+            SET amount = lottery_jackpot.amount + $3, updated_at = now()
+            RETURNING *"#,
+        )
+        .bind(JACKPOT_ID)
+        .bind(seed)
+        .bind(amount)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+}
+
+#[async_trait]
+impl RewardRepository for PostgresRepository {
+    async fn get_rewards(&self, user_id: &str) -> RepositoryResult<Option<Rewards>> {
+        Ok(
+            sqlx::query_as::<_, Rewards>("SELECT * FROM user_rewards WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_versioned_rewards(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<Rewards>>> {
+        let row = sqlx::query(
+            "SELECT *, version::TEXT AS version_tag FROM user_rewards WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(Versioned {
+                document: Rewards::from_row(&row)?,
+                version: row.try_get("version_tag")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn add_rewards(&self, rewards: Rewards) -> RepositoryResult<()> {
+        let result = sqlx::query(
+            r#"INSERT INTO user_rewards (id, user_id, next_daily_time, next_weekly_time, daily_streak)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT DO NOTHING"#,
+        )
+        .bind(&rewards.id)
+        .bind(&rewards.user_id)
+        .bind(rewards.next_daily_time)
+        .bind(rewards.next_weekly_time)
+        .bind(rewards.daily_streak as i32)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            Err(RepositoryError::Conflict)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn replace_rewards(&self, rewards: Rewards, version: &str) -> RepositoryResult<()> {
+        let result = sqlx::query(
+            r#"UPDATE user_rewards
+            SET next_daily_time = $2, next_weekly_time = $3, daily_streak = $4,
+                version = version + 1
+            WHERE id = $1 AND version::TEXT = $5"#,
+        )
+        .bind(&rewards.id)
+        .bind(rewards.next_daily_time)
+        .bind(rewards.next_weekly_time)
+        .bind(rewards.daily_streak as i32)
+        .bind(version)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            Err(RepositoryError::Conflict)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl RollRepository for PostgresRepository {
+    async fn get_all_user_rolls(&self) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(sqlx::query_as::<_, UserRoll>("SELECT * FROM user_rolls")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_user_rolls_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        let page_size = page_request.page_size();
+        let items = sqlx::query_as::<_, UserRoll>(
+            "SELECT * FROM user_rolls WHERE ($1::TEXT IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(&page_request.continuation)
+        .bind(page_size as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Page::from_overfetched(items, page_size, |item| {
+            item.id.clone()
+        }))
+    }
+
+    async fn get_user_rolls(&self, user_id: &str) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(
+            sqlx::query_as::<_, UserRoll>("SELECT * FROM user_rolls WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_user_roll(
+        &self,
+        user_id: &str,
+        roll_id: i32,
+    ) -> RepositoryResult<Option<UserRoll>> {
+        Ok(sqlx::query_as::<_, UserRoll>(
+            "SELECT * FROM user_rolls WHERE user_id = $1 AND roll_id = $2",
+        )
+        .bind(user_id)
+        .bind(roll_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_user_roll_history(
+        &self,
+        user_id: &str,
+        query: &RollHistoryQuery,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>> {
+        let page_size = page_request.page_size();
+        let after = page_request
+            .continuation
+            .as_deref()
+            .and_then(|continuation| continuation.parse::<i32>().ok());
+        // Creation times are stored as text, so only the ones in RFC 3339 format are compared.
+        let items = sqlx::query_as::<_, UserRoll>(
+            r#"SELECT * FROM (
+                SELECT *, CASE
+                    WHEN created_at ~ '^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})$'
+                    THEN created_at::TIMESTAMPTZ
+                END AS created_time
+                FROM user_rolls
+                WHERE user_id = $1 AND ($2::INTEGER IS NULL OR roll_id > $2)
+            ) rolls
+            WHERE ($3::TIMESTAMPTZ IS NULL OR created_time >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR created_time <= $4)
+            ORDER BY roll_id LIMIT $5"#,
+        )
+        .bind(user_id)
+        .bind(after)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(page_size as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Page::from_overfetched(items, page_size, |item| {
+            item.roll_id.to_string()
+        }))
+    }
+
+    async fn get_user_character_rolls(
+        &self,
+        user_id: &str,
+        mal_character_ids: &[i32],
+    ) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(sqlx::query_as::<_, UserRoll>(
+            "SELECT * FROM user_rolls WHERE user_id = $1 AND mal_character_id = ANY($2)",
+        )
+        .bind(user_id)
+        .bind(mal_character_ids)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_max_roll_id(&self, user_id: &str) -> RepositoryResult<i32> {
+        Ok(sqlx::query_scalar::<_, i32>(
+            "SELECT COALESCE(MAX(roll_id), 0) FROM user_rolls WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn get_character_roll_counts(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterRollCount>> {
+        Ok(sqlx::query_as::<_, CharacterRollCount>(
+            r#"SELECT mal_character_id, COUNT(*) AS rolls, COUNT(*) FILTER (WHERE NOT consumed) AS copies
+            FROM user_rolls WHERE user_id = $1
+            GROUP BY mal_character_id ORDER BY mal_character_id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_character_rolls(&self, mal_character_id: i32) -> RepositoryResult<Vec<UserRoll>> {
+        Ok(
+            sqlx::query_as::<_, UserRoll>("SELECT * FROM user_rolls WHERE mal_character_id = $1")
+                .bind(mal_character_id)
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn add_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO user_rolls (id, roll_id, user_id, mal_character_id, created_at, banner_id, consumed)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+        )
+        .bind(&user_roll.id)
+        .bind(user_roll.roll_id)
+        .bind(&user_roll.user_id)
+        .bind(user_roll.mal_character_id)
+        .bind(&user_roll.created_at)
+        .bind(&user_roll.banner_id)
+        .bind(user_roll.consumed)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO user_rolls (id, roll_id, user_id, mal_character_id, created_at, banner_id, consumed)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO UPDATE
+            SET roll_id = EXCLUDED.roll_id, user_id = EXCLUDED.user_id,
+                mal_character_id = EXCLUDED.mal_character_id, created_at = EXCLUDED.created_at,
+                banner_id = EXCLUDED.banner_id, consumed = EXCLUDED.consumed"#,
+        )
+        .bind(&user_roll.id)
+        .bind(user_roll.roll_id)
+        .bind(&user_roll.user_id)
+        .bind(user_roll.mal_character_id)
+        .bind(&user_roll.created_at)
+        .bind(&user_roll.banner_id)
+        .bind(user_roll.consumed)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_roll_pity(&self, user_id: &str) -> RepositoryResult<Option<RollPity>> {
+        Ok(
+            sqlx::query_as::<_, RollPity>("SELECT * FROM user_roll_pities WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn upsert_roll_pity(&self, roll_pity: RollPity) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO user_roll_pities (id, user_id, misses)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (id) DO UPDATE SET misses = EXCLUDED.misses"#,
+        )
+        .bind(&roll_pity.id)
+        .bind(&roll_pity.user_id)
+        .bind(roll_pity.misses as i32)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CollectionRepository for PostgresRepository {
+    async fn get_character_progresses(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterProgress>> {
+        Ok(sqlx::query_as::<_, CharacterProgress>(
+            "SELECT * FROM character_progresses WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_character_progress(
+        &self,
+        user_id: &str,
+        mal_character_id: i32,
+    ) -> RepositoryResult<Option<CharacterProgress>> {
+        Ok(sqlx::query_as::<_, CharacterProgress>(
+            "SELECT * FROM character_progresses WHERE user_id = $1 AND mal_character_id = $2",
+        )
+        .bind(user_id)
+        .bind(mal_character_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_character_progress(&self, progress: CharacterProgress) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO character_progresses (id, user_id, mal_character_id, level, affinity)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET level = EXCLUDED.level, affinity = EXCLUDED.affinity"#,
+        )
+        .bind(&progress.id)
+        .bind(&progress.user_id)
+        .bind(progress.mal_character_id)
+        .bind(progress.level as i32)
+        .bind(progress.affinity as i32)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TradeRepository for PostgresRepository {
+    async fn get_trade_offer(&self, offer_id: &str) -> RepositoryResult<Option<TradeOffer>> {
+        Ok(
+            sqlx::query_as::<_, TradeOffer>("SELECT * FROM trade_offers WHERE id = $1")
+                .bind(offer_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_trade_offers(&self, user_id: &str) -> RepositoryResult<Vec<TradeOffer>> {
+        Ok(sqlx::query_as::<_, TradeOffer>(
+            "SELECT * FROM trade_offers WHERE from_user_id = $1 OR to_user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_trade_offer(&self, trade_offer: TradeOffer) -> RepositoryResult<()> {
+        upsert_trade_offer(&trade_offer, &self.pool).await?;
+        Ok(())
+    }
+
+    async fn complete_trade_offer(
+        &self,
+        trade_offer: TradeOffer,
+        transfers: Vec<(UserRoll, UserRoll)>,
+    ) -> RepositoryResult<()> {
+        let mut transaction = self.pool.begin().await?;
+        for (previous, user_roll) in transfers.iter() {
+            let moved = sqlx::query(
+                r#"UPDATE user_rolls SET id = $1, user_id = $2, roll_id = $3
+                WHERE id = $4 AND user_id = $5 AND roll_id = $6 AND NOT consumed"#,
+            )
+            .bind(&user_roll.id)
+            .bind(&user_roll.user_id)
+            .bind(user_roll.roll_id)
+            .bind(&previous.id)
+            .bind(&previous.user_id)
+            .bind(previous.roll_id)
+            .execute(&mut *transaction)
+            .await?;
+            if moved.rows_affected() == 0 {
+                return Err(RepositoryError::Conflict);
+            }
+        }
+
+        upsert_trade_offer(&trade_offer, &mut *transaction).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+async fn upsert_trade_offer<'e, E>(trade_offer: &TradeOffer, executor: E) -> sqlx::Result<()>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    sqlx::query(
+        r#"INSERT INTO trade_offers (id, from_user_id, to_user_id, offered_roll_ids, requested_roll_ids,
+            offered_credits, status, created_at, expires_at, resolved_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (id) DO UPDATE SET status = EXCLUDED.status, resolved_at = EXCLUDED.resolved_at"#,
+    )
+    .bind(&trade_offer.id)
+    .bind(&trade_offer.from_user_id)
+    .bind(&trade_offer.to_user_id)
+    .bind(&trade_offer.offered_roll_ids)
+    .bind(&trade_offer.requested_roll_ids)
+    .bind(trade_offer.offered_credits)
+    .bind(trade_offer.status.as_str())
+    .bind(trade_offer.created_at)
+    .bind(trade_offer.expires_at)
+    .bind(trade_offer.resolved_at)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl MarketRepository for PostgresRepository {
+    async fn get_active_market_listings(&self) -> RepositoryResult<Vec<MarketListing>> {
+        Ok(sqlx::query_as::<_, MarketListing>(
+            "SELECT * FROM market_listings WHERE status = $1 ORDER BY created_at",
+        )
+        .bind(ListingStatus::Active.as_str())
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_market_listing(
+        &self,
+        listing_id: &str,
+    ) -> RepositoryResult<Option<MarketListing>> {
+        Ok(
+            sqlx::query_as::<_, MarketListing>("SELECT * FROM market_listings WHERE id = $1")
+                .bind(listing_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn upsert_market_listing(&self, listing: MarketListing) -> RepositoryResult<()> {
+        upsert_market_listing(&listing, &self.pool).await?;
+        Ok(())
+    }
+
+    async fn complete_market_listing(
+        &self,
+        listing: MarketListing,
+        transfer: (UserRoll, UserRoll),
+    ) -> RepositoryResult<()> {
+        let (previous, user_roll) = transfer;
+        let mut transaction = self.pool.begin().await?;
+        let moved = sqlx::query(
+            r#"UPDATE user_rolls SET id = $1, user_id = $2, roll_id = $3
+            WHERE id = $4 AND user_id = $5 AND roll_id = $6 AND NOT consumed"#,
+        )
+        .bind(&user_roll.id)
+        .bind(&user_roll.user_id)
+        .bind(user_roll.roll_id)
+        .bind(&previous.id)
+        .bind(&previous.user_id)
+        .bind(previous.roll_id)
+        .execute(&mut *transaction)
+        .await?;
+        if moved.rows_affected() == 0 {
+            return Err(RepositoryError::Conflict);
+        }
+
+        upsert_market_listing(&listing, &mut *transaction).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+async fn upsert_market_listing<'e, E>(listing: &MarketListing, executor: E) -> sqlx::Result<()>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    sqlx::query(
+        r#"INSERT INTO market_listings (id, seller_id, roll_id, mal_character_id, listing_type, price,
+            highest_bid, highest_bidder_id, status, created_at, ends_at, buyer_id, sale_price, fee, settled_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        ON CONFLICT (id) DO UPDATE
+        SET highest_bid = EXCLUDED.highest_bid, highest_bidder_id = EXCLUDED.highest_bidder_id,
+            status = EXCLUDED.status, buyer_id = EXCLUDED.buyer_id, sale_price = EXCLUDED.sale_price,
+            fee = EXCLUDED.fee, settled_at = EXCLUDED.settled_at"#,
+    )
+    .bind(&listing.id)
+    .bind(&listing.seller_id)
+    .bind(listing.roll_id)
+    .bind(listing.mal_character_id)
+    .bind(listing.listing_type.as_str())
+    .bind(listing.price)
+    .bind(listing.highest_bid)
+    .bind(&listing.highest_bidder_id)
+    .bind(listing.status.as_str())
+    .bind(listing.created_at)
+    .bind(listing.ends_at)
+    .bind(&listing.buyer_id)
+    .bind(listing.sale_price)
+    .bind(listing.fee)
+    .bind(listing.settled_at)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl BannerRepository for PostgresRepository {
+    async fn get_banners(&self) -> RepositoryResult<Vec<Banner>> {
+        Ok(
+            sqlx::query_as::<_, Banner>("SELECT * FROM banners ORDER BY start_time")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_banner(&self, banner_id: &str) -> RepositoryResult<Option<Banner>> {
+        Ok(
+            sqlx::query_as::<_, Banner>("SELECT * FROM banners WHERE id = $1")
+                .bind(banner_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn upsert_banner(&self, banner: Banner) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO banners (id, name, start_time, end_time, featured_character_ids, rate_up, roll_cost)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO UPDATE
+            SET name = EXCLUDED.name, start_time = EXCLUDED.start_time, end_time = EXCLUDED.end_time,
+                featured_character_ids = EXCLUDED.featured_character_ids, rate_up = EXCLUDED.rate_up,
+                roll_cost = EXCLUDED.roll_cost"#,
+        )
+        .bind(&banner.id)
+        .bind(&banner.name)
+        .bind(banner.start_time)
+        .bind(banner.end_time)
+        .bind(&banner.featured_character_ids)
+        .bind(banner.rate_up)
+        .bind(banner.roll_cost)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MalCharacterRepository for PostgresRepository {
+    async fn get_all_mal_characters(&self) -> RepositoryResult<Vec<MalCharacter>> {
+        if let Some(mal_characters) = self.mal_character_cache.get_all() {
+            return Ok(mal_characters);
+        }
+
+        let mal_characters = sqlx::query_as::<_, MalCharacter>("SELECT * FROM mal_characters")
+            .fetch_all(&self.pool)
+            .await?;
+        self.mal_character_cache.insert_all(&mal_characters);
+        Ok(mal_characters)
+    }
+
+    async fn get_mal_character(&self, character_id: i32) -> RepositoryResult<Option<MalCharacter>> {
+        Ok(sqlx::query_as::<_, MalCharacter>(
+            "SELECT * FROM mal_characters WHERE character_id = $1",
+        )
+        .bind(character_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_mal_characters(
+        &self,
+        character_ids: &[i32],
+    ) -> RepositoryResult<Vec<MalCharacter>> {
+        let (mut mal_characters, missing) = self.mal_character_cache.get(character_ids);
+        if missing.is_empty() {
+            return Ok(mal_characters);
+        }
+
+        let loaded = sqlx::query_as::<_, MalCharacter>(
+            "SELECT * FROM mal_characters WHERE character_id = ANY($1)",
+        )
+        .bind(missing)
+        .fetch_all(&self.pool)
+        .await?;
+        self.mal_character_cache.insert(&loaded);
+        mal_characters.extend(loaded);
+        Ok(mal_characters)
+    }
+
+    async fn add_mal_character(&self, mal_character: MalCharacter) -> RepositoryResult<()> {
+        sqlx::query(
+            r#"INSERT INTO mal_characters (id, character_id, url, name, name_kanji, image_url, created_at, about, weight, rarity)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (character_id) DO UPDATE
+            SET url = EXCLUDED.url, name = EXCLUDED.name, name_kanji = EXCLUDED.name_kanji,
+                image_url = EXCLUDED.image_url, created_at = EXCLUDED.created_at, about = EXCLUDED.about,
+                weight = EXCLUDED.weight, rarity = EXCLUDED.rarity"#,
+        )
+        .bind(&mal_character.id)
+        .bind(mal_character.character_id)
+        .bind(&mal_character.url)
+        .bind(&mal_character.name)
+        .bind(&mal_character.name_kanji)
+        .bind(&mal_character.image_url)
+        .bind(&mal_character.created_at)
+        .bind(&mal_character.about)
+        .bind(mal_character.weight)
+        .bind(mal_character.rarity.as_str())
+        .execute(&self.pool)
+        .await?;
+        self.mal_character_cache
+            .evict(&[mal_character.character_id]);
+        Ok(())
+    }
+
+    async fn set_mal_character_rarities(
+        &self,
+        assignments: &[RarityAssignment],
+    ) -> RepositoryResult<Vec<i32>> {
+        let character_ids = assignments
+            .iter()
+            .map(|assignment| assignment.character_id)
+            .collect::<Vec<_>>();
+        let rarities = assignments
+            .iter()
+            .map(|assignment| assignment.rarity.as_str())
+            .collect::<Vec<_>>();
+        let updated = sqlx::query_scalar::<_, i32>(
+            r#"UPDATE mal_characters AS m SET rarity = a.rarity
+            FROM UNNEST($1::INTEGER[], $2::TEXT[]) AS a(character_id, rarity)
+            WHERE m.character_id = a.character_id
+            RETURNING m.character_id"#,
+        )
+        .bind(character_ids)
+        .bind(rarities)
+        .fetch_all(&self.pool)
+        .await?;
+        self.mal_character_cache.evict(&updated);
+        Ok(updated)
+    }
+}
+
+/// These run against the database in `TEST_DATABASE_URL`, each in a schema of its own, and are
+/// skipped when it isn't set.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::market::{ListingStatus, ListingType};
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use std::str::FromStr;
+    use std::time::Duration;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    struct TestDatabase {
+        repository: PostgresRepository,
+        admin_pool: Pool<Postgres>,
+        schema: String,
+    }
+
+    impl TestDatabase {
+        async fn new() -> Option<Self> {
+            let url = std::env::var("TEST_DATABASE_URL").ok()?;
+            let schema = format!("test_{}", Uuid::new_v4().simple());
+            let admin_pool = PgPoolOptions::new()
+                .connect(&url)
+                .await
+                .expect("Failed to connect to the test database.");
+            sqlx::query(&format!("CREATE SCHEMA {}", schema))
+                .execute(&admin_pool)
+                .await
+                .expect("Failed to create the test schema.");
+
+            let options = PgConnectOptions::from_str(&url)
+                .expect("Invalid test database URL.")
+                .options([("search_path", schema.as_str())]);
+            let pool = PgPoolOptions::new()
+                .connect_with(options)
+                .await
+                .expect("Failed to connect to the test schema.");
+            sqlx::migrate!()
+                .run(&pool)
+                .await
+                .expect("Failed to run migrations.");
+            let repository =
+                PostgresRepository::new(pool, MalCharacterCache::new(Duration::from_secs(60)));
+            Some(TestDatabase {
+                repository,
+                admin_pool,
+                schema,
+            })
+        }
+
+        async fn drop(self) {
+            self.repository.pool.close().await;
+            sqlx::query(&format!("DROP SCHEMA {} CASCADE", self.schema))
+                .execute(&self.admin_pool)
+                .await
+                .expect("Failed to drop the test schema.");
+        }
+    }
+
+    fn user_roll(user_id: &str, roll_id: i32, mal_character_id: i32) -> UserRoll {
+        UserRoll {
+            roll_id,
+            user_id: user_id.to_string(),
+            mal_character_id,
+            created_at: format!("2026-10-18T00:00:{:02}Z", roll_id),
+            id: UserRoll::get_id(user_id, roll_id),
+            ..UserRoll::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn replaces_credits_only_at_the_read_version() {
+        let Some(database) = TestDatabase::new().await else {
+            return;
+        };
+        let repository = &database.repository;
+        let user_credit = UserCredit {
+            id: "alice".to_string(),
+            username: "alice".to_string(),
+            user_id: "alice".to_string(),
+            credits: 100,
+        };
+        repository
+            .upsert_user_credit(user_credit.clone())
+            .await
+            .expect("Failed to add credit.");
+
+        let versioned = repository
+            .get_versioned_user_credit("alice")
+            .await
+            .expect("Failed to get credit.")
+            .expect("The credit is not found.");
+        let updated = UserCredit {
+            credits: 50,
+            ..user_credit.clone()
+        };
+        repository
+            .replace_user_credit(updated.clone(), &versioned.version)
+            .await
+            .expect("Failed to replace credit.");
+        let result = repository
+            .replace_user_credit(updated, &versioned.version)
+            .await;
+        assert!(matches!(result, Err(RepositoryError::Conflict)));
+        let credits = repository
+            .get_user_credit("alice")
+            .await
+            .expect("Failed to get credit.")
+            .map(|user_credit| user_credit.credits);
+        assert_eq!(credits, Some(50));
+
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn stores_and_counts_rolls() {
+        let Some(database) = TestDatabase::new().await else {
+            return;
+        };
+        let repository = &database.repository;
+        for user_roll in [
+            user_roll("alice", 1, 10),
+            user_roll("alice", 2, 10),
+            user_roll("alice", 3, 20),
+        ] {
+            repository
+                .add_user_roll(user_roll)
+                .await
+                .expect("Failed to add roll.");
+        }
+        let result = repository
+            .add_user_roll(UserRoll {
+                id: "another".to_string(),
+                ..user_roll("alice", 3, 30)
+            })
+            .await;
+        assert!(matches!(result, Err(RepositoryError::Conflict)));
+
+        repository
+            .update_user_roll(UserRoll {
+                consumed: true,
+                ..user_roll("alice", 2, 10)
+            })
+            .await
+            .expect("Failed to update roll.");
+        assert_eq!(repository.get_max_roll_id("alice").await.ok(), Some(3));
+        assert_eq!(repository.get_max_roll_id("bob").await.ok(), Some(0));
+        let counts = repository
+            .get_character_roll_counts("alice")
+            .await
+            .expect("Failed to count rolls.");
+        assert_eq!(
+            counts,
+            vec![
+                CharacterRollCount {
+                    mal_character_id: 10,
+                    rolls: 2,
+                    copies: 1,
+                },
+                CharacterRollCount {
+                    mal_character_id: 20,
+                    rolls: 1,
+                    copies: 1,
+                },
+            ]
+        );
+
+        let page_request = PageRequest {
+            limit: Some(2),
+            continuation: None,
+        };
+        let page = repository
+            .get_user_roll_history("alice", &RollHistoryQuery::default(), &page_request)
+            .await
+            .expect("Failed to page rolls.");
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|user_roll| user_roll.roll_id)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        let page_request = PageRequest {
+            continuation: page.continuation,
+            ..page_request
+        };
+        let page = repository
+            .get_user_roll_history("alice", &RollHistoryQuery::default(), &page_request)
+            .await
+            .expect("Failed to page rolls.");
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.continuation, None);
+
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn sells_listed_rolls_once() {
+        let Some(database) = TestDatabase::new().await else {
+            return;
+        };
+        let repository = &database.repository;
+        let previous = user_roll("alice", 1, 10);
+        repository
+            .add_user_roll(previous.clone())
+            .await
+            .expect("Failed to add roll.");
+        let listing = MarketListing {
+            id: "listing".to_string(),
+            seller_id: "alice".to_string(),
+            roll_id: 1,
+            mal_character_id: 10,
+            listing_type: ListingType::FixedPrice,
+            price: 10,
+            highest_bid: None,
+            highest_bidder_id: None,
+            status: ListingStatus::Sold,
+            created_at: OffsetDateTime::now_utc(),
+            ends_at: None,
+            buyer_id: Some("bob".to_string()),
+            sale_price: Some(10),
+            fee: Some(0),
+            settled_at: Some(OffsetDateTime::now_utc()),
+        };
+        let transfer = (previous.clone(), user_roll("bob", 1, 10));
+        repository
+            .complete_market_listing(listing.clone(), transfer.clone())
+            .await
+            .expect("Failed to complete listing.");
+        let result = repository.complete_market_listing(listing, transfer).await;
+        assert!(matches!(result, Err(RepositoryError::Conflict)));
+
+        let user_roll = repository
+            .get_user_roll("bob", 1)
+            .await
+            .expect("Failed to get roll.")
+            .expect("The roll is not found.");
+        assert_eq!(user_roll.id, "bob:1");
+        assert!(repository
+            .get_user_roll("alice", 1)
+            .await
+            .expect("Failed to get roll.")
+            .is_none());
+
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn archives_draws_with_unpaid_prizes() {
+        let Some(database) = TestDatabase::new().await else {
+            return;
+        };
+        let repository = &database.repository;
+        let drawn_at = OffsetDateTime::now_utc();
+        let lottery_draw = LotteryDraw {
+            id: "draw".to_string(),
+            winning_numbers: vec![1, 2, 3, 4, 5, 6],
+            max_number: 49,
+            drawn_at,
+            actor: "test".to_string(),
+            total_tickets: 2,
+            tiers: vec![],
+            jackpot: 0,
+            seed_hash: String::new(),
+            seed: String::new(),
+            committed_at: None,
+        };
+        let results = [("alice", 100, false), ("bob", 0, true)]
+            .into_iter()
+            .map(|(user_id, prize, paid)| LotteryDrawResult {
+                id: format!("draw:{}:0", user_id),
+                draw_id: "draw".to_string(),
+                user_id: user_id.to_string(),
+                ticket: vec![1, 2, 3, 4, 5, 6],
+                matches: 6,
+                prize,
+                drawn_at,
+                paid,
+            })
+            .collect::<Vec<_>>();
+        repository
+            .add_lottery_draw(lottery_draw, results)
+            .await
+            .expect("Failed to add draw.");
+
+        let unpaid = repository
+            .get_unpaid_lottery_results()
+            .await
+            .expect("Failed to get unpaid results.");
+        assert_eq!(
+            unpaid
+                .iter()
+                .map(|result| result.user_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["alice"]
+        );
+        repository
+            .set_lottery_results_paid(unpaid, true)
+            .await
+            .expect("Failed to mark results as paid.");
+        let unpaid = repository
+            .get_unpaid_lottery_results()
+            .await
+            .expect("Failed to get unpaid results.");
+        assert!(unpaid.is_empty());
+
+        database.drop().await;
+    }
+}
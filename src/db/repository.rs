@@ -0,0 +1,378 @@
+use crate::model::banner::Banner;
+use crate::model::collection::CharacterProgress;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransaction};
+use crate::model::lottery::draw::{LotteryCommitment, LotteryDraw, LotteryDrawResult};
+use crate::model::lottery::jackpot::LotteryJackpot;
+use crate::model::lottery::UserLottery;
+use crate::model::mal_character::{MalCharacter, RarityAssignment};
+use crate::model::market::MarketListing;
+use crate::model::page::{Page, PageRequest};
+use crate::model::rewards::Rewards;
+use crate::model::trade::TradeOffer;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RollHistoryQuery, RollPity, UserRoll};
+use axum::async_trait;
+use azure_core::error::ErrorKind;
+use azure_core::StatusCode;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// A conditional write lost against a concurrent modification of the same document.
+    Conflict,
+    Backend(String),
+}
+
+impl Display for RepositoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepositoryError::Conflict => {
+                write!(f, "The document was modified by another request.")
+            }
+            RepositoryError::Backend(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<azure_core::error::Error> for RepositoryError {
+    fn from(e: azure_core::error::Error) -> Self {
+        match e.kind() {
+            ErrorKind::HttpResponse {
+                status: StatusCode::PreconditionFailed | StatusCode::Conflict,
+                ..
+            } => RepositoryError::Conflict,
+            _ => RepositoryError::Backend(e.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => RepositoryError::Conflict,
+            e => RepositoryError::Backend(e.to_string()),
+        }
+    }
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+/// A document together with the version it was read at, e.g. a Cosmos ETag.
+#[derive(Clone, Debug)]
+pub struct Versioned<T> {
+    pub document: T,
+    pub version: String,
+}
+
+#[async_trait]
+pub trait CreditRepository {
+    async fn get_all_user_credits(&self) -> RepositoryResult<Vec<UserCredit>>;
+
+    async fn get_user_credits_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserCredit>>;
+
+    async fn get_user_credit(&self, user_id: &str) -> RepositoryResult<Option<UserCredit>>;
+
+    async fn upsert_user_credit(&self, user_credit: UserCredit) -> RepositoryResult<()>;
+
+    async fn get_versioned_user_credit(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<UserCredit>>>;
+
+    /// Replaces the credit document only if it is still at `version`,
+    /// failing with [`RepositoryError::Conflict`] otherwise.
+    async fn replace_user_credit(
+        &self,
+        user_credit: UserCredit,
+        version: &str,
+    ) -> RepositoryResult<()>;
+
+    async fn delete_user_credit(&self, user_credit: &UserCredit) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait LedgerRepository {
+    async fn add_credit_transaction(&self, transaction: CreditTransaction) -> RepositoryResult<()>;
+
+    /// Returns the user's transactions within the queried time range, oldest first.
+    async fn get_credit_transactions(
+        &self,
+        user_id: &str,
+        query: &CreditHistoryQuery,
+    ) -> RepositoryResult<Vec<CreditTransaction>>;
+}
+
+#[async_trait]
+pub trait LotteryRepository {
+    async fn get_all_user_lotteries(&self) -> RepositoryResult<Vec<UserLottery>>;
+
+    async fn get_user_lotteries_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserLottery>>;
+
+    async fn get_user_lottery(&self, user_id: &str) -> RepositoryResult<Option<UserLottery>>;
+
+    /// Atomically appends the lotteries of `user_lottery` to the user's existing document,
+    /// or stores `user_lottery` as-is if the user doesn't have one yet.
+    async fn add_user_lotteries(&self, user_lottery: UserLottery) -> RepositoryResult<UserLottery>;
+
+    /// Atomically removes and returns all of the user's tickets,
+    /// leaving tickets bought afterwards for the next draw.
+    async fn take_user_lotteries(&self, user_id: &str) -> RepositoryResult<Vec<Vec<u8>>>;
+}
+
+#[async_trait]
+pub trait LotteryDrawRepository {
+    async fn get_all_lottery_draws(&self) -> RepositoryResult<Vec<LotteryDraw>>;
+
+    async fn get_lottery_draws_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<LotteryDraw>>;
+
+    async fn get_lottery_draw(&self, draw_id: &str) -> RepositoryResult<Option<LotteryDraw>>;
+
+    /// Stores the draw together with every ticket that was scored in it.
+    async fn add_lottery_draw(
+        &self,
+        lottery_draw: LotteryDraw,
+        results: Vec<LotteryDrawResult>,
+    ) -> RepositoryResult<()>;
+
+    async fn get_lottery_draw_results(
+        &self,
+        draw_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>>;
+
+    /// Returns the results of the user's tickets in past draws, oldest first.
+    async fn get_user_lottery_results(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<LotteryDrawResult>>;
+
+    /// Returns the results of every draw whose prizes haven't been paid yet.
+    async fn get_unpaid_lottery_results(&self) -> RepositoryResult<Vec<LotteryDrawResult>>;
+
+    async fn set_lottery_results_paid(
+        &self,
+        results: Vec<LotteryDrawResult>,
+        paid: bool,
+    ) -> RepositoryResult<()>;
+
+    /// Returns the commitment of the upcoming draw, if one has been made.
+    async fn get_lottery_commitment(&self) -> RepositoryResult<Option<LotteryCommitment>>;
+
+    async fn upsert_lottery_commitment(
+        &self,
+        commitment: LotteryCommitment,
+    ) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait JackpotRepository {
+    async fn get_jackpot(&self) -> RepositoryResult<Option<LotteryJackpot>>;
+
+    /// Atomically adds `amount` to the jackpot pool, which starts out at `seed`,
+    /// and returns the updated pool.
+    async fn add_to_jackpot(&self, amount: i32, seed: i32) -> RepositoryResult<LotteryJackpot>;
+}
+
+#[async_trait]
+pub trait RewardRepository {
+    async fn get_rewards(&self, user_id: &str) -> RepositoryResult<Option<Rewards>>;
+
+    async fn get_versioned_rewards(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Option<Versioned<Rewards>>>;
+
+    /// Stores the rewards of a user who doesn't have any yet,
+    /// failing with [`RepositoryError::Conflict`] if another request created them first.
+    async fn add_rewards(&self, rewards: Rewards) -> RepositoryResult<()>;
+
+    /// Replaces the rewards only if they are still at `version`,
+    /// failing with [`RepositoryError::Conflict`] otherwise.
+    async fn replace_rewards(&self, rewards: Rewards, version: &str) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait RollRepository {
+    async fn get_all_user_rolls(&self) -> RepositoryResult<Vec<UserRoll>>;
+
+    async fn get_user_rolls_page(
+        &self,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>>;
+
+    async fn get_user_rolls(&self, user_id: &str) -> RepositoryResult<Vec<UserRoll>>;
+
+    async fn get_user_roll(
+        &self,
+        user_id: &str,
+        roll_id: i32,
+    ) -> RepositoryResult<Option<UserRoll>>;
+
+    /// Pages through the user's rolls made within the queried time range, in roll ID order.
+    async fn get_user_roll_history(
+        &self,
+        user_id: &str,
+        query: &RollHistoryQuery,
+        page_request: &PageRequest,
+    ) -> RepositoryResult<Page<UserRoll>>;
+
+    /// The user's rolls of the given characters.
+    async fn get_user_character_rolls(
+        &self,
+        user_id: &str,
+        mal_character_ids: &[i32],
+    ) -> RepositoryResult<Vec<UserRoll>>;
+
+    /// The highest roll ID the user has, or 0 if they have no rolls.
+    async fn get_max_roll_id(&self, user_id: &str) -> RepositoryResult<i32>;
+
+    /// How many rolls of each character the user has, in character ID order.
+    async fn get_character_roll_counts(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterRollCount>>;
+
+    /// Rolls of the given character across all users.
+    async fn get_character_rolls(&self, mal_character_id: i32) -> RepositoryResult<Vec<UserRoll>>;
+
+    /// Stores a new roll, failing with a conflict if the user already has a roll with its roll ID.
+    async fn add_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()>;
+
+    /// Replaces a stored roll, e.g. to mark it as spent.
+    async fn update_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()>;
+
+    async fn get_roll_pity(&self, user_id: &str) -> RepositoryResult<Option<RollPity>>;
+
+    async fn upsert_roll_pity(&self, roll_pity: RollPity) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait CollectionRepository {
+    async fn get_character_progresses(
+        &self,
+        user_id: &str,
+    ) -> RepositoryResult<Vec<CharacterProgress>>;
+
+    async fn get_character_progress(
+        &self,
+        user_id: &str,
+        mal_character_id: i32,
+    ) -> RepositoryResult<Option<CharacterProgress>>;
+
+    async fn upsert_character_progress(&self, progress: CharacterProgress) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait TradeRepository {
+    async fn get_trade_offer(&self, offer_id: &str) -> RepositoryResult<Option<TradeOffer>>;
+
+    /// Offers the user has made or received.
+    async fn get_trade_offers(&self, user_id: &str) -> RepositoryResult<Vec<TradeOffer>>;
+
+    async fn upsert_trade_offer(&self, trade_offer: TradeOffer) -> RepositoryResult<()>;
+
+    /// Stores the accepted offer and moves its rolls to their new owners, all at once where the
+    /// backend allows it. Each transfer pairs a roll as it is now with the roll after the trade.
+    /// Fails with a conflict if any roll has changed in the meantime.
+    async fn complete_trade_offer(
+        &self,
+        trade_offer: TradeOffer,
+        transfers: Vec<(UserRoll, UserRoll)>,
+    ) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait MarketRepository {
+    /// Listings that are still up for sale, including auctions that have ended but aren't settled.
+    async fn get_active_market_listings(&self) -> RepositoryResult<Vec<MarketListing>>;
+
+    async fn get_market_listing(&self, listing_id: &str)
+        -> RepositoryResult<Option<MarketListing>>;
+
+    async fn upsert_market_listing(&self, listing: MarketListing) -> RepositoryResult<()>;
+
+    /// Stores the sold listing and moves its roll to the buyer, all at once where the backend
+    /// allows it. Fails with a conflict if the roll has changed in the meantime.
+    async fn complete_market_listing(
+        &self,
+        listing: MarketListing,
+        transfer: (UserRoll, UserRoll),
+    ) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait BannerRepository {
+    async fn get_banners(&self) -> RepositoryResult<Vec<Banner>>;
+
+    async fn get_banner(&self, banner_id: &str) -> RepositoryResult<Option<Banner>>;
+
+    async fn upsert_banner(&self, banner: Banner) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait MalCharacterRepository {
+    async fn get_all_mal_characters(&self) -> RepositoryResult<Vec<MalCharacter>>;
+
+    async fn get_mal_character(&self, character_id: i32) -> RepositoryResult<Option<MalCharacter>>;
+
+    /// The characters with the given IDs that exist.
+    async fn get_mal_characters(
+        &self,
+        character_ids: &[i32],
+    ) -> RepositoryResult<Vec<MalCharacter>>;
+
+    async fn add_mal_character(&self, mal_character: MalCharacter) -> RepositoryResult<()>;
+
+    /// Sets the rarities of existing characters and returns the IDs of the characters found.
+    async fn set_mal_character_rarities(
+        &self,
+        assignments: &[RarityAssignment],
+    ) -> RepositoryResult<Vec<i32>>;
+}
+
+/// Everything the controllers need from a storage backend.
+pub trait Repository:
+    CreditRepository
+    + LedgerRepository
+    + LotteryRepository
+    + LotteryDrawRepository
+    + JackpotRepository
+    + RewardRepository
+    + RollRepository
+    + CollectionRepository
+    + TradeRepository
+    + MarketRepository
+    + BannerRepository
+    + MalCharacterRepository
+    + Send
+    + Sync
+{
+}
+
+impl<T> Repository for T where
+    T: CreditRepository
+        + LedgerRepository
+        + LotteryRepository
+        + LotteryDrawRepository
+        + JackpotRepository
+        + RewardRepository
+        + RollRepository
+        + CollectionRepository
+        + TradeRepository
+        + MarketRepository
+        + BannerRepository
+        + MalCharacterRepository
+        + Send
+        + Sync
+{
+}
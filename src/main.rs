@@ -3,34 +3,52 @@ use axum::Router;
 use tower_http::services::ServeDir;
 use tracing::Level;
 
+use crate::controller::banner_controller::{get_banner, get_banners, post_banner};
 use crate::controller::credit_controller::{
-    add_credit, add_user, delete_user, get_all_user_credits, get_single_user_credits, reduce_credit,
+    add_credit, add_user, delete_user, get_all_user_credits, get_credit_history,
+    get_single_user_credits, reduce_credit,
 };
 use crate::controller::dialog_controller::{generate_dialog, get_dialog_options};
 use crate::controller::login_controller::login;
 use crate::controller::lottery_controller::{
-    add_lottery, delete_lotteries, get_all_lotteries, get_daily_reward, get_user_lotteries,
-    get_weekly_reward,
+    add_lottery, add_quick_pick_lottery, delete_lotteries, draw_lottery, get_all_lotteries,
+    get_jackpot, get_lottery_draw, get_lottery_draws, get_next_lottery_draw, get_user_lotteries,
+    get_user_lottery_results, verify_lottery_draw,
 };
 use crate::controller::mal_character_controller::{
-    get_all_mal_characters, get_mal_character, post_mal_character,
+    assign_rarities, get_all_mal_characters, get_mal_character, get_mal_character_owners,
+    get_roll_rates, post_mal_character,
 };
+use crate::controller::market_controller::{
+    bid_on_market_listing, buy_market_listing, cancel_market_listing, get_market_listing,
+    get_market_listings, post_market_listing,
+};
+use crate::controller::reward_controller::{get_daily_reward, get_rewards, get_weekly_reward};
 use crate::controller::roll_controller::{
-    get_all_rolls, get_all_user_rolls, get_user_roll_by_id, post_user_roll,
+    get_all_rolls, get_all_user_rolls, get_collection, get_roll_summary, get_user_roll_by_id,
+    post_user_roll, roll_character, use_duplicate,
+};
+use crate::controller::trade_controller::{
+    accept_trade_offer, cancel_trade_offer, get_pending_trade_offers, get_trade_offer,
+    post_trade_offer, reject_trade_offer,
 };
+use crate::db::initialize_repository;
 use crate::model::app_state::AppState;
 use crate::shared::configuration::CONFIGURATION;
+use crate::shared::lottery::initialize_lottery_draw;
+use crate::shared::market::initialize_market_settlement;
 use crate::shared::swc_notifier::{
     initialize_slime_notification, initialize_tartarus_notification,
 };
 use crate::shared::swc_scraper::initialize_scraper;
-use crate::shared::util::initialize_clients;
 
 mod controller;
 mod db;
 mod middleware;
 mod model;
 mod shared;
+#[cfg(test)]
+mod tests;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -69,19 +87,47 @@ async fn main() -> anyhow::Result<()> {
     });
 
     let state = AppState {
-        cosmos_db: initialize_clients(),
+        repository: initialize_repository().await?,
     };
 
-    let app = Router::new()
+    let repository = state.repository.clone();
+    tokio::spawn(async move {
+        initialize_lottery_draw(repository).await;
+    });
+
+    let repository = state.repository.clone();
+    tokio::spawn(async move {
+        initialize_market_settlement(repository).await;
+    });
+
+    let app = create_router(state);
+
+    let listener = tokio::net::TcpListener::bind(&CONFIGURATION.server_bind_point).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+fn create_router(state: AppState) -> Router {
+    Router::new()
+        .route("/banners", get(get_banners).post(post_banner))
+        .route("/banners/:banner_id", get(get_banner))
         .route("/credit", get(get_all_user_credits).post(add_user))
         .route(
             "/credit/:user_id",
             get(get_single_user_credits).delete(delete_user),
         )
+        .route("/credit/:user_id/history", get(get_credit_history))
         .route("/credit/:user_id/plus", patch(add_credit))
         .route("/credit/:user_id/minus", patch(reduce_credit))
         .route("/dialog", get(get_dialog_options).post(generate_dialog))
         .route("/lottery", get(get_all_lotteries))
+        .route("/lottery/draw", post(draw_lottery))
+        .route("/lottery/draws", get(get_lottery_draws))
+        .route("/lottery/draws/next", get(get_next_lottery_draw))
+        .route("/lottery/draws/:id", get(get_lottery_draw))
+        .route("/lottery/draws/:id/verify", get(verify_lottery_draw))
+        .route("/lottery/jackpot", get(get_jackpot))
         .route(
             "/lottery/:user_id",
             get(get_user_lotteries).delete(delete_lotteries),
@@ -89,22 +135,46 @@ async fn main() -> anyhow::Result<()> {
         .route("/lottery/:user_id/daily", get(get_daily_reward))
         .route("/lottery/:user_id/weekly", get(get_weekly_reward))
         .route("/lottery/:user_id/new", post(add_lottery))
+        .route("/lottery/:user_id/quickpick", post(add_quick_pick_lottery))
+        .route("/lottery/:user_id/results", get(get_user_lottery_results))
         .route(
             "/mal_character",
             get(get_all_mal_characters).post(post_mal_character),
         )
+        .route("/mal_character/rarity", patch(assign_rarities))
+        .route("/mal_character/rates", get(get_roll_rates))
         .route("/mal_character/:id", get(get_mal_character))
+        .route("/mal_character/:id/owners", get(get_mal_character_owners))
+        .route(
+            "/market",
+            get(get_market_listings).post(post_market_listing),
+        )
+        .route("/market/:listing_id", get(get_market_listing))
+        .route("/market/:listing_id/bid", post(bid_on_market_listing))
+        .route("/market/:listing_id/buy", post(buy_market_listing))
+        .route("/market/:listing_id/cancel", post(cancel_market_listing))
+        .route("/rewards/:user_id", get(get_rewards))
+        .route("/rewards/:user_id/daily", get(get_daily_reward))
+        .route("/rewards/:user_id/weekly", get(get_weekly_reward))
+        .route("/trades", post(post_trade_offer))
+        .route("/trades/user/:user_id", get(get_pending_trade_offers))
+        .route("/trades/:offer_id", get(get_trade_offer))
+        .route("/trades/:offer_id/accept", post(accept_trade_offer))
+        .route("/trades/:offer_id/cancel", post(cancel_trade_offer))
+        .route("/trades/:offer_id/reject", post(reject_trade_offer))
         .route("/user_roll", get(get_all_rolls))
         .route("/user_roll/:user_id", get(get_all_user_rolls))
+        .route("/user_roll/:user_id/collection", get(get_collection))
         .route("/user_roll/:user_id/new", post(post_user_roll))
+        .route("/user_roll/:user_id/roll", post(roll_character))
+        .route("/user_roll/:user_id/summary", get(get_roll_summary))
         .route("/user_roll/:user_id/:roll_id", get(get_user_roll_by_id))
+        .route(
+            "/user_roll/:user_id/:roll_id/duplicate",
+            post(use_duplicate),
+        )
         .route("/login", post(login))
         .nest_service("/asset", get_service(ServeDir::new("./asset")))
         .nest_service("/upload", get_service(ServeDir::new("./upload")))
-        .with_state(state);
-
-    let listener = tokio::net::TcpListener::bind(&CONFIGURATION.server_bind_point).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .with_state(state)
 }
@@ -1,6 +1,7 @@
-use crate::model::cosmos_db::CosmosDb;
+use crate::db::repository::Repository;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub cosmos_db: CosmosDb,
+    pub repository: Arc<dyn Repository>,
 }
@@ -0,0 +1,64 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A limited-time event that rolls can target. Featured characters are more likely to be rolled
+/// than other characters of the same rarity while the banner runs.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct Banner {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub start_time: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub end_time: OffsetDateTime,
+    pub featured_character_ids: Vec<i32>,
+    /// What the weights of featured characters are multiplied by.
+    pub rate_up: f64,
+    pub roll_cost: i32,
+}
+
+impl Banner {
+    pub fn is_active(&self, now: OffsetDateTime) -> bool {
+        self.start_time <= now && now < self.end_time
+    }
+
+    pub fn is_featured(&self, character_id: i32) -> bool {
+        self.featured_character_ids.contains(&character_id)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("The banner needs a name.".to_string());
+        }
+        if self.end_time <= self.start_time {
+            return Err("The banner must end after it starts.".to_string());
+        }
+        if self.featured_character_ids.is_empty() {
+            return Err("The banner needs at least one featured character.".to_string());
+        }
+        if !self.rate_up.is_finite() || self.rate_up < 1.0 {
+            return Err("The rate up of the banner must be at least 1.".to_string());
+        }
+        if self.roll_cost < 0 {
+            return Err("The roll cost of the banner can't be negative.".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl CosmosEntity for Banner {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct BannerQuery {
+    /// Only returns the banners that are running right now.
+    #[serde(default)]
+    pub active: bool,
+}
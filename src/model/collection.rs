@@ -0,0 +1,88 @@
+use crate::model::mal_character::{MalCharacter, Rarity};
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+
+/// How far a user has raised a character they own by spending duplicates of it.
+/// Keyed by the user ID and the character ID.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct CharacterProgress {
+    pub id: String,
+    pub user_id: String,
+    pub mal_character_id: i32,
+    #[sqlx(try_from = "i32")]
+    pub level: u32,
+    #[sqlx(try_from = "i32")]
+    pub affinity: u32,
+}
+
+impl CharacterProgress {
+    /// The progress of a character that no duplicate has been spent on yet.
+    pub fn new(user_id: &str, mal_character_id: i32) -> Self {
+        CharacterProgress {
+            id: format!("{}:{}", user_id, mal_character_id),
+            user_id: user_id.to_string(),
+            mal_character_id,
+            level: 1,
+            affinity: 0,
+        }
+    }
+}
+
+impl CosmosEntity for CharacterProgress {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.user_id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Converts the duplicate into credits.
+    Convert,
+    /// Raises the character's level by one.
+    Level,
+    /// Raises the character's affinity by one.
+    Affinity,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DuplicateRequest {
+    pub action: DuplicateAction,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DuplicateResult {
+    pub roll_id: i32,
+    pub mal_character_id: i32,
+    pub action: DuplicateAction,
+    pub credits_awarded: i32,
+    pub level: u32,
+    pub affinity: u32,
+}
+
+/// A character the user owns, along with how many copies of it they hold.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CollectionEntry {
+    pub mal_character: MalCharacter,
+    pub rarity: Rarity,
+    /// Copies that haven't been converted or spent yet, including the first one.
+    pub copies: usize,
+    pub duplicates: usize,
+    pub level: u32,
+    pub affinity: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CharacterOwner {
+    pub user_id: String,
+    /// Copies that haven't been converted or spent yet.
+    pub copies: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CharacterOwners {
+    pub mal_character: MalCharacter,
+    pub owners: Vec<CharacterOwner>,
+}
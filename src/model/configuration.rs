@@ -1,4 +1,9 @@
+use crate::model::mal_character::Rarity;
+use crate::shared::time_zone::TimeZone;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use time::Weekday;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Configuration {
@@ -16,4 +21,621 @@ pub struct Configuration {
     pub cosmos_db_account: String,
     pub swc_publication_endpoints: Vec<String>,
     pub swc_check_interval: i32,
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    #[serde(default)]
+    pub lottery_rules: LotteryRules,
+    #[serde(default)]
+    pub reward_rules: RewardRules,
+    #[serde(default)]
+    pub roll_rules: RollRules,
+    #[serde(default)]
+    pub trade_rules: TradeRules,
+    #[serde(default)]
+    pub market_rules: MarketRules,
+}
+
+impl Configuration {
+    /// Rejects rules that would make the lottery or the rewards misbehave at runtime.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.lottery_rules.validate()?;
+        self.reward_rules.validate()?;
+        self.roll_rules.validate()?;
+        self.trade_rules.validate()?;
+        self.market_rules.validate()?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum StorageBackend {
+    #[default]
+    Cosmos,
+    Postgres,
+    InMemory,
+}
+
+impl StorageBackend {
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "Cosmos" => Ok(StorageBackend::Cosmos),
+            "Postgres" => Ok(StorageBackend::Postgres),
+            "InMemory" => Ok(StorageBackend::InMemory),
+            _ => anyhow::bail!(
+                "{} is not a valid storage backend, expected Cosmos, Postgres or InMemory.",
+                name
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct LotteryRules {
+    pub numbers_per_ticket: u8,
+    /// Ticket numbers range from 1 to this number, inclusive.
+    pub max_number: u8,
+    pub ticket_price: i32,
+    pub max_quick_pick_count: u32,
+    /// The percentage of every ticket purchase that goes into the jackpot pool.
+    pub jackpot_share_percent: u8,
+    pub payouts: Vec<LotteryPayout>,
+    pub draw_schedule: LotteryDrawSchedule,
+}
+
+impl Default for LotteryRules {
+    fn default() -> Self {
+        LotteryRules {
+            numbers_per_ticket: 6,
+            max_number: 49,
+            ticket_price: 10,
+            max_quick_pick_count: 100,
+            jackpot_share_percent: 50,
+            payouts: vec![
+                LotteryPayout {
+                    matches: 3,
+                    prize: 50,
+                },
+                LotteryPayout {
+                    matches: 4,
+                    prize: 500,
+                },
+                LotteryPayout {
+                    matches: 5,
+                    prize: 5000,
+                },
+                LotteryPayout {
+                    matches: 6,
+                    prize: 100000,
+                },
+            ],
+            draw_schedule: LotteryDrawSchedule::default(),
+        }
+    }
+}
+
+impl LotteryRules {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.numbers_per_ticket == 0 {
+            anyhow::bail!("lottery_rules.numbers_per_ticket has to be at least 1.");
+        }
+        if self.max_number < self.numbers_per_ticket {
+            anyhow::bail!(
+                "lottery_rules.max_number has to be at least lottery_rules.numbers_per_ticket ({}).",
+                self.numbers_per_ticket
+            );
+        }
+        if self.ticket_price <= 0 {
+            anyhow::bail!("lottery_rules.ticket_price has to be positive.");
+        }
+        if self.max_quick_pick_count == 0 {
+            anyhow::bail!("lottery_rules.max_quick_pick_count has to be at least 1.");
+        }
+        if self.jackpot_share_percent > 100 {
+            anyhow::bail!("lottery_rules.jackpot_share_percent can't exceed 100.");
+        }
+
+        for (index, payout) in self.payouts.iter().enumerate() {
+            if payout.matches > self.numbers_per_ticket {
+                anyhow::bail!(
+                    "The lottery payout for {} matches exceeds the {} numbers of a ticket.",
+                    payout.matches,
+                    self.numbers_per_ticket
+                );
+            }
+            if payout.prize < 0 {
+                anyhow::bail!(
+                    "The lottery payout for {} matches can't be negative.",
+                    payout.matches
+                );
+            }
+            if self.payouts[..index]
+                .iter()
+                .any(|other| other.matches == payout.matches)
+            {
+                anyhow::bail!(
+                    "The lottery payout for {} matches is defined more than once.",
+                    payout.matches
+                );
+            }
+        }
+
+        self.draw_schedule.validate()
+    }
+
+    pub fn get_prize(&self, matches: u8) -> i32 {
+        self.payouts
+            .iter()
+            .find(|payout| payout.matches == matches)
+            .map(|payout| payout.prize)
+            .unwrap_or_default()
+    }
+}
+
+/// The prize paid for a single ticket matching `matches` of the winning numbers.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug)]
+pub struct LotteryPayout {
+    pub matches: u8,
+    pub prize: i32,
+}
+
+/// When the weekly lottery draw takes place, in UTC.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct LotteryDrawSchedule {
+    pub enabled: bool,
+    /// The English name of the weekday, e.g. `Saturday`.
+    pub weekday: String,
+    pub hour: u8,
+}
+
+impl Default for LotteryDrawSchedule {
+    fn default() -> Self {
+        LotteryDrawSchedule {
+            enabled: true,
+            weekday: "Saturday".to_string(),
+            hour: 12,
+        }
+    }
+}
+
+impl LotteryDrawSchedule {
+    fn validate(&self) -> anyhow::Result<()> {
+        self.get_weekday()?;
+        if self.hour > 23 {
+            anyhow::bail!("lottery_rules.draw_schedule.hour has to be between 0 and 23.");
+        }
+        Ok(())
+    }
+
+    pub fn get_weekday(&self) -> anyhow::Result<Weekday> {
+        Weekday::from_str(&self.weekday).map_err(|_| {
+            anyhow::anyhow!(
+                "lottery_rules.draw_schedule.weekday {} is not a weekday.",
+                &self.weekday
+            )
+        })
+    }
+}
+
+/// How the time of a user's next daily and weekly reward is determined.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum RewardResetMode {
+    /// Rewards become available again a fixed number of hours after they were claimed.
+    #[default]
+    Rolling,
+    /// Daily rewards reset at a fixed wall-clock time and weekly rewards on a fixed weekday, in `time_zone`.
+    Calendar,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct RewardRules {
+    pub daily_reward: i32,
+    pub weekly_reward: i32,
+    pub reset_mode: RewardResetMode,
+    /// Only used in the `Rolling` reset mode.
+    pub daily_cooldown_hours: i64,
+    /// Only used in the `Rolling` reset mode.
+    pub weekly_cooldown_hours: i64,
+    /// The IANA name of the time zone used in the `Calendar` reset mode, e.g. `Asia/Tokyo`.
+    pub time_zone: String,
+    /// The hour of the day at which rewards reset in the `Calendar` reset mode.
+    pub reset_hour: u8,
+    /// The English name of the weekday on which weekly rewards reset in the `Calendar` reset mode.
+    pub weekly_reset_weekday: String,
+    /// How many hours a daily reward can be claimed late, after the next one has become
+    /// available, without losing the daily streak.
+    pub streak_grace_hours: i64,
+    /// The bonus schedule repeats every this many days of a streak. 0 means it never repeats.
+    pub streak_cycle_days: u32,
+    /// Extra credits paid on top of the daily reward on certain days of a streak.
+    pub streak_bonuses: Vec<StreakBonus>,
+}
+
+impl Default for RewardRules {
+    fn default() -> Self {
+        RewardRules {
+            daily_reward: 10,
+            weekly_reward: 70,
+            reset_mode: RewardResetMode::Rolling,
+            daily_cooldown_hours: 24,
+            weekly_cooldown_hours: 24 * 7,
+            time_zone: "UTC".to_string(),
+            reset_hour: 0,
+            weekly_reset_weekday: "Monday".to_string(),
+            streak_grace_hours: 0,
+            streak_cycle_days: 7,
+            streak_bonuses: vec![StreakBonus { day: 7, bonus: 30 }],
+        }
+    }
+}
+
+impl RewardRules {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.daily_reward < 0 || self.weekly_reward < 0 {
+            anyhow::bail!(
+                "reward_rules.daily_reward and reward_rules.weekly_reward can't be negative."
+            );
+        }
+        if self.daily_cooldown_hours <= 0 || self.weekly_cooldown_hours <= 0 {
+            anyhow::bail!(
+                "reward_rules.daily_cooldown_hours and reward_rules.weekly_cooldown_hours have to be positive."
+            );
+        }
+        if self.reset_hour > 23 {
+            anyhow::bail!("reward_rules.reset_hour has to be between 0 and 23.");
+        }
+        self.get_weekly_reset_weekday()?;
+        if self.reset_mode == RewardResetMode::Calendar {
+            self.get_time_zone()?;
+        }
+        if self.streak_grace_hours < 0 {
+            anyhow::bail!("reward_rules.streak_grace_hours can't be negative.");
+        }
+
+        for (index, streak_bonus) in self.streak_bonuses.iter().enumerate() {
+            if streak_bonus.day == 0
+                || (self.streak_cycle_days > 0 && streak_bonus.day > self.streak_cycle_days)
+            {
+                anyhow::bail!(
+                    "The streak bonus for day {} is outside of the streak cycle of {} days.",
+                    streak_bonus.day,
+                    self.streak_cycle_days
+                );
+            }
+            if streak_bonus.bonus < 0 {
+                anyhow::bail!(
+                    "The streak bonus for day {} can't be negative.",
+                    streak_bonus.day
+                );
+            }
+            if self.streak_bonuses[..index]
+                .iter()
+                .any(|other| other.day == streak_bonus.day)
+            {
+                anyhow::bail!(
+                    "The streak bonus for day {} is defined more than once.",
+                    streak_bonus.day
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The bonus paid for claiming the daily reward on the given day of a streak.
+    pub fn get_streak_bonus(&self, streak: u32) -> i32 {
+        let day = match self.streak_cycle_days {
+            0 => streak,
+            cycle_days => (streak.max(1) - 1) % cycle_days + 1,
+        };
+        self.streak_bonuses
+            .iter()
+            .find(|streak_bonus| streak_bonus.day == day)
+            .map(|streak_bonus| streak_bonus.bonus)
+            .unwrap_or_default()
+    }
+
+    pub fn get_weekly_reset_weekday(&self) -> anyhow::Result<Weekday> {
+        Weekday::from_str(&self.weekly_reset_weekday).map_err(|_| {
+            anyhow::anyhow!(
+                "reward_rules.weekly_reset_weekday {} is not a weekday.",
+                &self.weekly_reset_weekday
+            )
+        })
+    }
+
+    pub fn get_time_zone(&self) -> anyhow::Result<TimeZone> {
+        TimeZone::load(&self.time_zone)
+            .map_err(|e| anyhow::anyhow!("reward_rules.time_zone is invalid: {}", e))
+    }
+}
+
+/// Extra credits paid for claiming the daily reward on the given day of a streak.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug)]
+pub struct StreakBonus {
+    pub day: u32,
+    pub bonus: i32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct RollRules {
+    pub roll_cost: i32,
+    /// Drop rates of each rarity relative to each other. Rarities that are left out can't be rolled.
+    pub rarity_rates: BTreeMap<Rarity, f64>,
+    /// Characters of this rarity or higher reset the pity counter when pulled.
+    pub pity_rarity: Rarity,
+    /// The roll on which a character of the pity rarity is guaranteed after missing it on all the
+    /// rolls before. 0 disables hard pity.
+    pub hard_pity: u32,
+    /// The roll from which each further miss raises the rates of the pity rarities. 0 disables soft pity.
+    pub soft_pity_start: u32,
+    /// How much the rate of each pity rarity is raised by per roll once soft pity has started.
+    pub soft_pity_rate_increase: f64,
+    /// Credits paid for converting a duplicate of each rarity.
+    pub duplicate_credits: BTreeMap<Rarity, i32>,
+    pub max_character_level: u32,
+    pub max_character_affinity: u32,
+    /// How long characters are kept in memory before they are loaded again. Rarity and weight
+    /// changes made directly in the database take up to this long to affect rolls. 0 disables caching.
+    pub character_cache_seconds: u64,
+}
+
+impl Default for RollRules {
+    fn default() -> Self {
+        RollRules {
+            roll_cost: 10,
+            rarity_rates: BTreeMap::from([
+                (Rarity::Common, 60.0),
+                (Rarity::Uncommon, 25.0),
+                (Rarity::Rare, 10.0),
+                (Rarity::Epic, 4.0),
+                (Rarity::Legendary, 1.0),
+            ]),
+            pity_rarity: Rarity::Epic,
+            hard_pity: 90,
+            soft_pity_start: 0,
+            soft_pity_rate_increase: 5.0,
+            duplicate_credits: BTreeMap::from([
+                (Rarity::Common, 2),
+                (Rarity::Uncommon, 4),
+                (Rarity::Rare, 10),
+                (Rarity::Epic, 25),
+                (Rarity::Legendary, 60),
+            ]),
+            max_character_level: 10,
+            max_character_affinity: 100,
+            character_cache_seconds: 300,
+        }
+    }
+}
+
+impl RollRules {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.roll_cost < 0 {
+            anyhow::bail!("roll_rules.roll_cost can't be negative.");
+        }
+        for (rarity, rate) in &self.rarity_rates {
+            if !rate.is_finite() || *rate < 0.0 {
+                anyhow::bail!(
+                    "roll_rules.rarity_rates.{} must be a non-negative number.",
+                    rarity.as_str()
+                );
+            }
+        }
+        if self.rarity_rates.values().sum::<f64>() <= 0.0 {
+            anyhow::bail!("roll_rules.rarity_rates must give at least one rarity a positive rate.");
+        }
+        if (self.hard_pity > 0 || self.soft_pity_start > 0)
+            && !self
+                .rarity_rates
+                .iter()
+                .any(|(rarity, rate)| *rarity >= self.pity_rarity && *rate > 0.0)
+        {
+            anyhow::bail!(
+                "roll_rules.rarity_rates must give {} or a higher rarity a positive rate when pity is enabled.",
+                self.pity_rarity.as_str()
+            );
+        }
+        if self.hard_pity > 0 && self.soft_pity_start >= self.hard_pity {
+            anyhow::bail!("roll_rules.soft_pity_start must be less than roll_rules.hard_pity.");
+        }
+        if !self.soft_pity_rate_increase.is_finite() || self.soft_pity_rate_increase < 0.0 {
+            anyhow::bail!("roll_rules.soft_pity_rate_increase must be a non-negative number.");
+        }
+        for (rarity, credits) in &self.duplicate_credits {
+            if *credits < 0 {
+                anyhow::bail!(
+                    "roll_rules.duplicate_credits.{} can't be negative.",
+                    rarity.as_str()
+                );
+            }
+        }
+        if self.max_character_level == 0 {
+            anyhow::bail!("roll_rules.max_character_level must be at least 1.");
+        }
+        Ok(())
+    }
+
+    pub fn get_rarity_rate(&self, rarity: Rarity) -> f64 {
+        self.rarity_rates.get(&rarity).copied().unwrap_or_default()
+    }
+
+    pub fn get_duplicate_credits(&self, rarity: Rarity) -> i32 {
+        self.duplicate_credits
+            .get(&rarity)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether the roll after the given number of misses is guaranteed to be of the pity rarity.
+    pub fn is_hard_pity_reached(&self, misses: u32) -> bool {
+        self.hard_pity > 0 && misses + 1 >= self.hard_pity
+    }
+
+    /// How much the rate of each pity rarity is raised by on the roll after the given number of misses.
+    pub fn get_soft_pity_bonus(&self, misses: u32) -> f64 {
+        let roll = misses + 1;
+        if self.soft_pity_start == 0 || roll < self.soft_pity_start {
+            return 0.0;
+        }
+        f64::from(roll - self.soft_pity_start + 1) * self.soft_pity_rate_increase
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TradeRules {
+    /// How long an offer stays open before it expires.
+    pub offer_expiry_hours: i64,
+    /// The most rolls either side of an offer can include.
+    pub max_rolls_per_offer: usize,
+}
+
+impl Default for TradeRules {
+    fn default() -> Self {
+        TradeRules {
+            offer_expiry_hours: 72,
+            max_rolls_per_offer: 10,
+        }
+    }
+}
+
+impl TradeRules {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.offer_expiry_hours <= 0 {
+            anyhow::bail!("trade_rules.offer_expiry_hours must be positive.");
+        }
+        if self.max_rolls_per_offer == 0 {
+            anyhow::bail!("trade_rules.max_rolls_per_offer must be at least 1.");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct MarketRules {
+    /// The percentage of every sale that is taken out of circulation instead of paid to the seller.
+    pub fee_percent: u8,
+    /// How much a bid has to exceed the highest bid by.
+    pub min_bid_increment: i32,
+    pub default_auction_hours: i64,
+    pub max_auction_hours: i64,
+    /// How often ended auctions are looked for and settled.
+    pub settlement_interval_seconds: u64,
+}
+
+impl Default for MarketRules {
+    fn default() -> Self {
+        MarketRules {
+            fee_percent: 5,
+            min_bid_increment: 1,
+            default_auction_hours: 24,
+            max_auction_hours: 168,
+            settlement_interval_seconds: 60,
+        }
+    }
+}
+
+impl MarketRules {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.fee_percent > 100 {
+            anyhow::bail!("market_rules.fee_percent can't be more than 100.");
+        }
+        if self.min_bid_increment < 1 {
+            anyhow::bail!("market_rules.min_bid_increment must be at least 1.");
+        }
+        if self.max_auction_hours < 1 {
+            anyhow::bail!("market_rules.max_auction_hours must be at least 1.");
+        }
+        if !(1..=self.max_auction_hours).contains(&self.default_auction_hours) {
+            anyhow::bail!(
+                "market_rules.default_auction_hours must be between 1 and market_rules.max_auction_hours."
+            );
+        }
+        if self.settlement_interval_seconds == 0 {
+            anyhow::bail!("market_rules.settlement_interval_seconds must be at least 1.");
+        }
+        Ok(())
+    }
+
+    /// The part of a sale price taken as a fee.
+    pub fn get_fee(&self, sale_price: i32) -> i32 {
+        sale_price * self.fee_percent as i32 / 100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_storage_backend_names() {
+        assert_eq!(
+            StorageBackend::from_name("Postgres").ok(),
+            Some(StorageBackend::Postgres)
+        );
+        assert_eq!(
+            StorageBackend::from_name("InMemory").ok(),
+            Some(StorageBackend::InMemory)
+        );
+        assert!(StorageBackend::from_name("postgres").is_err());
+        assert!(StorageBackend::from_name("").is_err());
+    }
+
+    #[test]
+    fn repeats_streak_bonuses_every_cycle() {
+        let rules = RewardRules {
+            streak_cycle_days: 7,
+            streak_bonuses: vec![
+                StreakBonus { day: 1, bonus: 5 },
+                StreakBonus { day: 7, bonus: 30 },
+            ],
+            ..RewardRules::default()
+        };
+        assert_eq!(rules.get_streak_bonus(0), 5);
+        assert_eq!(rules.get_streak_bonus(1), 5);
+        assert_eq!(rules.get_streak_bonus(6), 0);
+        assert_eq!(rules.get_streak_bonus(7), 30);
+        assert_eq!(rules.get_streak_bonus(8), 5);
+        assert_eq!(rules.get_streak_bonus(14), 30);
+
+        let rules = RewardRules {
+            streak_cycle_days: 0,
+            ..rules
+        };
+        assert_eq!(rules.get_streak_bonus(7), 30);
+        assert_eq!(rules.get_streak_bonus(14), 0);
+    }
+
+    #[test]
+    fn raises_pity_rates_after_soft_pity_starts() {
+        let rules = RollRules {
+            hard_pity: 10,
+            soft_pity_start: 8,
+            soft_pity_rate_increase: 5.0,
+            ..RollRules::default()
+        };
+        assert!(rules.validate().is_ok());
+        assert_eq!(rules.get_soft_pity_bonus(6), 0.0);
+        assert_eq!(rules.get_soft_pity_bonus(7), 5.0);
+        assert_eq!(rules.get_soft_pity_bonus(8), 10.0);
+        assert!(!rules.is_hard_pity_reached(8));
+        assert!(rules.is_hard_pity_reached(9));
+
+        let rules = RollRules {
+            soft_pity_start: 10,
+            ..rules
+        };
+        assert!(rules.validate().is_err());
+        let rules = RollRules {
+            hard_pity: 0,
+            soft_pity_start: 0,
+            ..rules
+        };
+        assert_eq!(rules.get_soft_pity_bonus(100), 0.0);
+        assert!(!rules.is_hard_pity_reached(100));
+    }
 }
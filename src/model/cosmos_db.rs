@@ -2,6 +2,7 @@ use azure_data_cosmos::prelude::{CosmosClient, DatabaseClient};
 
 #[derive(Clone)]
 pub struct CosmosDb {
+    #[allow(dead_code)]
     pub client: CosmosClient,
     pub database: DatabaseClient,
 }
@@ -0,0 +1,108 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct CreditTransaction {
+    pub id: String,
+    pub user_id: String,
+    pub amount: i32,
+    #[sqlx(try_from = "String")]
+    pub reason: CreditTransactionReason,
+    pub balance_after: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub actor: String,
+}
+
+impl CosmosEntity for CreditTransaction {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.user_id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CreditTransactionReason {
+    Adjustment,
+    LotteryPurchase,
+    LotteryRefund,
+    LotteryPrize,
+    DailyReward,
+    WeeklyReward,
+    RollPurchase,
+    RollRefund,
+    DuplicateConversion,
+    TradeSent,
+    TradeReceived,
+    TradeRefund,
+    MarketPurchase,
+    MarketSale,
+    MarketBid,
+    MarketRefund,
+}
+
+impl CreditTransactionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CreditTransactionReason::Adjustment => "Adjustment",
+            CreditTransactionReason::LotteryPurchase => "LotteryPurchase",
+            CreditTransactionReason::LotteryRefund => "LotteryRefund",
+            CreditTransactionReason::LotteryPrize => "LotteryPrize",
+            CreditTransactionReason::DailyReward => "DailyReward",
+            CreditTransactionReason::WeeklyReward => "WeeklyReward",
+            CreditTransactionReason::RollPurchase => "RollPurchase",
+            CreditTransactionReason::RollRefund => "RollRefund",
+            CreditTransactionReason::DuplicateConversion => "DuplicateConversion",
+            CreditTransactionReason::TradeSent => "TradeSent",
+            CreditTransactionReason::TradeReceived => "TradeReceived",
+            CreditTransactionReason::TradeRefund => "TradeRefund",
+            CreditTransactionReason::MarketPurchase => "MarketPurchase",
+            CreditTransactionReason::MarketSale => "MarketSale",
+            CreditTransactionReason::MarketBid => "MarketBid",
+            CreditTransactionReason::MarketRefund => "MarketRefund",
+        }
+    }
+}
+
+impl TryFrom<String> for CreditTransactionReason {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "Adjustment" => Ok(CreditTransactionReason::Adjustment),
+            "LotteryPurchase" => Ok(CreditTransactionReason::LotteryPurchase),
+            "LotteryRefund" => Ok(CreditTransactionReason::LotteryRefund),
+            "LotteryPrize" => Ok(CreditTransactionReason::LotteryPrize),
+            "DailyReward" => Ok(CreditTransactionReason::DailyReward),
+            "WeeklyReward" => Ok(CreditTransactionReason::WeeklyReward),
+            "RollPurchase" => Ok(CreditTransactionReason::RollPurchase),
+            "RollRefund" => Ok(CreditTransactionReason::RollRefund),
+            "DuplicateConversion" => Ok(CreditTransactionReason::DuplicateConversion),
+            "TradeSent" => Ok(CreditTransactionReason::TradeSent),
+            "TradeReceived" => Ok(CreditTransactionReason::TradeReceived),
+            "TradeRefund" => Ok(CreditTransactionReason::TradeRefund),
+            "MarketPurchase" => Ok(CreditTransactionReason::MarketPurchase),
+            "MarketSale" => Ok(CreditTransactionReason::MarketSale),
+            "MarketBid" => Ok(CreditTransactionReason::MarketBid),
+            "MarketRefund" => Ok(CreditTransactionReason::MarketRefund),
+            _ => Err(format!("Unknown credit transaction reason: {}", value)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct CreditHistoryQuery {
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+impl CreditHistoryQuery {
+    pub fn contains(&self, time: OffsetDateTime) -> bool {
+        self.from.map(|from| time >= from).unwrap_or(true)
+            && self.to.map(|to| time <= to).unwrap_or(true)
+    }
+}
@@ -0,0 +1,156 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// The ID of the single document holding the commitment of the upcoming draw.
+pub const PENDING_COMMITMENT_ID: &str = "pending";
+
+/// A completed lottery draw. The scored tickets are archived separately as [`LotteryDrawResult`]s.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
+pub struct LotteryDraw {
+    pub id: String,
+    #[sqlx(json)]
+    pub winning_numbers: Vec<u8>,
+    /// The upper bound of the number range the winning numbers were drawn from.
+    #[serde(default = "default_max_number")]
+    #[sqlx(try_from = "i16")]
+    pub max_number: u8,
+    #[serde(with = "time::serde::rfc3339")]
+    pub drawn_at: OffsetDateTime,
+    pub actor: String,
+    #[serde(default)]
+    pub total_tickets: i32,
+    #[serde(default)]
+    #[sqlx(json)]
+    pub tiers: Vec<LotteryDrawTier>,
+    #[serde(default)]
+    pub jackpot: i32,
+    /// The SHA-256 hash of `seed`, published before the draw.
+    #[serde(default)]
+    pub seed_hash: String,
+    /// The seed the winning numbers were derived from, revealed by the draw.
+    #[serde(default)]
+    pub seed: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub committed_at: Option<OffsetDateTime>,
+}
+
+/// Draws made before the number range became configurable always used 1–49.
+fn default_max_number() -> u8 {
+    49
+}
+
+impl CosmosEntity for LotteryDraw {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+/// How many tickets matched a prize tier of the payout table in a draw.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LotteryDrawTier {
+    pub matches: u8,
+    pub winners: i32,
+    pub prize: i32,
+}
+
+/// A single ticket scored in a draw.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
+pub struct LotteryDrawResult {
+    pub id: String,
+    pub draw_id: String,
+    pub user_id: String,
+    #[sqlx(json)]
+    pub ticket: Vec<u8>,
+    #[sqlx(try_from = "i16")]
+    pub matches: u8,
+    pub prize: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub drawn_at: OffsetDateTime,
+    /// Whether the prize has been paid to the user. Failed payments are retried with later draws.
+    #[serde(default = "default_paid")]
+    pub paid: bool,
+}
+
+/// Results archived before payments were tracked were paid together with their draw.
+fn default_paid() -> bool {
+    true
+}
+
+impl CosmosEntity for LotteryDrawResult {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.user_id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LotteryDrawDetails {
+    #[serde(flatten)]
+    pub draw: LotteryDraw,
+    pub results: Vec<LotteryDrawResult>,
+}
+
+/// The secret seed of the upcoming draw. Only its hash is published until the draw reveals it.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
+pub struct LotteryCommitment {
+    pub id: String,
+    pub draw_id: String,
+    pub seed: String,
+    pub seed_hash: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub committed_at: OffsetDateTime,
+}
+
+impl CosmosEntity for LotteryCommitment {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NextLotteryDraw {
+    pub draw_id: String,
+    pub seed_hash: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub committed_at: OffsetDateTime,
+}
+
+impl From<LotteryCommitment> for NextLotteryDraw {
+    fn from(commitment: LotteryCommitment) -> Self {
+        NextLotteryDraw {
+            draw_id: commitment.draw_id,
+            seed_hash: commitment.seed_hash,
+            committed_at: commitment.committed_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LotteryDrawVerification {
+    pub draw_id: String,
+    pub seed: String,
+    pub seed_hash: String,
+    /// Whether `seed_hash` is the SHA-256 hash of `seed`.
+    pub seed_hash_matches: bool,
+    /// Whether re-deriving the numbers from `seed` yields `winning_numbers`.
+    pub winning_numbers_match: bool,
+    pub winning_numbers: Vec<u8>,
+    pub algorithm: String,
+    pub steps: Vec<LotteryDrawVerificationStep>,
+}
+
+/// One hash computed while deriving the winning numbers from a seed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LotteryDrawVerificationStep {
+    pub counter: u32,
+    pub digest: String,
+    pub value: u32,
+    /// The number this step produced, if it was neither rejected nor a repeat.
+    pub number: Option<u8>,
+}
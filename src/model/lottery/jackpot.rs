@@ -0,0 +1,23 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// The ID of the single document holding the jackpot pool.
+pub const JACKPOT_ID: &str = "jackpot";
+
+/// The progressive jackpot pool, funded by ticket sales and paid out to top tier winners.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
+pub struct LotteryJackpot {
+    pub id: String,
+    pub amount: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl CosmosEntity for LotteryJackpot {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
@@ -1,14 +1,14 @@
+pub mod draw;
+pub mod jackpot;
+
 use azure_data_cosmos::CosmosEntity;
 use serde::{Deserialize, Serialize};
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
 pub struct UserLottery {
     pub id: String,
     pub user_id: String,
-    pub next_daily_time: String,
-    pub next_weekly_time: String,
+    #[sqlx(json)]
     pub lotteries: Vec<Vec<u8>>,
 }
 
@@ -20,24 +20,20 @@ impl CosmosEntity for UserLottery {
     }
 }
 
-impl Default for UserLottery {
-    fn default() -> Self {
-        UserLottery {
-            id: "".to_string(),
-            user_id: "".to_string(),
-            next_daily_time: OffsetDateTime::UNIX_EPOCH
-                .format(&Rfc3339)
-                .unwrap_or_default(),
-            next_weekly_time: OffsetDateTime::UNIX_EPOCH
-                .format(&Rfc3339)
-                .unwrap_or_default(),
-            lotteries: vec![],
-        }
-    }
-}
-
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct UserLotteryUpdateInfo {
     pub username: String,
     pub lotteries: Vec<Vec<u8>>,
 }
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct QuickPickInfo {
+    pub username: String,
+    pub count: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct QuickPickResult {
+    pub tickets: Vec<Vec<u8>>,
+    pub user_lottery: UserLottery,
+}
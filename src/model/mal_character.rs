@@ -1,7 +1,7 @@
 use azure_data_cosmos::CosmosEntity;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
 pub struct MalCharacter {
     #[serde(rename = "Id")]
     pub character_id: i32,
@@ -17,9 +17,20 @@ pub struct MalCharacter {
     pub created_at: String,
     #[serde(rename = "About")]
     pub about: String,
+    /// How likely the character is to be rolled relative to the others. 0 means it can't be rolled.
+    #[serde(rename = "Weight", default = "default_weight")]
+    pub weight: i32,
+    #[serde(rename = "Rarity", default)]
+    #[sqlx(try_from = "String")]
+    pub rarity: Rarity,
     pub id: String,
 }
 
+/// Characters added before rolls were weighted are all equally likely.
+fn default_weight() -> i32 {
+    1
+}
+
 impl CosmosEntity for MalCharacter {
     type Entity = i32;
 
@@ -27,3 +38,94 @@ impl CosmosEntity for MalCharacter {
         self.character_id
     }
 }
+
+impl Default for MalCharacter {
+    fn default() -> Self {
+        MalCharacter {
+            character_id: 0,
+            url: String::new(),
+            name: String::new(),
+            name_kanji: String::new(),
+            image_url: String::new(),
+            created_at: String::new(),
+            about: String::new(),
+            weight: default_weight(),
+            rarity: Rarity::default(),
+            id: String::new(),
+        }
+    }
+}
+
+/// Rolls first pick a rarity according to the configured drop rates, then a character of that
+/// rarity according to the characters' weights.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    pub const ALL: [Rarity; 5] = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::Epic,
+        Rarity::Legendary,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rarity::Common => "Common",
+            Rarity::Uncommon => "Uncommon",
+            Rarity::Rare => "Rare",
+            Rarity::Epic => "Epic",
+            Rarity::Legendary => "Legendary",
+        }
+    }
+}
+
+impl TryFrom<String> for Rarity {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Rarity::ALL
+            .into_iter()
+            .find(|rarity| rarity.as_str() == value)
+            .ok_or_else(|| format!("Unknown rarity: {}", value))
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RarityAssignment {
+    #[serde(rename = "Id")]
+    pub character_id: i32,
+    #[serde(rename = "Rarity")]
+    pub rarity: Rarity,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct RarityAssignmentResult {
+    pub updated: Vec<i32>,
+    pub not_found: Vec<i32>,
+}
+
+/// The published odds of rolling a character of the given rarity.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RarityRate {
+    pub rarity: Rarity,
+    /// The configured drop rate of the rarity, relative to the other rarities.
+    pub configured_rate: f64,
+    /// The actual chance in percent, after leaving out rarities without rollable characters.
+    pub drop_rate: f64,
+    pub character_count: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RollRates {
+    pub roll_cost: i32,
+    pub rates: Vec<RarityRate>,
+}
@@ -0,0 +1,123 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A roll put up for sale on the marketplace, either for a fixed price or as a timed auction.
+/// Bids are taken from the bidder's credits right away and held until the auction settles.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct MarketListing {
+    pub id: String,
+    pub seller_id: String,
+    pub roll_id: i32,
+    pub mal_character_id: i32,
+    #[sqlx(try_from = "String")]
+    pub listing_type: ListingType,
+    /// The fixed price, or the lowest bid an auction accepts.
+    pub price: i32,
+    pub highest_bid: Option<i32>,
+    pub highest_bidder_id: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub status: ListingStatus,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    /// When an auction stops taking bids. Fixed-price listings stay up until sold or cancelled.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub buyer_id: Option<String>,
+    pub sale_price: Option<i32>,
+    /// The part of the sale price that was taken as a fee instead of being paid to the seller.
+    pub fee: Option<i32>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub settled_at: Option<OffsetDateTime>,
+}
+
+impl CosmosEntity for MarketListing {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ListingType {
+    FixedPrice,
+    Auction,
+}
+
+impl ListingType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListingType::FixedPrice => "FixedPrice",
+            ListingType::Auction => "Auction",
+        }
+    }
+}
+
+impl TryFrom<String> for ListingType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "FixedPrice" => Ok(ListingType::FixedPrice),
+            "Auction" => Ok(ListingType::Auction),
+            _ => Err(format!("Unknown listing type: {}", value)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ListingStatus {
+    Active,
+    Sold,
+    Cancelled,
+    /// An auction that ended without any bids.
+    Expired,
+}
+
+impl ListingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListingStatus::Active => "Active",
+            ListingStatus::Sold => "Sold",
+            ListingStatus::Cancelled => "Cancelled",
+            ListingStatus::Expired => "Expired",
+        }
+    }
+}
+
+impl TryFrom<String> for ListingStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "Active" => Ok(ListingStatus::Active),
+            "Sold" => Ok(ListingStatus::Sold),
+            "Cancelled" => Ok(ListingStatus::Cancelled),
+            "Expired" => Ok(ListingStatus::Expired),
+            _ => Err(format!("Unknown listing status: {}", value)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ListingRequest {
+    pub seller_id: String,
+    pub roll_id: i32,
+    pub listing_type: ListingType,
+    pub price: i32,
+    /// How long an auction runs. Defaults to the configured auction duration.
+    pub duration_hours: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BidRequest {
+    pub user_id: String,
+    pub amount: i32,
+}
+
+/// Identifies the user buying a listing or cancelling their own.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MarketActionRequest {
+    pub user_id: String,
+}
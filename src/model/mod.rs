@@ -1,12 +1,19 @@
 pub mod app_state;
+pub mod banner;
 pub mod claim;
+pub mod collection;
 pub mod configuration;
 pub mod cosmos_db;
+pub mod credit_transaction;
 pub mod dialog_info;
 pub mod errors;
 pub mod login_info;
 pub mod lottery;
 pub mod mal_character;
+pub mod market;
+pub mod page;
+pub mod rewards;
 pub mod swc;
+pub mod trade;
 pub mod user_credit;
 pub mod user_roll;
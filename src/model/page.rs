@@ -0,0 +1,99 @@
+use crate::shared::constants::CONTINUATION_HEADER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct PageRequest {
+    pub limit: Option<u32>,
+    pub continuation: Option<String>,
+}
+
+impl PageRequest {
+    /// Whether the client asked for paging at all.
+    /// Requests without `limit` and `continuation` still receive the whole collection.
+    pub fn is_paged(&self) -> bool {
+        self.limit.is_some() || self.continuation.is_some()
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub continuation: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page out of at most `page_size + 1` items fetched in key order,
+    /// using the key of the last returned item as the continuation token.
+    pub fn from_overfetched<F>(mut items: Vec<T>, page_size: u32, key: F) -> Self
+    where
+        F: Fn(&T) -> String,
+    {
+        let has_more = items.len() > page_size as usize;
+        items.truncate(page_size as usize);
+        let continuation = if has_more {
+            items.last().map(key)
+        } else {
+            None
+        };
+        Page {
+            items,
+            continuation,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        let mut response = (StatusCode::OK, Json(self.items)).into_response();
+        if let Some(continuation) = self
+            .continuation
+            .and_then(|token| HeaderValue::from_str(&token).ok())
+        {
+            response
+                .headers_mut()
+                .insert(CONTINUATION_HEADER, continuation);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_page_sizes() {
+        let page_request = |limit| PageRequest {
+            limit,
+            continuation: None,
+        };
+        assert_eq!(page_request(None).page_size(), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_request(Some(0)).page_size(), 1);
+        assert_eq!(page_request(Some(5000)).page_size(), MAX_PAGE_SIZE);
+        assert!(!page_request(None).is_paged());
+        assert!(page_request(Some(10)).is_paged());
+    }
+
+    #[test]
+    fn continues_after_the_last_item() {
+        let page = Page::from_overfetched(vec![1, 2, 3], 2, |item| item.to_string());
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.continuation.as_deref(), Some("2"));
+
+        let page = Page::from_overfetched(vec![3], 2, |item| item.to_string());
+        assert_eq!(page.items, vec![3]);
+        assert_eq!(page.continuation, None);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::model::user_credit::UserCredit;
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardType {
+    Daily,
+    Weekly,
+}
+
+/// When a user can claim their next daily and weekly rewards. Keyed by the user ID of their credit.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Debug, Clone)]
+pub struct Rewards {
+    pub id: String,
+    pub user_id: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_daily_time: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_weekly_time: OffsetDateTime,
+    /// How many daily rewards in a row the user has claimed without missing a day.
+    #[serde(default)]
+    #[sqlx(try_from = "i32")]
+    pub daily_streak: u32,
+}
+
+impl Rewards {
+    /// The rewards of a user who has never claimed any, both of which are available right away.
+    pub fn new(user_id: &str) -> Self {
+        Rewards {
+            id: user_id.to_string(),
+            user_id: user_id.to_string(),
+            next_daily_time: OffsetDateTime::UNIX_EPOCH,
+            next_weekly_time: OffsetDateTime::UNIX_EPOCH,
+            daily_streak: 0,
+        }
+    }
+}
+
+impl CosmosEntity for Rewards {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+/// The user's credit after a successful claim, along with what was paid and the state of the daily streak.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RewardClaimResult {
+    #[serde(flatten)]
+    pub user_credit: UserCredit,
+    /// The credits paid for this claim, including `streak_bonus`.
+    pub amount: i32,
+    pub streak_bonus: i32,
+    pub daily_streak: u32,
+    /// The daily streak is lost unless the next daily reward is claimed before this time.
+    #[serde(with = "time::serde::rfc3339")]
+    pub streak_expires_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RewardStatus {
+    pub reward_type: RewardType,
+    pub claimable: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_claim_time: OffsetDateTime,
+    pub seconds_remaining: i64,
+    /// The credits the next claim pays, including the streak bonus it would earn.
+    pub amount: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UserRewardStatus {
+    pub user_id: String,
+    pub daily: RewardStatus,
+    pub weekly: RewardStatus,
+    /// The current daily streak, or 0 if it has already been lost.
+    pub daily_streak: u32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub streak_expires_at: Option<OffsetDateTime>,
+}
+
+/// Returned when a reward is claimed before it is available again.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RewardNotReadyError {
+    pub error_message: String,
+    pub reward_type: RewardType,
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_claim_time: OffsetDateTime,
+    pub seconds_remaining: i64,
+}
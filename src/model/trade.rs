@@ -0,0 +1,100 @@
+use azure_data_cosmos::CosmosEntity;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// An offer from one user to give some of their rolls, plus optional credits, in exchange for
+/// some of another user's rolls. Roll IDs refer to each user's own rolls.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct TradeOffer {
+    pub id: String,
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub offered_roll_ids: Vec<i32>,
+    pub requested_roll_ids: Vec<i32>,
+    pub offered_credits: i32,
+    #[sqlx(try_from = "String")]
+    pub status: TradeStatus,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub resolved_at: Option<OffsetDateTime>,
+}
+
+impl TradeOffer {
+    /// Pending offers that have passed their expiry are reported as expired.
+    pub fn get_status(&self, now: OffsetDateTime) -> TradeStatus {
+        match self.status {
+            TradeStatus::Pending if now >= self.expires_at => TradeStatus::Expired,
+            status => status,
+        }
+    }
+}
+
+impl CosmosEntity for TradeOffer {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TradeStatus {
+    Pending,
+    Accepted,
+    Rejected,
+    Cancelled,
+    Expired,
+}
+
+impl TradeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeStatus::Pending => "Pending",
+            TradeStatus::Accepted => "Accepted",
+            TradeStatus::Rejected => "Rejected",
+            TradeStatus::Cancelled => "Cancelled",
+            TradeStatus::Expired => "Expired",
+        }
+    }
+}
+
+impl TryFrom<String> for TradeStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "Pending" => Ok(TradeStatus::Pending),
+            "Accepted" => Ok(TradeStatus::Accepted),
+            "Rejected" => Ok(TradeStatus::Rejected),
+            "Cancelled" => Ok(TradeStatus::Cancelled),
+            "Expired" => Ok(TradeStatus::Expired),
+            _ => Err(format!("Unknown trade status: {}", value)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TradeOfferRequest {
+    pub from_user_id: String,
+    pub to_user_id: String,
+    #[serde(default)]
+    pub offered_roll_ids: Vec<i32>,
+    pub requested_roll_ids: Vec<i32>,
+    #[serde(default)]
+    pub offered_credits: i32,
+}
+
+/// Identifies the user accepting, rejecting or cancelling an offer.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TradeResponseRequest {
+    pub user_id: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct PendingTradeOffers {
+    pub incoming: Vec<TradeOffer>,
+    pub outgoing: Vec<TradeOffer>,
+}
@@ -1,8 +1,11 @@
-use crate::model::mal_character::MalCharacter;
+use crate::model::mal_character::{MalCharacter, Rarity};
 use azure_data_cosmos::CosmosEntity;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
-#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug, Default)]
 pub struct UserRoll {
     #[serde(rename = "Id")]
     pub roll_id: i32,
@@ -12,9 +15,23 @@ pub struct UserRoll {
     pub mal_character_id: i32,
     #[serde(rename = "CreatedAt")]
     pub created_at: String,
+    /// The banner the roll was made on, if any.
+    #[serde(rename = "BannerId", default)]
+    pub banner_id: Option<String>,
+    /// Whether the roll has been converted into credits or spent on its character.
+    #[serde(rename = "Consumed", default)]
+    pub consumed: bool,
     pub id: String,
 }
 
+impl UserRoll {
+    /// The document ID of the user's roll with the given roll ID. Deriving it from both makes a
+    /// second roll with the same roll ID conflict even where the storage has no unique index.
+    pub fn get_id(user_id: &str, roll_id: i32) -> String {
+        format!("{}:{}", user_id, roll_id)
+    }
+}
+
 impl CosmosEntity for UserRoll {
     type Entity = i32;
 
@@ -27,4 +44,138 @@ impl CosmosEntity for UserRoll {
 pub struct GetRollResult {
     pub user_roll: UserRoll,
     pub mal_character: MalCharacter,
+    pub rarity: Rarity,
+    /// Whether the roll is unspent and the user owns another unspent copy of the character, so
+    /// that this one can be converted or spent on the character.
+    #[serde(default)]
+    pub duplicate: bool,
+}
+
+/// How many rolls in a row a user has made without pulling a character of the pity rarity.
+/// Keyed by the user ID.
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone, Debug)]
+pub struct RollPity {
+    pub id: String,
+    pub user_id: String,
+    #[sqlx(try_from = "i32")]
+    pub misses: u32,
+}
+
+impl RollPity {
+    pub fn new(user_id: &str) -> Self {
+        RollPity {
+            id: user_id.to_string(),
+            user_id: user_id.to_string(),
+            misses: 0,
+        }
+    }
+}
+
+impl CosmosEntity for RollPity {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PityStatus {
+    pub misses: u32,
+    pub pity_rarity: Rarity,
+    /// How many more rolls until a character of the pity rarity is guaranteed, if hard pity is enabled.
+    pub rolls_until_guaranteed: Option<u32>,
+    /// Whether the next roll has raised odds of pulling a character of the pity rarity.
+    pub soft_pity_active: bool,
+}
+
+/// A roll that has just been made, along with the pity it leaves the user with.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RollResult {
+    #[serde(flatten)]
+    pub roll: GetRollResult,
+    pub pity: PityStatus,
+}
+
+/// How many rolls of a character a user has made, and how many of those are still unspent.
+#[derive(sqlx::FromRow, Clone, Debug, Eq, PartialEq)]
+pub struct CharacterRollCount {
+    pub mal_character_id: i32,
+    pub rolls: i64,
+    pub copies: i64,
+}
+
+impl CharacterRollCount {
+    /// Counts rolls given as their character IDs and whether they have been spent.
+    pub fn count<I: IntoIterator<Item = (i32, bool)>>(user_rolls: I) -> Vec<Self> {
+        let mut counts = BTreeMap::<i32, CharacterRollCount>::new();
+        for (mal_character_id, consumed) in user_rolls {
+            let count = counts
+                .entry(mal_character_id)
+                .or_insert_with(|| CharacterRollCount {
+                    mal_character_id,
+                    rolls: 0,
+                    copies: 0,
+                });
+            count.rolls += 1;
+            if !consumed {
+                count.copies += 1;
+            }
+        }
+        counts.into_values().collect()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RollSummary {
+    pub user_id: String,
+    pub total_rolls: usize,
+    /// Characters the user still has at least one copy of.
+    pub unique_characters: usize,
+    pub total_characters: usize,
+    pub completion_percentage: f64,
+    /// Copies beyond the first that haven't been converted or spent yet.
+    pub duplicates: usize,
+    pub rarity_breakdown: Vec<RarityBreakdown>,
+    pub pity: PityStatus,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RarityBreakdown {
+    pub rarity: Rarity,
+    pub unique_characters: usize,
+    pub total_characters: usize,
+    pub completion_percentage: f64,
+    pub duplicates: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct RollHistoryQuery {
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+impl RollHistoryQuery {
+    pub fn is_filtered(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+
+    /// Whether the roll was made within the queried time range. Rolls whose creation time can't be
+    /// parsed only match when no range is given.
+    pub fn contains(&self, user_roll: &UserRoll) -> bool {
+        if !self.is_filtered() {
+            return true;
+        }
+        OffsetDateTime::parse(&user_roll.created_at, &Rfc3339).is_ok_and(|time| {
+            self.from.map(|from| time >= from).unwrap_or(true)
+                && self.to.map(|to| time <= to).unwrap_or(true)
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct RollRequest {
+    pub banner_id: Option<String>,
 }
@@ -0,0 +1,324 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::collection::{
+    CharacterOwner, CharacterOwners, CharacterProgress, CollectionEntry, DuplicateAction,
+    DuplicateResult,
+};
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::errors::ServerError;
+use crate::model::mal_character::Rarity;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RarityBreakdown, RollSummary, UserRoll};
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::{update_credit, CreditChange, CreditUpdateError};
+use crate::shared::roll;
+use crate::shared::roll_lock::ROLL_LOCKS;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+pub enum DuplicateError {
+    RollNotFound,
+    AlreadyConsumed,
+    /// The roll is up for sale on the marketplace.
+    Listed,
+    /// The user doesn't own another copy of the rolled character.
+    NotDuplicate,
+    MaxLevel,
+    MaxAffinity,
+    Credit(CreditUpdateError),
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for DuplicateError {
+    fn into_response(self) -> Response {
+        let (status_code, error_message) = match self {
+            DuplicateError::RollNotFound => (
+                StatusCode::NOT_FOUND,
+                "Cannot find the specified roll within user's rolls.",
+            ),
+            DuplicateError::AlreadyConsumed => (
+                StatusCode::CONFLICT,
+                "The specified roll has already been converted or spent.",
+            ),
+            DuplicateError::Listed => (
+                StatusCode::CONFLICT,
+                "The specified roll is listed on the marketplace.",
+            ),
+            DuplicateError::NotDuplicate => (
+                StatusCode::BAD_REQUEST,
+                "The specified roll is the user's only copy of the character.",
+            ),
+            DuplicateError::MaxLevel => (
+                StatusCode::BAD_REQUEST,
+                "The character has already reached the maximum level.",
+            ),
+            DuplicateError::MaxAffinity => (
+                StatusCode::BAD_REQUEST,
+                "The character has already reached the maximum affinity.",
+            ),
+            DuplicateError::Credit(e) => return e.into_response(),
+            DuplicateError::Repository(e) => {
+                let error_message = format!("Failed to spend the duplicate: {}", e);
+                tracing::error!("{}", &error_message);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ServerError { error_message }),
+                )
+                    .into_response();
+            }
+        };
+        (status_code, Json(ServerError::with_message(error_message))).into_response()
+    }
+}
+
+/// Converts a duplicate roll into credits or spends it on its character. The roll is marked as
+/// consumed first so that it can't be spent twice, and is restored if what it was spent on fails.
+pub async fn use_duplicate(
+    repository: &dyn Repository,
+    user_id: &str,
+    roll_id: i32,
+    action: DuplicateAction,
+    actor: &str,
+) -> Result<DuplicateResult, DuplicateError> {
+    let _guard = ROLL_LOCKS.lock([user_id]).await;
+
+    let user_roll = repository
+        .get_user_roll(user_id, roll_id)
+        .await
+        .map_err(DuplicateError::Repository)?
+        .ok_or(DuplicateError::RollNotFound)?;
+    if user_roll.consumed {
+        return Err(DuplicateError::AlreadyConsumed);
+    }
+    if roll::get_listed_roll_ids(repository, user_id)
+        .await
+        .map_err(DuplicateError::Repository)?
+        .contains(&roll_id)
+    {
+        return Err(DuplicateError::Listed);
+    }
+    let character_rolls = repository
+        .get_user_character_rolls(user_id, &[user_roll.mal_character_id])
+        .await
+        .map_err(DuplicateError::Repository)?;
+    if !roll::is_duplicate(&character_rolls, &user_roll) {
+        return Err(DuplicateError::NotDuplicate);
+    }
+
+    let rules = &CONFIGURATION.roll_rules;
+    let progress = repository
+        .get_character_progress(user_id, user_roll.mal_character_id)
+        .await
+        .map_err(DuplicateError::Repository)?
+        .unwrap_or_else(|| CharacterProgress::new(user_id, user_roll.mal_character_id));
+    let (credits_awarded, progress) = match action {
+        DuplicateAction::Convert => {
+            let rarity = repository
+                .get_mal_character(user_roll.mal_character_id)
+                .await
+                .map_err(DuplicateError::Repository)?
+                .map(|mal_character| mal_character.rarity)
+                .unwrap_or_default();
+            (rules.get_duplicate_credits(rarity), progress)
+        }
+        DuplicateAction::Level => {
+            if progress.level >= rules.max_character_level {
+                return Err(DuplicateError::MaxLevel);
+            }
+            let progress = CharacterProgress {
+                level: progress.level + 1,
+                ..progress
+            };
+            (0, progress)
+        }
+        DuplicateAction::Affinity => {
+            if progress.affinity >= rules.max_character_affinity {
+                return Err(DuplicateError::MaxAffinity);
+            }
+            let progress = CharacterProgress {
+                affinity: progress.affinity + 1,
+                ..progress
+            };
+            (0, progress)
+        }
+    };
+
+    repository
+        .update_user_roll(UserRoll {
+            consumed: true,
+            ..user_roll.clone()
+        })
+        .await
+        .map_err(DuplicateError::Repository)?;
+
+    let result = match action {
+        DuplicateAction::Convert => {
+            let change = CreditChange::new(actor, CreditTransactionReason::DuplicateConversion);
+            update_credit(repository, user_id, &change, |credit| {
+                Ok(UserCredit {
+                    credits: credit.credits + credits_awarded,
+                    ..credit
+                })
+            })
+            .await
+            .map(|_| ())
+            .map_err(DuplicateError::Credit)
+        }
+        DuplicateAction::Level | DuplicateAction::Affinity => repository
+            .upsert_character_progress(progress.clone())
+            .await
+            .map_err(DuplicateError::Repository),
+    };
+
+    if let Err(e) = result {
+        if let Err(restore_error) = repository.update_user_roll(user_roll).await {
+            tracing::error!(
+                "Failed to restore roll {} of user {} after failing to spend it: {}",
+                roll_id,
+                user_id,
+                restore_error
+            );
+        }
+        return Err(e);
+    }
+
+    Ok(DuplicateResult {
+        roll_id,
+        mal_character_id: progress.mal_character_id,
+        action,
+        credits_awarded,
+        level: progress.level,
+        affinity: progress.affinity,
+    })
+}
+
+/// The characters the user owns at least one unspent copy of, in character ID order.
+pub async fn get_collection(
+    repository: &dyn Repository,
+    user_id: &str,
+) -> Result<Vec<CollectionEntry>, RepositoryError> {
+    let copies = get_copies(&repository.get_character_roll_counts(user_id).await?);
+
+    let mal_characters = repository
+        .get_mal_characters(&copies.keys().copied().collect::<Vec<_>>())
+        .await?
+        .into_iter()
+        .map(|mal_character| (mal_character.character_id, mal_character))
+        .collect::<BTreeMap<_, _>>();
+    let progresses = repository.get_character_progresses(user_id).await?;
+    Ok(copies
+        .into_iter()
+        .map(|(mal_character_id, copies)| {
+            let mal_character = mal_characters
+                .get(&mal_character_id)
+                .cloned()
+                .unwrap_or_default();
+            let progress = progresses
+                .iter()
+                .find(|progress| progress.mal_character_id == mal_character_id)
+                .cloned()
+                .unwrap_or_else(|| CharacterProgress::new(user_id, mal_character_id));
+            CollectionEntry {
+                rarity: mal_character.rarity,
+                mal_character,
+                copies,
+                duplicates: copies - 1,
+                level: progress.level,
+                affinity: progress.affinity,
+            }
+        })
+        .collect())
+}
+
+/// How much of the character pool the user has collected, overall and by rarity.
+pub async fn get_roll_summary(
+    repository: &dyn Repository,
+    user_id: &str,
+) -> Result<RollSummary, RepositoryError> {
+    let roll_counts = repository.get_character_roll_counts(user_id).await?;
+    let mal_characters = repository.get_all_mal_characters().await?;
+    let roll_pity = repository.get_roll_pity(user_id).await?;
+
+    let copies = get_copies(&roll_counts);
+    let duplicates = copies.values().map(|copies| copies - 1).sum();
+
+    let rarity_breakdown = Rarity::ALL
+        .into_iter()
+        .map(|rarity| {
+            let characters = mal_characters
+                .iter()
+                .filter(|character| character.rarity == rarity)
+                .collect::<Vec<_>>();
+            let owned = characters
+                .iter()
+                .filter_map(|character| copies.get(&character.character_id))
+                .collect::<Vec<_>>();
+            RarityBreakdown {
+                rarity,
+                unique_characters: owned.len(),
+                total_characters: characters.len(),
+                completion_percentage: get_completion_percentage(owned.len(), characters.len()),
+                duplicates: owned.iter().map(|copies| *copies - 1).sum(),
+            }
+        })
+        .collect();
+
+    Ok(RollSummary {
+        user_id: user_id.to_string(),
+        total_rolls: roll_counts.iter().map(|count| count.rolls as usize).sum(),
+        unique_characters: copies.len(),
+        total_characters: mal_characters.len(),
+        completion_percentage: get_completion_percentage(copies.len(), mal_characters.len()),
+        duplicates,
+        rarity_breakdown,
+        pity: roll::get_pity_status(roll_pity.map(|pity| pity.misses).unwrap_or_default()),
+    })
+}
+
+/// How many unspent copies the user has of each character they own.
+fn get_copies(roll_counts: &[CharacterRollCount]) -> BTreeMap<i32, usize> {
+    roll_counts
+        .iter()
+        .filter(|count| count.copies > 0)
+        .map(|count| (count.mal_character_id, count.copies as usize))
+        .collect()
+}
+
+fn get_completion_percentage(owned: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        owned as f64 / total as f64 * 100.0
+    }
+}
+
+/// The users who still have at least one copy of the character, or `None` if the character
+/// doesn't exist.
+pub async fn get_character_owners(
+    repository: &dyn Repository,
+    mal_character_id: i32,
+) -> Result<Option<CharacterOwners>, RepositoryError> {
+    let Some(mal_character) = repository.get_mal_character(mal_character_id).await? else {
+        return Ok(None);
+    };
+
+    let mut copies = BTreeMap::<String, usize>::new();
+    for user_roll in repository
+        .get_character_rolls(mal_character_id)
+        .await?
+        .into_iter()
+        .filter(|user_roll| !user_roll.consumed)
+    {
+        *copies.entry(user_roll.user_id).or_default() += 1;
+    }
+
+    Ok(Some(CharacterOwners {
+        mal_character,
+        owners: copies
+            .into_iter()
+            .map(|(user_id, copies)| CharacterOwner { user_id, copies })
+            .collect(),
+    }))
+}
@@ -1,4 +1,7 @@
-use crate::model::configuration::Configuration;
+use crate::model::configuration::{
+    Configuration, LotteryRules, MarketRules, RewardRules, RollRules, StorageBackend, TradeRules,
+};
+#[cfg(not(test))]
 use crate::shared::constants::CONFIG_DIRECTORY;
 use once_cell::sync::Lazy;
 
@@ -7,12 +10,49 @@ pub static CONFIGURATION: Lazy<Configuration> =
 
 const CONFIGURATION_FILE_NAME: &str = "/config.toml";
 
+#[cfg(not(test))]
 fn initialize() -> anyhow::Result<Configuration> {
-    if !std::path::Path::new(CONFIG_DIRECTORY).exists() {
-        std::fs::create_dir(CONFIG_DIRECTORY)?;
+    load(CONFIG_DIRECTORY)
+}
+
+/// Tests run against the in-memory backend with the default rules, without reading or writing
+/// the configuration file.
+#[cfg(test)]
+fn initialize() -> anyhow::Result<Configuration> {
+    let configuration = Configuration {
+        database_url: String::new(),
+        jwt_secret: "test-secret".to_string(),
+        bot_user_name: "test-bot".to_string(),
+        bot_user_pass: "test-pass".to_string(),
+        web_driver_address: String::new(),
+        server_bind_point: String::new(),
+        server_address: String::new(),
+        dialog_quality: 75,
+        log_level: "DEBUG".to_string(),
+        cosmos_db_primary_key: String::new(),
+        cosmos_db_database_name: String::new(),
+        cosmos_db_account: String::new(),
+        swc_publication_endpoints: vec![],
+        swc_check_interval: 3,
+        storage_backend: StorageBackend::InMemory,
+        lottery_rules: LotteryRules::default(),
+        reward_rules: RewardRules::default(),
+        roll_rules: RollRules::default(),
+        trade_rules: TradeRules::default(),
+        market_rules: MarketRules::default(),
+    };
+    configuration.validate()?;
+    Ok(configuration)
+}
+
+/// Reads the configuration file in the given directory. If there is none yet, the configuration
+/// is read from environment variables and written there.
+fn load(directory: &str) -> anyhow::Result<Configuration> {
+    if !std::path::Path::new(directory).exists() {
+        std::fs::create_dir(directory)?;
     }
 
-    let configuration_path = String::from(CONFIG_DIRECTORY) + CONFIGURATION_FILE_NAME;
+    let configuration_path = String::from(directory) + CONFIGURATION_FILE_NAME;
     if !std::path::Path::new(&configuration_path).exists() {
         // Read from environment variables
         let configuration = Configuration {
@@ -25,18 +65,63 @@ fn initialize() -> anyhow::Result<Configuration> {
             server_address: std::env::var("SERVER_ADDRESS")?,
             dialog_quality: 75,
             log_level: "DEBUG".to_string(),
-            cosmos_db_primary_key: std::env::var("COSMOS_DB_PRIMARY_KEY")?,
-            cosmos_db_database_name: std::env::var("COSMOS_DB_DATABASE_NAME")?,
-            cosmos_db_account: std::env::var("COSMOS_DB_ACCOUNT")?,
+            cosmos_db_primary_key: std::env::var("COSMOS_DB_PRIMARY_KEY").unwrap_or_default(),
+            cosmos_db_database_name: std::env::var("COSMOS_DB_DATABASE_NAME").unwrap_or_default(),
+            cosmos_db_account: std::env::var("COSMOS_DB_ACCOUNT").unwrap_or_default(),
             swc_publication_endpoints: vec![],
             swc_check_interval: 3,
+            storage_backend: match std::env::var("STORAGE_BACKEND") {
+                Ok(name) => StorageBackend::from_name(&name)?,
+                Err(_) => StorageBackend::default(),
+            },
+            lottery_rules: LotteryRules::default(),
+            reward_rules: RewardRules::default(),
+            roll_rules: RollRules::default(),
+            trade_rules: TradeRules::default(),
+            market_rules: MarketRules::default(),
         };
+        configuration.validate()?;
         let serialized_toml = toml::to_string_pretty(&configuration)?;
         std::fs::write(&configuration_path, serialized_toml)?;
         Ok(configuration)
     } else {
         let toml = std::fs::read_to_string(&configuration_path)?;
         let deserialized_toml = toml::from_str::<Configuration>(&toml)?;
+        deserialized_toml.validate()?;
         Ok(deserialized_toml)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_configuration_files() {
+        let directory = std::env::temp_dir().join(format!("tetsuki-{}", uuid::Uuid::new_v4()));
+        let directory = directory.to_str().unwrap();
+        std::fs::create_dir(directory).unwrap();
+
+        let mut configuration = initialize().unwrap();
+        configuration.storage_backend = StorageBackend::Postgres;
+        let configuration_path = String::from(directory) + CONFIGURATION_FILE_NAME;
+        std::fs::write(
+            &configuration_path,
+            toml::to_string_pretty(&configuration).unwrap(),
+        )
+        .unwrap();
+        let loaded = load(directory).unwrap();
+        assert_eq!(loaded.storage_backend, StorageBackend::Postgres);
+        assert_eq!(loaded.jwt_secret, configuration.jwt_secret);
+
+        configuration.lottery_rules.numbers_per_ticket = 0;
+        std::fs::write(
+            &configuration_path,
+            toml::to_string_pretty(&configuration).unwrap(),
+        )
+        .unwrap();
+        assert!(load(directory).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,2 +1,4 @@
 pub const ASSET_DIRECTORY: &str = "asset";
+#[cfg(not(test))]
 pub const CONFIG_DIRECTORY: &str = "config";
+pub const CONTINUATION_HEADER: &str = "x-continuation";
@@ -0,0 +1,224 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::credit_transaction::{CreditTransaction, CreditTransactionReason};
+use crate::model::errors::ServerError;
+use crate::model::user_credit::{UserCredit, UserCreditUpdateInfo, UserCreditUpdateOpt};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rand::Rng;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// How many times a credit update is attempted before giving up on concurrent writers.
+const MAX_CREDIT_UPDATE_ATTEMPTS: u32 = 5;
+const CREDIT_UPDATE_BACKOFF_MILLIS: u64 = 20;
+
+#[derive(Debug)]
+pub enum CreditUpdateError {
+    NotFound,
+    InsufficientCredits,
+    Conflict,
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for CreditUpdateError {
+    fn into_response(self) -> Response {
+        match self {
+            CreditUpdateError::NotFound => (
+                StatusCode::NOT_FOUND,
+                Json(ServerError::with_message(
+                    "Cannot update user's credit because the specified user doesn't exist.",
+                )),
+            )
+                .into_response(),
+            CreditUpdateError::InsufficientCredits => (
+                StatusCode::BAD_REQUEST,
+                Json(ServerError::with_message(
+                    "The specified user doesn't have enough credits.",
+                )),
+            )
+                .into_response(),
+            CreditUpdateError::Conflict => (
+                StatusCode::CONFLICT,
+                Json(ServerError::with_message(
+                    "The user's credit was modified by another request. Please try again.",
+                )),
+            )
+                .into_response(),
+            CreditUpdateError::Repository(e) => {
+                let error_message = format!("{}", e);
+                tracing::error!("{}", &error_message);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ServerError { error_message }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Who changed a user's credit and why, recorded in the credit ledger.
+#[derive(Clone, Debug)]
+pub struct CreditChange {
+    pub actor: String,
+    pub reason: CreditTransactionReason,
+}
+
+impl CreditChange {
+    pub fn new<S: Into<String>>(actor: S, reason: CreditTransactionReason) -> Self {
+        CreditChange {
+            actor: actor.into(),
+            reason,
+        }
+    }
+}
+
+/// Applies `update` to the user's current credit and writes it back conditionally on the
+/// version that was read, re-reading and retrying with a short backoff when another request
+/// modified the credit in the meantime. Successful changes are appended to the credit ledger.
+pub async fn update_credit<F>(
+    repository: &dyn Repository,
+    user_id: &str,
+    change: &CreditChange,
+    update: F,
+) -> Result<UserCredit, CreditUpdateError>
+where
+    F: Fn(UserCredit) -> Result<UserCredit, CreditUpdateError>,
+{
+    for attempt in 1..=MAX_CREDIT_UPDATE_ATTEMPTS {
+        let versioned = repository
+            .get_versioned_user_credit(user_id)
+            .await
+            .map_err(CreditUpdateError::Repository)?
+            .ok_or(CreditUpdateError::NotFound)?;
+
+        let previous_credits = versioned.document.credits;
+        let new_document = update(versioned.document)?;
+        match repository
+            .replace_user_credit(new_document.clone(), &versioned.version)
+            .await
+        {
+            Ok(_) => {
+                record_transaction(
+                    repository,
+                    &new_document,
+                    new_document.credits - previous_credits,
+                    change,
+                )
+                .await;
+                return Ok(new_document);
+            }
+            Err(RepositoryError::Conflict) => {
+                tracing::warn!(
+                    "Credit update for user {} conflicted (attempt {}/{}).",
+                    user_id,
+                    attempt,
+                    MAX_CREDIT_UPDATE_ATTEMPTS
+                );
+                let jitter = rand::thread_rng().gen_range(0..CREDIT_UPDATE_BACKOFF_MILLIS);
+                let backoff = CREDIT_UPDATE_BACKOFF_MILLIS * attempt as u64 + jitter;
+                tokio::time::sleep(tokio::time::Duration::from_millis(backoff)).await;
+            }
+            Err(e) => return Err(CreditUpdateError::Repository(e)),
+        }
+    }
+
+    Err(CreditUpdateError::Conflict)
+}
+
+/// Adds the amount to the user's credits, or takes it away if it is negative. Fails rather than
+/// leave the user with negative credits.
+pub async fn pay(
+    repository: &dyn Repository,
+    user_id: &str,
+    amount: i32,
+    reason: CreditTransactionReason,
+    actor: &str,
+) -> Result<(), CreditUpdateError> {
+    let change = CreditChange::new(actor, reason);
+    update_credit(repository, user_id, &change, |credit| {
+        if credit.credits + amount < 0 {
+            return Err(CreditUpdateError::InsufficientCredits);
+        }
+        Ok(UserCredit {
+            credits: credit.credits + amount,
+            ..credit
+        })
+    })
+    .await
+    .map(|_| ())
+}
+
+/// Undoes a payment made for the trade offer, listing, etc. with the given ID. The request that
+/// needed the refund is already failing, so a failed refund is logged rather than returned.
+pub async fn refund(
+    repository: &dyn Repository,
+    user_id: &str,
+    amount: i32,
+    reason: CreditTransactionReason,
+    reference_id: &str,
+    actor: &str,
+) {
+    if let Err(e) = pay(repository, user_id, amount, reason, actor).await {
+        tracing::error!(
+            "Failed to refund {} credits to user {} for {} ({}): {:?}",
+            amount,
+            user_id,
+            reference_id,
+            reason.as_str(),
+            e
+        );
+    }
+}
+
+async fn record_transaction(
+    repository: &dyn Repository,
+    user_credit: &UserCredit,
+    amount: i32,
+    change: &CreditChange,
+) {
+    let transaction = CreditTransaction {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_credit.user_id.clone(),
+        amount,
+        reason: change.reason,
+        balance_after: user_credit.credits,
+        created_at: OffsetDateTime::now_utc(),
+        actor: change.actor.clone(),
+    };
+
+    // The credit itself has already been committed at this point, so a failure here is
+    // logged with the full entry rather than surfaced to the caller.
+    if let Err(e) = repository.add_credit_transaction(transaction.clone()).await {
+        tracing::error!(
+            "Failed to record credit transaction {:?}: {}",
+            transaction,
+            e
+        );
+    }
+}
+
+pub async fn adjust_credit(
+    repository: &dyn Repository,
+    user_id: String,
+    request: UserCreditUpdateInfo,
+    opt: UserCreditUpdateOpt,
+    change: CreditChange,
+) -> Response {
+    let result = update_credit(repository, &user_id, &change, |user_credit| {
+        Ok(UserCredit {
+            credits: match opt {
+                UserCreditUpdateOpt::Plus => user_credit.credits + request.credit,
+                UserCreditUpdateOpt::Minus => user_credit.credits - request.credit,
+            },
+            ..user_credit
+        })
+    })
+    .await;
+
+    match result {
+        Ok(new_document) => (StatusCode::OK, Json(new_document)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,562 @@
+use crate::db::repository::{Repository, RepositoryResult};
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::lottery::draw::{
+    LotteryCommitment, LotteryDraw, LotteryDrawDetails, LotteryDrawResult, LotteryDrawTier,
+    LotteryDrawVerification, LotteryDrawVerificationStep, PENDING_COMMITMENT_ID,
+};
+use crate::model::lottery::jackpot::{LotteryJackpot, JACKPOT_ID};
+use crate::model::lottery::UserLottery;
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::pay;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Add;
+use std::sync::Arc;
+use time::{OffsetDateTime, Time};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const SCHEDULED_DRAW_ACTOR: &str = "scheduler";
+
+/// Only one draw may run at a time, otherwise tickets could be scored twice.
+static DRAW_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Generates a sorted set of distinct lottery numbers, as used for both draws and quick-picks.
+pub fn generate_numbers() -> Vec<u8> {
+    let rules = &CONFIGURATION.lottery_rules;
+    let mut rng = rand::thread_rng();
+    let mut numbers = rand::seq::index::sample(
+        &mut rng,
+        rules.max_number as usize,
+        rules.numbers_per_ticket as usize,
+    )
+    .into_iter()
+    .map(|index| index as u8 + 1)
+    .collect::<Vec<_>>();
+    numbers.sort_unstable();
+    numbers
+}
+
+pub fn hash_seed(seed: &str) -> String {
+    hex::encode(Sha256::digest(seed.as_bytes()))
+}
+
+/// Deterministically derives `count` distinct numbers between 1 and `max_number` from a draw's
+/// seed, along with every step taken so that players can follow the derivation.
+/// See [`describe_derivation`].
+pub fn derive_numbers(
+    seed: &str,
+    count: usize,
+    max_number: u8,
+) -> (Vec<u8>, Vec<LotteryDrawVerificationStep>) {
+    let range = max_number as u64;
+    // Values at or above the largest multiple of the range are rejected to avoid modulo bias.
+    let limit = (1_u64 << 32) / range * range;
+
+    let mut numbers = Vec::with_capacity(count);
+    let mut steps = vec![];
+    let mut counter = 0_u32;
+    while numbers.len() < count {
+        let digest = Sha256::digest(format!("{}:{}", seed, counter).as_bytes());
+        let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        let number = Some(value as u64)
+            .filter(|value| *value < limit)
+            .map(|value| (value % range) as u8 + 1)
+            .filter(|number| !numbers.contains(number));
+        if let Some(number) = number {
+            numbers.push(number);
+        }
+
+        steps.push(LotteryDrawVerificationStep {
+            counter,
+            digest: hex::encode(digest),
+            value,
+            number,
+        });
+        counter += 1;
+    }
+
+    numbers.sort_unstable();
+    (numbers, steps)
+}
+
+pub fn describe_derivation(count: usize, max_number: u8) -> String {
+    let range = max_number as u64;
+    format!(
+        "The seed hash is the hex-encoded SHA-256 digest of the seed. \
+        For counter = 0, 1, 2, ..., compute the SHA-256 digest of the UTF-8 string \"<seed>:<counter>\" \
+        and read its first 4 bytes as a big-endian unsigned integer. \
+        Values of {} or above are rejected to avoid modulo bias, otherwise the number is value % {} + 1. \
+        Numbers that were already drawn are skipped until {} distinct numbers are found, \
+        which are then sorted in ascending order.",
+        (1_u64 << 32) / range * range,
+        range,
+        count
+    )
+}
+
+pub fn verify_lottery_draw(lottery_draw: &LotteryDraw) -> Option<LotteryDrawVerification> {
+    // Draws made before seeds were committed can't be verified.
+    if lottery_draw.seed.is_empty() {
+        return None;
+    }
+
+    let count = lottery_draw.winning_numbers.len();
+    let (winning_numbers, steps) =
+        derive_numbers(&lottery_draw.seed, count, lottery_draw.max_number);
+    Some(LotteryDrawVerification {
+        draw_id: lottery_draw.id.clone(),
+        seed: lottery_draw.seed.clone(),
+        seed_hash: lottery_draw.seed_hash.clone(),
+        seed_hash_matches: hash_seed(&lottery_draw.seed) == lottery_draw.seed_hash,
+        winning_numbers_match: winning_numbers == lottery_draw.winning_numbers,
+        winning_numbers,
+        algorithm: describe_derivation(count, lottery_draw.max_number),
+        steps,
+    })
+}
+
+fn create_commitment() -> LotteryCommitment {
+    let seed = hex::encode(rand::thread_rng().gen::<[u8; 32]>());
+    LotteryCommitment {
+        id: PENDING_COMMITMENT_ID.to_string(),
+        draw_id: Uuid::new_v4().to_string(),
+        seed_hash: hash_seed(&seed),
+        seed,
+        committed_at: OffsetDateTime::now_utc(),
+    }
+}
+
+/// Returns the commitment of the upcoming draw, committing to a new seed if there is none.
+/// The caller has to hold the draw lock so that a published commitment is never replaced.
+async fn ensure_commitment(repository: &dyn Repository) -> RepositoryResult<LotteryCommitment> {
+    if let Some(commitment) = repository.get_lottery_commitment().await? {
+        return Ok(commitment);
+    }
+
+    let commitment = create_commitment();
+    repository
+        .upsert_lottery_commitment(commitment.clone())
+        .await?;
+    Ok(commitment)
+}
+
+pub async fn get_lottery_commitment(
+    repository: &dyn Repository,
+) -> RepositoryResult<LotteryCommitment> {
+    let _guard = DRAW_LOCK.lock().await;
+    ensure_commitment(repository).await
+}
+
+pub fn count_matches(ticket: &[u8], winning_numbers: &[u8]) -> u8 {
+    winning_numbers
+        .iter()
+        .filter(|number| ticket.contains(number))
+        .count() as u8
+}
+
+/// Tickets matching every winning number share the jackpot pool instead of a fixed prize.
+fn get_jackpot_matches() -> u8 {
+    CONFIGURATION.lottery_rules.numbers_per_ticket
+}
+
+/// The amount the jackpot pool starts at and is reset to after it has been won,
+/// which is the top tier prize of the payout table.
+pub fn get_jackpot_seed() -> i32 {
+    CONFIGURATION.lottery_rules.get_prize(get_jackpot_matches())
+}
+
+pub async fn get_jackpot(repository: &dyn Repository) -> RepositoryResult<LotteryJackpot> {
+    Ok(repository
+        .get_jackpot()
+        .await?
+        .unwrap_or_else(|| LotteryJackpot {
+            id: JACKPOT_ID.to_string(),
+            amount: get_jackpot_seed(),
+            updated_at: OffsetDateTime::now_utc(),
+        }))
+}
+
+/// Routes the configured share of a ticket purchase into the jackpot pool.
+pub async fn contribute_to_jackpot(repository: &dyn Repository, cost: i32) {
+    let contribution = cost * CONFIGURATION.lottery_rules.jackpot_share_percent as i32 / 100;
+    if contribution <= 0 {
+        return;
+    }
+
+    if let Err(e) = repository
+        .add_to_jackpot(contribution, get_jackpot_seed())
+        .await
+    {
+        tracing::error!(
+            "Failed to add {} credits to the jackpot pool: {}",
+            contribution,
+            e
+        );
+    }
+}
+
+/// Derives the winning numbers from the committed seed, scores and archives every outstanding
+/// ticket, then pays out the prizes and commits to the seed of the next draw.
+/// Tickets bought while the draw is running take part in the next one.
+pub async fn draw_lottery(
+    repository: &dyn Repository,
+    actor: &str,
+) -> RepositoryResult<LotteryDrawDetails> {
+    let _guard = DRAW_LOCK.lock().await;
+
+    let rules = &CONFIGURATION.lottery_rules;
+    let jackpot_matches = get_jackpot_matches();
+    let commitment = ensure_commitment(repository).await?;
+    let draw_id = commitment.draw_id.clone();
+    let drawn_at = OffsetDateTime::now_utc();
+    let (winning_numbers, _) = derive_numbers(
+        &commitment.seed,
+        rules.numbers_per_ticket as usize,
+        rules.max_number,
+    );
+    let jackpot = get_jackpot(repository).await?.amount;
+    let user_ids = repository
+        .get_all_user_lotteries()
+        .await?
+        .into_iter()
+        .filter(|user_lottery| !user_lottery.lotteries.is_empty())
+        .map(|user_lottery| user_lottery.user_id)
+        .collect::<Vec<_>>();
+
+    let mut results = vec![];
+    for user_id in user_ids {
+        let tickets = match repository.take_user_lotteries(&user_id).await {
+            Ok(tickets) => tickets,
+            Err(e) => {
+                // The user's tickets stay where they are and take part in the next draw.
+                tracing::error!("Failed to take lotteries of user {}: {}", &user_id, e);
+                continue;
+            }
+        };
+
+        // Result IDs are derived from the draw so that archiving the draw again after a failure
+        // replaces the results stored by the failed attempt instead of duplicating them.
+        results.extend(tickets.into_iter().enumerate().map(|(index, ticket)| {
+            let matches = count_matches(&ticket, &winning_numbers);
+            LotteryDrawResult {
+                id: format!("{}:{}:{}", &draw_id, &user_id, index),
+                draw_id: draw_id.clone(),
+                user_id: user_id.clone(),
+                ticket,
+                matches,
+                prize: rules.get_prize(matches),
+                drawn_at,
+                paid: false,
+            }
+        }));
+    }
+
+    // The pool is split evenly among the top tier winners, and any remainder rolls over.
+    let jackpot_winners = results
+        .iter()
+        .filter(|result| result.matches == jackpot_matches)
+        .count() as i32;
+    let jackpot_share = if jackpot_winners > 0 {
+        jackpot / jackpot_winners
+    } else {
+        jackpot
+    };
+    for result in results
+        .iter_mut()
+        .filter(|result| result.matches == jackpot_matches)
+    {
+        result.prize = jackpot_share;
+    }
+    for result in results.iter_mut().filter(|result| result.prize <= 0) {
+        result.paid = true;
+    }
+
+    let mut tiers = rules
+        .payouts
+        .iter()
+        .filter(|payout| payout.matches != jackpot_matches)
+        .map(|payout| LotteryDrawTier {
+            matches: payout.matches,
+            winners: results
+                .iter()
+                .filter(|result| result.matches == payout.matches)
+                .count() as i32,
+            prize: payout.prize,
+        })
+        .collect::<Vec<_>>();
+    tiers.push(LotteryDrawTier {
+        matches: jackpot_matches,
+        winners: jackpot_winners,
+        prize: jackpot_share,
+    });
+    tiers.sort_by_key(|tier| std::cmp::Reverse(tier.matches));
+
+    let lottery_draw = LotteryDraw {
+        id: draw_id,
+        winning_numbers,
+        max_number: rules.max_number,
+        drawn_at,
+        actor: actor.to_string(),
+        total_tickets: results.len() as i32,
+        tiers,
+        jackpot,
+        seed_hash: commitment.seed_hash,
+        seed: commitment.seed,
+        committed_at: Some(commitment.committed_at),
+    };
+
+    if let Err(e) = repository
+        .add_lottery_draw(lottery_draw.clone(), results.clone())
+        .await
+    {
+        tracing::error!("Failed to archive lottery draw {}: {}", &lottery_draw.id, e);
+        restore_tickets(repository, &results).await;
+        return Err(e);
+    }
+
+    if let Err(e) = repository
+        .upsert_lottery_commitment(create_commitment())
+        .await
+    {
+        // The next draw commits to a seed itself, which is then visible from its `committed_at`.
+        tracing::error!(
+            "Failed to commit to the seed of the next lottery draw: {}",
+            e
+        );
+    }
+
+    if jackpot_winners > 0 {
+        // Contributions made while the draw was running stay in the pool.
+        let paid = jackpot_share * jackpot_winners;
+        if let Err(e) = repository
+            .add_to_jackpot(get_jackpot_seed() - paid, get_jackpot_seed())
+            .await
+        {
+            tracing::error!(
+                "Failed to reset the jackpot pool after lottery draw {}: {}",
+                &lottery_draw.id,
+                e
+            );
+        }
+    }
+
+    let paid_result_ids = pay_out(repository, actor).await;
+    for result in results
+        .iter_mut()
+        .filter(|result| paid_result_ids.contains(&result.id))
+    {
+        result.paid = true;
+    }
+    tracing::info!(
+        "Lottery draw {} finished with winning numbers {:?} and {} tickets.",
+        &lottery_draw.id,
+        &lottery_draw.winning_numbers,
+        lottery_draw.total_tickets
+    );
+    Ok(LotteryDrawDetails {
+        draw: lottery_draw,
+        results,
+    })
+}
+
+/// Puts the tickets of a draw that couldn't be archived back to their owners.
+async fn restore_tickets(repository: &dyn Repository, results: &[LotteryDrawResult]) {
+    let mut tickets = BTreeMap::<&str, Vec<Vec<u8>>>::new();
+    for result in results.iter() {
+        tickets
+            .entry(result.user_id.as_str())
+            .or_default()
+            .push(result.ticket.clone());
+    }
+
+    for (user_id, lotteries) in tickets {
+        let user_lottery = UserLottery {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            lotteries,
+        };
+        if let Err(e) = repository.add_user_lotteries(user_lottery).await {
+            tracing::error!("Failed to restore lotteries of user {}: {}", user_id, e);
+        }
+    }
+}
+
+/// Pays every prize that hasn't been paid yet, including those left over by failed payments in
+/// earlier draws, and returns the IDs of the results that were paid. The results are marked as
+/// paid before the credits move, so a prize is never paid twice, and marked unpaid again if the
+/// payment fails so that the next draw retries it.
+async fn pay_out(repository: &dyn Repository, actor: &str) -> BTreeSet<String> {
+    let unpaid_results = match repository.get_unpaid_lottery_results().await {
+        Ok(results) => results,
+        Err(e) => {
+            tracing::error!("Failed to retrieve unpaid lottery prizes: {}", e);
+            return BTreeSet::new();
+        }
+    };
+    let mut prizes = BTreeMap::<String, Vec<LotteryDrawResult>>::new();
+    for result in unpaid_results {
+        prizes
+            .entry(result.user_id.clone())
+            .or_default()
+            .push(result);
+    }
+
+    let mut paid_result_ids = BTreeSet::new();
+    for (user_id, results) in prizes {
+        let prize = results.iter().map(|result| result.prize).sum::<i32>();
+        if let Err(e) = repository
+            .set_lottery_results_paid(results.clone(), true)
+            .await
+        {
+            tracing::error!(
+                "Failed to mark lottery prizes of user {} as paid, retrying with the next draw: {}",
+                &user_id,
+                e
+            );
+            continue;
+        }
+
+        if let Err(e) = pay(
+            repository,
+            &user_id,
+            prize,
+            CreditTransactionReason::LotteryPrize,
+            actor,
+        )
+        .await
+        {
+            tracing::error!(
+                "Failed to pay {} credits of lottery prizes to user {}, retrying with the next draw: {:?}",
+                prize,
+                &user_id,
+                e
+            );
+            if let Err(e) = repository.set_lottery_results_paid(results, false).await {
+                tracing::error!(
+                    "Failed to mark lottery prizes of user {} as unpaid again, {} credits are owed: {}",
+                    &user_id,
+                    prize,
+                    e
+                );
+            }
+            continue;
+        }
+        paid_result_ids.extend(results.into_iter().map(|result| result.id));
+    }
+    paid_result_ids
+}
+
+pub async fn initialize_lottery_draw(repository: Arc<dyn Repository>) {
+    if let Err(e) = get_lottery_commitment(repository.as_ref()).await {
+        tracing::error!(
+            "Failed to commit to the seed of the next lottery draw: {}",
+            e
+        );
+    }
+    {
+        let _guard = DRAW_LOCK.lock().await;
+        pay_out(repository.as_ref(), SCHEDULED_DRAW_ACTOR).await;
+    }
+
+    let schedule = &CONFIGURATION.lottery_rules.draw_schedule;
+    if !schedule.enabled {
+        return;
+    }
+
+    // The schedule has been validated at startup.
+    let (Ok(weekday), Ok(draw_time)) =
+        (schedule.get_weekday(), Time::from_hms(schedule.hour, 0, 0))
+    else {
+        tracing::error!("Invalid lottery draw schedule, scheduled draws are disabled.");
+        return;
+    };
+
+    let now = OffsetDateTime::now_utc();
+    let mut next_draw = now.replace_time(draw_time);
+    while next_draw.weekday() != weekday || next_draw <= now {
+        next_draw = next_draw.add(time::Duration::days(1));
+    }
+
+    loop {
+        let duration = next_draw - OffsetDateTime::now_utc();
+        if duration.is_positive() {
+            tokio::time::sleep(tokio::time::Duration::from_secs_f32(
+                duration.as_seconds_f32(),
+            ))
+            .await;
+        }
+
+        if let Err(e) = draw_lottery(repository.as_ref(), SCHEDULED_DRAW_ACTOR).await {
+            tracing::error!("Scheduled lottery draw failed: {}", e);
+        }
+        next_draw = next_draw.add(time::Duration::days(7));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_draw(seed: &str) -> LotteryDraw {
+        let (winning_numbers, _) = derive_numbers(seed, 6, 49);
+        LotteryDraw {
+            id: "draw".to_string(),
+            winning_numbers,
+            max_number: 49,
+            drawn_at: OffsetDateTime::now_utc(),
+            actor: "test".to_string(),
+            total_tickets: 0,
+            tiers: vec![],
+            jackpot: 0,
+            seed_hash: hash_seed(seed),
+            seed: seed.to_string(),
+            committed_at: None,
+        }
+    }
+
+    #[test]
+    fn derives_distinct_numbers_from_the_seed() {
+        let (numbers, steps) = derive_numbers("seed", 6, 49);
+        assert_eq!(derive_numbers("seed", 6, 49).0, numbers);
+        assert_ne!(derive_numbers("another seed", 6, 49).0, numbers);
+        assert_eq!(numbers.len(), 6);
+        assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(numbers.iter().all(|number| (1..=49).contains(number)));
+
+        let mut drawn = steps
+            .iter()
+            .filter_map(|step| step.number)
+            .collect::<Vec<_>>();
+        drawn.sort_unstable();
+        assert_eq!(drawn, numbers);
+        assert!(steps
+            .iter()
+            .enumerate()
+            .all(|(counter, step)| step.counter == counter as u32));
+
+        // Every number of a range that small has to be drawn, which takes rejected steps too.
+        let (numbers, steps) = derive_numbers("seed", 3, 3);
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert!(steps.len() >= 3);
+    }
+
+    #[test]
+    fn verifies_draws_against_their_seed() {
+        let verification = verify_lottery_draw(&create_draw("seed")).unwrap();
+        assert!(verification.seed_hash_matches);
+        assert!(verification.winning_numbers_match);
+
+        let mut lottery_draw = create_draw("seed");
+        lottery_draw.winning_numbers = vec![1, 2, 3, 4, 5, 6];
+        lottery_draw.seed_hash = hash_seed("another seed");
+        let verification = verify_lottery_draw(&lottery_draw).unwrap();
+        assert!(!verification.seed_hash_matches);
+        assert!(!verification.winning_numbers_match);
+
+        lottery_draw.seed = String::new();
+        assert!(verify_lottery_draw(&lottery_draw).is_none());
+    }
+}
@@ -0,0 +1,561 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::errors::ServerError;
+use crate::model::market::{ListingRequest, ListingStatus, ListingType, MarketListing};
+use crate::model::user_roll::UserRoll;
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::{pay, refund, CreditUpdateError};
+use crate::shared::roll;
+use crate::shared::roll_lock::{RollGuard, ROLL_LOCKS};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::ops::Add;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const SETTLEMENT_ACTOR: &str = "market";
+
+#[derive(Debug)]
+pub enum MarketError {
+    InvalidListing(String),
+    ListingNotFound,
+    /// Only the seller can do this to the listing.
+    NotSeller,
+    /// Fixed-price listings can't be bid on and auctions can't be bought outright.
+    WrongListingType(ListingType),
+    NotActive(ListingStatus),
+    /// The bid has to be at least the given amount.
+    BidTooLow(i32),
+    OwnListing,
+    /// Auctions can't be cancelled once somebody has bid on them.
+    HasBids,
+    /// The roll has been traded, converted or spent since it was listed.
+    RollUnavailable,
+    Credit(CreditUpdateError),
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for MarketError {
+    fn into_response(self) -> Response {
+        let (status_code, error_message) = match self {
+            MarketError::InvalidListing(error_message) => (StatusCode::BAD_REQUEST, error_message),
+            MarketError::ListingNotFound => (
+                StatusCode::NOT_FOUND,
+                "The specified listing is not found.".to_string(),
+            ),
+            MarketError::NotSeller => (
+                StatusCode::FORBIDDEN,
+                "Only the seller can cancel the listing.".to_string(),
+            ),
+            MarketError::WrongListingType(listing_type) => (
+                StatusCode::BAD_REQUEST,
+                match listing_type {
+                    ListingType::FixedPrice => {
+                        "The listing is for a fixed price and can't be bid on."
+                    }
+                    ListingType::Auction => "The listing is an auction and can only be bid on.",
+                }
+                .to_string(),
+            ),
+            MarketError::NotActive(status) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "The listing is no longer active, it is {}.",
+                    status.as_str().to_lowercase()
+                ),
+            ),
+            MarketError::BidTooLow(min_bid) => (
+                StatusCode::BAD_REQUEST,
+                format!("The bid has to be at least {} credits.", min_bid),
+            ),
+            MarketError::OwnListing => (
+                StatusCode::BAD_REQUEST,
+                "Users can't buy or bid on their own listings.".to_string(),
+            ),
+            MarketError::HasBids => (
+                StatusCode::CONFLICT,
+                "The auction can't be cancelled because it has bids.".to_string(),
+            ),
+            MarketError::RollUnavailable => (
+                StatusCode::CONFLICT,
+                "The listed roll is no longer available.".to_string(),
+            ),
+            MarketError::Credit(e) => return e.into_response(),
+            MarketError::Repository(e) => {
+                let error_message = format!("Failed to process the listing: {}", e);
+                tracing::error!("{}", &error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            }
+        };
+        (status_code, Json(ServerError { error_message })).into_response()
+    }
+}
+
+pub async fn create_listing(
+    repository: &dyn Repository,
+    request: ListingRequest,
+) -> Result<MarketListing, MarketError> {
+    let rules = &CONFIGURATION.market_rules;
+    if request.price < 1 {
+        return Err(MarketError::InvalidListing(
+            "The price has to be at least 1 credit.".to_string(),
+        ));
+    }
+    let ends_at = match request.listing_type {
+        ListingType::FixedPrice => None,
+        ListingType::Auction => {
+            let duration_hours = request
+                .duration_hours
+                .unwrap_or(rules.default_auction_hours);
+            if !(1..=rules.max_auction_hours).contains(&duration_hours) {
+                return Err(MarketError::InvalidListing(format!(
+                    "Auctions have to run between 1 and {} hours.",
+                    rules.max_auction_hours
+                )));
+            }
+            Some(OffsetDateTime::now_utc().add(time::Duration::hours(duration_hours)))
+        }
+    };
+
+    repository
+        .get_user_credit(&request.seller_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .ok_or_else(|| {
+            MarketError::InvalidListing("The seller's credit info is not found.".to_string())
+        })?;
+
+    let _guard = ROLL_LOCKS.lock([&request.seller_id]).await;
+
+    let user_roll = roll::get_available_rolls(repository, &request.seller_id, &[request.roll_id])
+        .await
+        .map_err(MarketError::Repository)?
+        .and_then(|user_rolls| user_rolls.into_iter().next())
+        .ok_or_else(|| MarketError::InvalidListing("The roll isn't available.".to_string()))?;
+
+    let listing = MarketListing {
+        id: Uuid::new_v4().to_string(),
+        seller_id: request.seller_id,
+        roll_id: user_roll.roll_id,
+        mal_character_id: user_roll.mal_character_id,
+        listing_type: request.listing_type,
+        price: request.price,
+        highest_bid: None,
+        highest_bidder_id: None,
+        status: ListingStatus::Active,
+        created_at: OffsetDateTime::now_utc(),
+        ends_at,
+        buyer_id: None,
+        sale_price: None,
+        fee: None,
+        settled_at: None,
+    };
+    repository
+        .upsert_market_listing(listing.clone())
+        .await
+        .map_err(MarketError::Repository)?;
+    Ok(listing)
+}
+
+/// Buys a fixed-price listing. The buyer pays first and is paid back if the sale can't go through.
+pub async fn buy(
+    repository: &dyn Repository,
+    listing_id: &str,
+    buyer_id: &str,
+    actor: &str,
+) -> Result<MarketListing, MarketError> {
+    let _guard = lock_listing(repository, listing_id, Some(buyer_id)).await?;
+
+    let listing = get_active_listing(repository, listing_id, ListingType::FixedPrice).await?;
+    if listing.seller_id == buyer_id {
+        return Err(MarketError::OwnListing);
+    }
+
+    let price = listing.price;
+    pay(
+        repository,
+        buyer_id,
+        -price,
+        CreditTransactionReason::MarketPurchase,
+        actor,
+    )
+    .await
+    .map_err(MarketError::Credit)?;
+
+    let listing_id = listing.id.clone();
+    match settle_sale(repository, listing, buyer_id, price, actor).await {
+        // Nothing on the listing refers to the buyer's payment yet, so it is theirs to take back.
+        Err(MarketError::Repository(e)) => {
+            give_back(repository, &listing_id, buyer_id, price, actor).await?;
+            Err(MarketError::Repository(e))
+        }
+        result => result,
+    }
+}
+
+/// Places a bid on an auction. The bid is taken from the bidder's credits right away, and the
+/// previous highest bid is paid back to whoever made it.
+pub async fn bid(
+    repository: &dyn Repository,
+    listing_id: &str,
+    bidder_id: &str,
+    amount: i32,
+    actor: &str,
+) -> Result<MarketListing, MarketError> {
+    let _guard = lock_listing(repository, listing_id, Some(bidder_id)).await?;
+
+    let listing = get_active_listing(repository, listing_id, ListingType::Auction).await?;
+    if listing
+        .ends_at
+        .is_some_and(|ends_at| ends_at <= OffsetDateTime::now_utc())
+    {
+        let listing = settle_auction(repository, listing, actor).await?;
+        return Err(MarketError::NotActive(listing.status));
+    }
+    if listing.seller_id == bidder_id {
+        return Err(MarketError::OwnListing);
+    }
+    let min_bid = listing
+        .highest_bid
+        .map(|highest_bid| highest_bid + CONFIGURATION.market_rules.min_bid_increment)
+        .unwrap_or(listing.price);
+    if amount < min_bid {
+        return Err(MarketError::BidTooLow(min_bid));
+    }
+
+    pay(
+        repository,
+        bidder_id,
+        -amount,
+        CreditTransactionReason::MarketBid,
+        actor,
+    )
+    .await
+    .map_err(MarketError::Credit)?;
+
+    let previous_bid = listing.highest_bid.zip(listing.highest_bidder_id.clone());
+    let listing = MarketListing {
+        highest_bid: Some(amount),
+        highest_bidder_id: Some(bidder_id.to_string()),
+        ..listing
+    };
+    if let Err(e) = repository.upsert_market_listing(listing.clone()).await {
+        refund(
+            repository,
+            bidder_id,
+            amount,
+            CreditTransactionReason::MarketRefund,
+            &listing.id,
+            actor,
+        )
+        .await;
+        return Err(MarketError::Repository(e));
+    }
+
+    if let Some((previous_bid, previous_bidder_id)) = previous_bid {
+        refund(
+            repository,
+            &previous_bidder_id,
+            previous_bid,
+            CreditTransactionReason::MarketRefund,
+            &listing.id,
+            actor,
+        )
+        .await;
+    }
+    Ok(listing)
+}
+
+/// Takes a listing off the market. Auctions can only be cancelled before anybody bids on them.
+pub async fn cancel(
+    repository: &dyn Repository,
+    listing_id: &str,
+    user_id: &str,
+) -> Result<MarketListing, MarketError> {
+    let _guard = lock_listing(repository, listing_id, None).await?;
+
+    let listing = repository
+        .get_market_listing(listing_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .ok_or(MarketError::ListingNotFound)?;
+    if listing.seller_id != user_id {
+        return Err(MarketError::NotSeller);
+    }
+    if listing.status != ListingStatus::Active {
+        return Err(MarketError::NotActive(listing.status));
+    }
+    if listing.highest_bid.is_some() {
+        return Err(MarketError::HasBids);
+    }
+
+    let listing = MarketListing {
+        status: ListingStatus::Cancelled,
+        settled_at: Some(OffsetDateTime::now_utc()),
+        ..listing
+    };
+    repository
+        .upsert_market_listing(listing.clone())
+        .await
+        .map_err(MarketError::Repository)?;
+    Ok(listing)
+}
+
+/// Settles every auction that has ended, selling the roll to the highest bidder.
+pub async fn settle_ended_auctions(repository: &dyn Repository, actor: &str) {
+    let now = OffsetDateTime::now_utc();
+    let listings = match repository.get_active_market_listings().await {
+        Ok(listings) => listings,
+        Err(e) => {
+            tracing::error!("Failed to retrieve market listings to settle: {}", e);
+            return;
+        }
+    };
+    for listing in listings.into_iter().filter(|listing| {
+        listing.listing_type == ListingType::Auction
+            && listing.ends_at.is_some_and(|ends_at| ends_at <= now)
+    }) {
+        if let Err(e) = settle_ended_auction(repository, &listing.id, actor).await {
+            tracing::error!("Failed to settle auction {}: {:?}", &listing.id, e);
+        }
+    }
+}
+
+/// Settles the auction unless it has been settled since it was found to have ended.
+async fn settle_ended_auction(
+    repository: &dyn Repository,
+    listing_id: &str,
+    actor: &str,
+) -> Result<(), MarketError> {
+    let _guard = lock_listing(repository, listing_id, None).await?;
+
+    let listing = match get_active_listing(repository, listing_id, ListingType::Auction).await {
+        Ok(listing) => listing,
+        Err(MarketError::NotActive(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    settle_auction(repository, listing, actor).await?;
+    Ok(())
+}
+
+pub async fn initialize_market_settlement(repository: Arc<dyn Repository>) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+        CONFIGURATION.market_rules.settlement_interval_seconds,
+    ));
+    loop {
+        interval.tick().await;
+        settle_ended_auctions(repository.as_ref(), SETTLEMENT_ACTOR).await;
+    }
+}
+
+/// Holds the rolls of everyone the listing can move rolls or credits between: its seller, its
+/// highest bidder and the given user. Every change to a listing holds its seller, so the highest
+/// bidder can't change while the guard is held.
+async fn lock_listing(
+    repository: &dyn Repository,
+    listing_id: &str,
+    user_id: Option<&str>,
+) -> Result<RollGuard<'static>, MarketError> {
+    let mut listing = repository
+        .get_market_listing(listing_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .ok_or(MarketError::ListingNotFound)?;
+    loop {
+        let user_ids = [
+            Some(listing.seller_id.as_str()),
+            listing.highest_bidder_id.as_deref(),
+            user_id,
+        ];
+        let guard = ROLL_LOCKS.lock(user_ids.into_iter().flatten()).await;
+
+        // Somebody may have outbid the highest bidder before their rolls were locked.
+        let current = repository
+            .get_market_listing(listing_id)
+            .await
+            .map_err(MarketError::Repository)?
+            .ok_or(MarketError::ListingNotFound)?;
+        if current.highest_bidder_id == listing.highest_bidder_id {
+            return Ok(guard);
+        }
+        drop(guard);
+        listing = current;
+    }
+}
+
+async fn get_active_listing(
+    repository: &dyn Repository,
+    listing_id: &str,
+    listing_type: ListingType,
+) -> Result<MarketListing, MarketError> {
+    let listing = repository
+        .get_market_listing(listing_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .ok_or(MarketError::ListingNotFound)?;
+    if listing.listing_type != listing_type {
+        return Err(MarketError::WrongListingType(listing.listing_type));
+    }
+    if listing.status != ListingStatus::Active {
+        return Err(MarketError::NotActive(listing.status));
+    }
+    Ok(listing)
+}
+
+/// Sells an ended auction to its highest bidder, or lets it expire if nobody bid.
+async fn settle_auction(
+    repository: &dyn Repository,
+    listing: MarketListing,
+    actor: &str,
+) -> Result<MarketListing, MarketError> {
+    match (listing.highest_bid, listing.highest_bidder_id.clone()) {
+        (Some(highest_bid), Some(highest_bidder_id)) => {
+            settle_sale(repository, listing, &highest_bidder_id, highest_bid, actor).await
+        }
+        _ => {
+            let listing = MarketListing {
+                status: ListingStatus::Expired,
+                settled_at: listing.ends_at,
+                ..listing
+            };
+            repository
+                .upsert_market_listing(listing.clone())
+                .await
+                .map_err(MarketError::Repository)?;
+            Ok(listing)
+        }
+    }
+}
+
+/// Moves the roll to the buyer and marks the listing sold, then pays the seller the sale price
+/// minus the fee. The buyer's credits are already held, either by `buy` or as the auction's highest
+/// bid, and stay held if the sale fails for a reason that settling it again could get past.
+async fn settle_sale(
+    repository: &dyn Repository,
+    listing: MarketListing,
+    buyer_id: &str,
+    sale_price: i32,
+    actor: &str,
+) -> Result<MarketListing, MarketError> {
+    let Some(user_roll) = get_listed_roll(repository, &listing).await? else {
+        return fail_sale(repository, listing, buyer_id, sale_price, actor).await;
+    };
+    let transfer = roll::get_transfers(repository, vec![user_roll], buyer_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .remove(0);
+
+    let fee = CONFIGURATION.market_rules.get_fee(sale_price);
+    let sold_listing = MarketListing {
+        status: ListingStatus::Sold,
+        buyer_id: Some(buyer_id.to_string()),
+        sale_price: Some(sale_price),
+        fee: Some(fee),
+        settled_at: Some(OffsetDateTime::now_utc()),
+        ..listing.clone()
+    };
+    match repository
+        .complete_market_listing(sold_listing.clone(), transfer)
+        .await
+    {
+        Ok(()) => {}
+        // Either the roll is gone or the buyer's next roll ID was taken by another request.
+        Err(RepositoryError::Conflict) => {
+            return match get_listed_roll(repository, &listing).await? {
+                Some(_) => Err(MarketError::Repository(RepositoryError::Conflict)),
+                None => fail_sale(repository, listing, buyer_id, sale_price, actor).await,
+            };
+        }
+        Err(e) => return Err(MarketError::Repository(e)),
+    }
+
+    let proceeds = sale_price - fee;
+    if proceeds > 0 {
+        if let Err(e) = pay(
+            repository,
+            &sold_listing.seller_id,
+            proceeds,
+            CreditTransactionReason::MarketSale,
+            actor,
+        )
+        .await
+        {
+            tracing::error!(
+                "Listing {} was sold but {} credits couldn't be paid to its seller {}: {:?}",
+                sold_listing.id,
+                proceeds,
+                sold_listing.seller_id,
+                e
+            );
+            return Err(MarketError::Credit(e));
+        }
+    }
+
+    Ok(sold_listing)
+}
+
+/// The listed roll, unless it has been spent or is no longer the seller's. The listed roll itself
+/// doesn't count as available, so it is looked up directly.
+async fn get_listed_roll(
+    repository: &dyn Repository,
+    listing: &MarketListing,
+) -> Result<Option<UserRoll>, MarketError> {
+    Ok(repository
+        .get_user_roll(&listing.seller_id, listing.roll_id)
+        .await
+        .map_err(MarketError::Repository)?
+        .filter(|user_roll| !user_roll.consumed))
+}
+
+/// Takes the listing off the market when its roll is gone, and only then pays the buyer back. If
+/// the listing can't be cancelled the buyer's credits stay held, so they can't be paid back twice.
+async fn fail_sale(
+    repository: &dyn Repository,
+    listing: MarketListing,
+    buyer_id: &str,
+    sale_price: i32,
+    actor: &str,
+) -> Result<MarketListing, MarketError> {
+    let listing = MarketListing {
+        status: ListingStatus::Cancelled,
+        settled_at: Some(OffsetDateTime::now_utc()),
+        ..listing
+    };
+    repository
+        .upsert_market_listing(listing.clone())
+        .await
+        .map_err(MarketError::Repository)?;
+    give_back(repository, &listing.id, buyer_id, sale_price, actor).await?;
+    Err(MarketError::RollUnavailable)
+}
+
+/// Pays back credits the buyer no longer owes for the listing, surfacing a failure instead of
+/// losing the credits silently.
+async fn give_back(
+    repository: &dyn Repository,
+    listing_id: &str,
+    buyer_id: &str,
+    amount: i32,
+    actor: &str,
+) -> Result<(), MarketError> {
+    pay(
+        repository,
+        buyer_id,
+        amount,
+        CreditTransactionReason::MarketRefund,
+        actor,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to pay {} credits back to user {} for listing {}: {:?}",
+            amount,
+            buyer_id,
+            listing_id,
+            e
+        );
+        MarketError::Credit(e)
+    })
+}
@@ -1,9 +1,18 @@
 use once_cell::sync::Lazy;
 
+pub mod collection;
 pub mod configuration;
 pub mod constants;
+pub mod credit;
+pub mod lottery;
+pub mod market;
+pub mod reward;
+pub mod roll;
+pub mod roll_lock;
 pub mod swc_notifier;
 pub mod swc_scraper;
+pub mod time_zone;
+pub mod trade;
 pub mod util;
 pub mod web_driver;
 
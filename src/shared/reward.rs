@@ -0,0 +1,457 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::configuration::{RewardResetMode, RewardRules};
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::errors::ServerError;
+use crate::model::rewards::{
+    RewardNotReadyError, RewardStatus, RewardType, Rewards, UserRewardStatus,
+};
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::time_zone::TimeZone;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use once_cell::sync::Lazy;
+use std::ops::Add;
+use time::{OffsetDateTime, Time, UtcOffset};
+
+/// How many times a reward claim is attempted before giving up on concurrent writers.
+const MAX_REWARD_CLAIM_ATTEMPTS: u32 = 5;
+
+static REWARD_TIME_ZONE: Lazy<TimeZone> = Lazy::new(|| {
+    CONFIGURATION
+        .reward_rules
+        .get_time_zone()
+        .unwrap_or_else(|e| {
+            tracing::error!("{}, falling back to UTC.", e);
+            TimeZone::utc()
+        })
+});
+
+impl RewardType {
+    pub fn get_credits(self) -> i32 {
+        match self {
+            RewardType::Daily => CONFIGURATION.reward_rules.daily_reward,
+            RewardType::Weekly => CONFIGURATION.reward_rules.weekly_reward,
+        }
+    }
+
+    pub fn get_reason(self) -> CreditTransactionReason {
+        match self {
+            RewardType::Daily => CreditTransactionReason::DailyReward,
+            RewardType::Weekly => CreditTransactionReason::WeeklyReward,
+        }
+    }
+
+    fn get_next_time(self, rewards: &Rewards) -> OffsetDateTime {
+        match self {
+            RewardType::Daily => rewards.next_daily_time,
+            RewardType::Weekly => rewards.next_weekly_time,
+        }
+    }
+
+    /// The bonus paid on top of the reward itself for the streak reached by claiming it.
+    pub fn get_streak_bonus(self, rewards: &Rewards) -> i32 {
+        match self {
+            RewardType::Daily => CONFIGURATION
+                .reward_rules
+                .get_streak_bonus(rewards.daily_streak),
+            RewardType::Weekly => 0,
+        }
+    }
+
+    fn claim(self, rewards: Rewards, now: OffsetDateTime) -> Rewards {
+        match self {
+            RewardType::Daily => Rewards {
+                next_daily_time: get_next_reward_time(self, now),
+                daily_streak: get_next_streak(&rewards, now),
+                ..rewards
+            },
+            RewardType::Weekly => Rewards {
+                next_weekly_time: get_next_reward_time(self, now),
+                ..rewards
+            },
+        }
+    }
+
+    fn restore(self, rewards: Rewards, previous: &Rewards) -> Rewards {
+        match self {
+            RewardType::Daily => Rewards {
+                next_daily_time: previous.next_daily_time,
+                daily_streak: previous.daily_streak,
+                ..rewards
+            },
+            RewardType::Weekly => Rewards {
+                next_weekly_time: previous.next_weekly_time,
+                ..rewards
+            },
+        }
+    }
+}
+
+/// A reward that has been marked as claimed, but not paid out yet.
+#[derive(Clone, Debug)]
+pub struct ClaimedReward {
+    pub previous: Rewards,
+    pub rewards: Rewards,
+}
+
+#[derive(Debug)]
+pub enum RewardClaimError {
+    /// The reward has already been claimed and isn't available again until the given time.
+    TooEarly(RewardType, OffsetDateTime),
+    Conflict,
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for RewardClaimError {
+    fn into_response(self) -> Response {
+        match self {
+            RewardClaimError::TooEarly(reward_type, next_claim_time) => {
+                let seconds_remaining =
+                    get_seconds_remaining(next_claim_time, OffsetDateTime::now_utc());
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, seconds_remaining.to_string())],
+                    Json(RewardNotReadyError {
+                        error_message: format!(
+                            "The {} reward can't be claimed again for another {} seconds.",
+                            match reward_type {
+                                RewardType::Daily => "daily",
+                                RewardType::Weekly => "weekly",
+                            },
+                            seconds_remaining
+                        ),
+                        reward_type,
+                        next_claim_time,
+                        seconds_remaining,
+                    }),
+                )
+                    .into_response()
+            }
+            RewardClaimError::Conflict => (
+                StatusCode::CONFLICT,
+                Json(ServerError::with_message(
+                    "The user's rewards were modified by another request. Please try again.",
+                )),
+            )
+                .into_response(),
+            RewardClaimError::Repository(e) => {
+                let error_message = format!("Failed to update user's rewards: {}", e);
+                tracing::error!("{}", &error_message);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ServerError { error_message }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// When a reward claimed at `now` becomes available again, according to the configured reset mode.
+pub fn get_next_reward_time(reward_type: RewardType, now: OffsetDateTime) -> OffsetDateTime {
+    get_next_reset_time(
+        &CONFIGURATION.reward_rules,
+        &REWARD_TIME_ZONE,
+        reward_type,
+        now,
+    )
+}
+
+fn get_next_reset_time(
+    rules: &RewardRules,
+    time_zone: &TimeZone,
+    reward_type: RewardType,
+    now: OffsetDateTime,
+) -> OffsetDateTime {
+    match rules.reset_mode {
+        RewardResetMode::Rolling => {
+            let cooldown_hours = match reward_type {
+                RewardType::Daily => rules.daily_cooldown_hours,
+                RewardType::Weekly => rules.weekly_cooldown_hours,
+            };
+            now.add(time::Duration::hours(cooldown_hours))
+        }
+        RewardResetMode::Calendar => {
+            // The reward rules have been validated at startup.
+            let reset_time = Time::from_hms(rules.reset_hour, 0, 0).unwrap_or(Time::MIDNIGHT);
+            let weekday = match reward_type {
+                RewardType::Daily => None,
+                RewardType::Weekly => rules.get_weekly_reset_weekday().ok(),
+            };
+            time_zone
+                .get_next_occurrence(now, reset_time, weekday)
+                .to_offset(UtcOffset::UTC)
+        }
+    }
+}
+
+/// A daily streak continues as long as the next daily reward is claimed before the one after it
+/// becomes available, plus the configured grace period.
+pub fn get_streak_expiry(next_daily_time: OffsetDateTime) -> OffsetDateTime {
+    get_next_reward_time(RewardType::Daily, next_daily_time).add(time::Duration::hours(
+        CONFIGURATION.reward_rules.streak_grace_hours,
+    ))
+}
+
+/// The streak a daily claim at `now` reaches.
+fn get_next_streak(rewards: &Rewards, now: OffsetDateTime) -> u32 {
+    if now < get_streak_expiry(rewards.next_daily_time) {
+        rewards.daily_streak + 1
+    } else {
+        1
+    }
+}
+
+fn get_seconds_remaining(next_claim_time: OffsetDateTime, now: OffsetDateTime) -> i64 {
+    (next_claim_time - now).as_seconds_f64().ceil().max(0.0) as i64
+}
+
+pub fn get_reward_status(rewards: &Rewards, now: OffsetDateTime) -> UserRewardStatus {
+    let get_status = |reward_type: RewardType| {
+        let next_claim_time = reward_type.get_next_time(rewards);
+        let streak_bonus = match reward_type {
+            RewardType::Daily => CONFIGURATION
+                .reward_rules
+                .get_streak_bonus(get_next_streak(rewards, now.max(next_claim_time))),
+            RewardType::Weekly => 0,
+        };
+        RewardStatus {
+            reward_type,
+            claimable: now >= next_claim_time,
+            next_claim_time,
+            seconds_remaining: get_seconds_remaining(next_claim_time, now),
+            amount: reward_type.get_credits() + streak_bonus,
+        }
+    };
+
+    let streak_expiry = get_streak_expiry(rewards.next_daily_time);
+    let has_streak = rewards.daily_streak > 0 && now < streak_expiry;
+    UserRewardStatus {
+        user_id: rewards.user_id.clone(),
+        daily: get_status(RewardType::Daily),
+        weekly: get_status(RewardType::Weekly),
+        daily_streak: if has_streak { rewards.daily_streak } else { 0 },
+        streak_expires_at: has_streak.then_some(streak_expiry),
+    }
+}
+
+/// Marks the reward as claimed, conditionally on the version that was read so that concurrent
+/// claims can't both succeed.
+pub async fn claim_reward(
+    repository: &dyn Repository,
+    user_id: &str,
+    reward_type: RewardType,
+) -> Result<ClaimedReward, RewardClaimError> {
+    for _ in 0..MAX_REWARD_CLAIM_ATTEMPTS {
+        let versioned = repository
+            .get_versioned_rewards(user_id)
+            .await
+            .map_err(RewardClaimError::Repository)?;
+        let previous = versioned
+            .as_ref()
+            .map(|versioned| versioned.document.clone())
+            .unwrap_or_else(|| Rewards::new(user_id));
+
+        let now = OffsetDateTime::now_utc();
+        let next_claim_time = reward_type.get_next_time(&previous);
+        if now < next_claim_time {
+            return Err(RewardClaimError::TooEarly(reward_type, next_claim_time));
+        }
+
+        let rewards = reward_type.claim(previous.clone(), now);
+        let result = match versioned {
+            Some(versioned) => {
+                repository
+                    .replace_rewards(rewards.clone(), &versioned.version)
+                    .await
+            }
+            None => repository.add_rewards(rewards.clone()).await,
+        };
+
+        match result {
+            Ok(_) => return Ok(ClaimedReward { previous, rewards }),
+            Err(RepositoryError::Conflict) => continue,
+            Err(e) => return Err(RewardClaimError::Repository(e)),
+        }
+    }
+
+    Err(RewardClaimError::Conflict)
+}
+
+/// Makes a reward available again after its credits could not be paid out.
+pub async fn revert_reward_claim(
+    repository: &dyn Repository,
+    user_id: &str,
+    reward_type: RewardType,
+    previous: &Rewards,
+) {
+    for _ in 0..MAX_REWARD_CLAIM_ATTEMPTS {
+        let versioned = match repository.get_versioned_rewards(user_id).await {
+            Ok(Some(versioned)) => versioned,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to revert the reward claim of user {}: {}",
+                    user_id,
+                    e
+                );
+                return;
+            }
+        };
+
+        let reverted = reward_type.restore(versioned.document, previous);
+        match repository
+            .replace_rewards(reverted, &versioned.version)
+            .await
+        {
+            Ok(_) => return,
+            Err(RepositoryError::Conflict) => continue,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to revert the reward claim of user {}: {}",
+                    user_id,
+                    e
+                );
+                return;
+            }
+        }
+    }
+
+    tracing::error!(
+        "Failed to revert the reward claim of user {} due to concurrent modifications.",
+        user_id
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    fn utc(month: u8, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        let month = Month::try_from(month).unwrap();
+        Date::from_calendar_date(2026, month, day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn resets_rewards_after_the_cooldown() {
+        let rules = RewardRules::default();
+        let now = utc(10, 18, 13, 45);
+        assert_eq!(
+            get_next_reset_time(&rules, &TimeZone::utc(), RewardType::Daily, now),
+            utc(10, 19, 13, 45)
+        );
+        assert_eq!(
+            get_next_reset_time(&rules, &TimeZone::utc(), RewardType::Weekly, now),
+            utc(10, 25, 13, 45)
+        );
+    }
+
+    #[test]
+    fn resets_rewards_on_the_calendar() {
+        let rules = RewardRules {
+            reset_mode: RewardResetMode::Calendar,
+            time_zone: "Asia/Tokyo".to_string(),
+            reset_hour: 4,
+            weekly_reset_weekday: "Monday".to_string(),
+            ..RewardRules::default()
+        };
+        let tokyo = rules.get_time_zone().unwrap();
+
+        // Sunday 18 October, 22:45 in Tokyo.
+        let now = utc(10, 18, 13, 45);
+        assert_eq!(
+            get_next_reset_time(&rules, &tokyo, RewardType::Daily, now),
+            utc(10, 18, 19, 0)
+        );
+        assert_eq!(
+            get_next_reset_time(&rules, &tokyo, RewardType::Weekly, now),
+            utc(10, 18, 19, 0)
+        );
+
+        // Right at the reset the next one is a full day or week away.
+        let now = utc(10, 18, 19, 0);
+        assert_eq!(
+            get_next_reset_time(&rules, &tokyo, RewardType::Daily, now),
+            utc(10, 19, 19, 0)
+        );
+        assert_eq!(
+            get_next_reset_time(&rules, &tokyo, RewardType::Weekly, now),
+            utc(10, 25, 19, 0)
+        );
+    }
+
+    fn create_rewards(daily_streak: u32, next_daily_time: OffsetDateTime) -> Rewards {
+        Rewards {
+            next_daily_time,
+            next_weekly_time: next_daily_time,
+            daily_streak,
+            ..Rewards::new("alice")
+        }
+    }
+
+    #[test]
+    fn continues_streaks_until_the_next_reset() {
+        let rules = &CONFIGURATION.reward_rules;
+        let next_daily_time = utc(10, 18, 12, 0);
+        let streak_expiry = get_streak_expiry(next_daily_time);
+        assert_eq!(
+            streak_expiry,
+            get_next_reward_time(RewardType::Daily, next_daily_time)
+                + time::Duration::hours(rules.streak_grace_hours)
+        );
+
+        let rewards = create_rewards(3, next_daily_time);
+        assert_eq!(get_next_streak(&rewards, next_daily_time), 4);
+        assert_eq!(
+            get_next_streak(&rewards, streak_expiry - time::Duration::SECOND),
+            4
+        );
+        assert_eq!(get_next_streak(&rewards, streak_expiry), 1);
+
+        let claimed = RewardType::Daily.claim(rewards.clone(), next_daily_time);
+        assert_eq!(claimed.daily_streak, 4);
+        assert_eq!(
+            claimed.next_daily_time,
+            get_next_reward_time(RewardType::Daily, next_daily_time)
+        );
+        let restored = RewardType::Daily.restore(claimed, &rewards);
+        assert_eq!(restored.daily_streak, 3);
+        assert_eq!(restored.next_daily_time, next_daily_time);
+    }
+
+    #[test]
+    fn reports_streaks_and_their_bonuses() {
+        let rules = &CONFIGURATION.reward_rules;
+        let next_daily_time = utc(10, 18, 12, 0);
+        let streak_expiry = get_streak_expiry(next_daily_time);
+        let bonus_day = rules
+            .streak_bonuses
+            .first()
+            .expect("The default rules have no streak bonus.");
+        let rewards = create_rewards(bonus_day.day - 1, next_daily_time);
+
+        let status = get_reward_status(&rewards, next_daily_time - time::Duration::HOUR);
+        assert!(!status.daily.claimable);
+        assert_eq!(status.daily.seconds_remaining, 3600);
+        assert_eq!(status.daily_streak, bonus_day.day - 1);
+        assert_eq!(status.streak_expires_at, Some(streak_expiry));
+        assert_eq!(status.daily.amount, rules.daily_reward + bonus_day.bonus);
+        assert_eq!(status.weekly.amount, rules.weekly_reward);
+
+        // A missed day resets the streak, and with it the bonus.
+        let status = get_reward_status(&rewards, streak_expiry);
+        assert!(status.daily.claimable);
+        assert_eq!(status.daily_streak, 0);
+        assert_eq!(status.streak_expires_at, None);
+        assert_eq!(
+            status.daily.amount,
+            rules.daily_reward + rules.get_streak_bonus(1)
+        );
+    }
+}
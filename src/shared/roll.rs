@@ -0,0 +1,565 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::banner::Banner;
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::errors::ServerError;
+use crate::model::mal_character::{MalCharacter, Rarity, RarityRate, RollRates};
+use crate::model::page::{Page, PageRequest};
+use crate::model::user_roll::{
+    GetRollResult, PityStatus, RollHistoryQuery, RollPity, RollResult, UserRoll,
+};
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::{pay, refund, CreditUpdateError};
+use crate::shared::roll_lock::ROLL_LOCKS;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rand::distributions::{Distribution, WeightedIndex};
+use std::collections::BTreeMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// How many roll IDs are tried before giving up on concurrent writers.
+const MAX_ROLL_ID_ATTEMPTS: u32 = 5;
+
+#[derive(Debug)]
+pub enum RollError {
+    /// There is no character with a positive weight to roll.
+    NoCharacters,
+    BannerNotFound,
+    BannerInactive,
+    Credit(CreditUpdateError),
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for RollError {
+    fn into_response(self) -> Response {
+        match self {
+            RollError::NoCharacters => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ServerError::with_message(
+                    "There are no characters that can be rolled.",
+                )),
+            )
+                .into_response(),
+            RollError::BannerNotFound => (
+                StatusCode::NOT_FOUND,
+                Json(ServerError::with_message(
+                    "The specified banner is not found.",
+                )),
+            )
+                .into_response(),
+            RollError::BannerInactive => (
+                StatusCode::BAD_REQUEST,
+                Json(ServerError::with_message(
+                    "The specified banner is not running right now.",
+                )),
+            )
+                .into_response(),
+            RollError::Credit(e) => e.into_response(),
+            RollError::Repository(e) => {
+                let error_message = format!("Failed to roll a character: {}", e);
+                tracing::error!("{}", &error_message);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ServerError { error_message }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Rarities that have at least one character that can be rolled, along with their drop rates for
+/// the roll after the given number of misses.
+fn get_rollable_rarities(characters: &[MalCharacter], misses: u32) -> Vec<(Rarity, f64)> {
+    let rules = &CONFIGURATION.roll_rules;
+    let rollable = Rarity::ALL
+        .into_iter()
+        .filter(|rarity| {
+            characters
+                .iter()
+                .any(|character| character.rarity == *rarity && character.weight > 0)
+        })
+        .collect::<Vec<_>>();
+    // Hard pity can only be honored if there is a character of the pity rarity to give.
+    let guaranteed = rules.is_hard_pity_reached(misses)
+        && rollable
+            .iter()
+            .any(|rarity| *rarity >= rules.pity_rarity && rules.get_rarity_rate(*rarity) > 0.0);
+
+    rollable
+        .into_iter()
+        .map(|rarity| {
+            let rate = rules.get_rarity_rate(rarity);
+            let rate = if rarity < rules.pity_rarity {
+                if guaranteed {
+                    0.0
+                } else {
+                    rate
+                }
+            } else if rate > 0.0 {
+                rate + rules.get_soft_pity_bonus(misses)
+            } else {
+                rate
+            };
+            (rarity, rate)
+        })
+        .filter(|(_, rate)| *rate > 0.0)
+        .collect()
+}
+
+/// Picks a rarity according to the drop rates and the user's pity, then a character of that rarity
+/// with a probability proportional to its weight. Banners raise the weights of their featured
+/// characters.
+pub fn pick_character<'a>(
+    characters: &'a [MalCharacter],
+    misses: u32,
+    banner: Option<&Banner>,
+) -> Option<&'a MalCharacter> {
+    let mut rng = rand::thread_rng();
+    let rarities = get_rollable_rarities(characters, misses);
+    let rarity_distribution = WeightedIndex::new(rarities.iter().map(|(_, rate)| *rate)).ok()?;
+    let (rarity, _) = rarities[rarity_distribution.sample(&mut rng)];
+
+    let candidates = characters
+        .iter()
+        .filter(|character| character.rarity == rarity)
+        .collect::<Vec<_>>();
+    let distribution = WeightedIndex::new(candidates.iter().map(|character| {
+        let weight = f64::from(character.weight.max(0));
+        match banner {
+            Some(banner) if banner.is_featured(character.character_id) => weight * banner.rate_up,
+            _ => weight,
+        }
+    }))
+    .ok()?;
+    candidates.get(distribution.sample(&mut rng)).copied()
+}
+
+pub fn get_pity_status(misses: u32) -> PityStatus {
+    let rules = &CONFIGURATION.roll_rules;
+    PityStatus {
+        misses,
+        pity_rarity: rules.pity_rarity,
+        rolls_until_guaranteed: (rules.hard_pity > 0)
+            .then(|| rules.hard_pity.saturating_sub(misses).max(1)),
+        soft_pity_active: rules.get_soft_pity_bonus(misses) > 0.0,
+    }
+}
+
+/// The odds of rolling each rarity given the characters that currently exist.
+pub fn get_roll_rates(characters: &[MalCharacter]) -> RollRates {
+    let rarities = get_rollable_rarities(characters, 0);
+    let total_rate = rarities.iter().map(|(_, rate)| *rate).sum::<f64>();
+    let rates = Rarity::ALL
+        .into_iter()
+        .map(|rarity| {
+            let drop_rate = rarities
+                .iter()
+                .find(|(rollable, _)| *rollable == rarity)
+                .map(|(_, rate)| rate / total_rate * 100.0)
+                .unwrap_or_default();
+            RarityRate {
+                rarity,
+                configured_rate: CONFIGURATION.roll_rules.get_rarity_rate(rarity),
+                drop_rate,
+                character_count: characters
+                    .iter()
+                    .filter(|character| character.rarity == rarity && character.weight > 0)
+                    .count(),
+            }
+        })
+        .collect();
+    RollRates {
+        roll_cost: CONFIGURATION.roll_rules.roll_cost,
+        rates,
+    }
+}
+
+/// Charges the user for a roll, picks a character for them and stores it as their next roll.
+/// Rolls on a banner cost what the banner asks for instead of the configured roll cost.
+/// The user is refunded if the roll can't be stored.
+pub async fn roll(
+    repository: &dyn Repository,
+    user_id: &str,
+    actor: &str,
+    banner_id: Option<&str>,
+) -> Result<RollResult, RollError> {
+    let banner = match banner_id {
+        Some(banner_id) => Some(
+            repository
+                .get_banner(banner_id)
+                .await
+                .map_err(RollError::Repository)?
+                .ok_or(RollError::BannerNotFound)?,
+        ),
+        None => None,
+    };
+    if let Some(banner) = &banner {
+        if !banner.is_active(OffsetDateTime::now_utc()) {
+            return Err(RollError::BannerInactive);
+        }
+    }
+
+    let _guard = ROLL_LOCKS.lock([user_id]).await;
+
+    let characters = repository
+        .get_all_mal_characters()
+        .await
+        .map_err(RollError::Repository)?;
+    let roll_pity = repository
+        .get_roll_pity(user_id)
+        .await
+        .map_err(RollError::Repository)?
+        .unwrap_or_else(|| RollPity::new(user_id));
+    let mal_character = pick_character(&characters, roll_pity.misses, banner.as_ref())
+        .cloned()
+        .ok_or(RollError::NoCharacters)?;
+    let cost = banner
+        .as_ref()
+        .map(|banner| banner.roll_cost)
+        .unwrap_or(CONFIGURATION.roll_rules.roll_cost);
+    pay(
+        repository,
+        user_id,
+        -cost,
+        CreditTransactionReason::RollPurchase,
+        actor,
+    )
+    .await
+    .map_err(RollError::Credit)?;
+
+    let user_roll = UserRoll {
+        user_id: user_id.to_string(),
+        mal_character_id: mal_character.character_id,
+        created_at: OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+        banner_id: banner.map(|banner| banner.id),
+        consumed: false,
+        ..UserRoll::default()
+    };
+    let user_roll = match add_next_user_roll(repository, user_roll).await {
+        Ok(user_roll) => user_roll,
+        Err(e) => {
+            refund(
+                repository,
+                user_id,
+                cost,
+                CreditTransactionReason::RollRefund,
+                "a new roll",
+                actor,
+            )
+            .await;
+            return Err(RollError::Repository(e));
+        }
+    };
+
+    // The roll stands whether or not it can be told apart from the user's other copies.
+    let duplicate = match repository
+        .get_user_character_rolls(user_id, &[user_roll.mal_character_id])
+        .await
+    {
+        Ok(character_rolls) => is_duplicate(&character_rolls, &user_roll),
+        Err(e) => {
+            tracing::error!(
+                "Failed to retrieve the rolls of character {} of user {}: {}",
+                user_roll.mal_character_id,
+                user_id,
+                e
+            );
+            false
+        }
+    };
+    let roll_pity = RollPity {
+        misses: if mal_character.rarity >= CONFIGURATION.roll_rules.pity_rarity {
+            0
+        } else {
+            roll_pity.misses + 1
+        },
+        ..roll_pity
+    };
+    // The roll has been paid for and stored already, so it stands even if the pity can't be saved.
+    if let Err(e) = repository.upsert_roll_pity(roll_pity.clone()).await {
+        tracing::error!("Failed to update the roll pity of user {}: {}", user_id, e);
+    }
+
+    Ok(RollResult {
+        roll: GetRollResult {
+            user_roll,
+            rarity: mal_character.rarity,
+            mal_character,
+            duplicate,
+        },
+        pity: get_pity_status(roll_pity.misses),
+    })
+}
+
+/// Stores a roll made elsewhere, e.g. by the bot, as the user's next roll. The roll ID is always
+/// allocated here rather than taken from the request, so it can't collide with the user's rolls.
+pub async fn import_roll(
+    repository: &dyn Repository,
+    user_id: &str,
+    user_roll: UserRoll,
+) -> Result<UserRoll, RepositoryError> {
+    let _guard = ROLL_LOCKS.lock([user_id]).await;
+
+    let created_at = if user_roll.created_at.is_empty() {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default()
+    } else {
+        user_roll.created_at
+    };
+    let user_roll = UserRoll {
+        user_id: user_id.to_string(),
+        created_at,
+        consumed: false,
+        ..user_roll
+    };
+    add_next_user_roll(repository, user_roll).await
+}
+
+/// Stores the roll under the user's next roll ID. The ID is taken with a write that conflicts if
+/// the user already has a roll with it, so when another writer takes the ID first the roll moves
+/// on to the ID after it.
+pub async fn add_next_user_roll(
+    repository: &dyn Repository,
+    user_roll: UserRoll,
+) -> Result<UserRoll, RepositoryError> {
+    for attempt in 1..=MAX_ROLL_ID_ATTEMPTS {
+        let roll_id = repository.get_max_roll_id(&user_roll.user_id).await? + 1;
+        let user_roll = UserRoll {
+            roll_id,
+            id: UserRoll::get_id(&user_roll.user_id, roll_id),
+            ..user_roll.clone()
+        };
+        match repository.add_user_roll(user_roll.clone()).await {
+            Ok(()) => return Ok(user_roll),
+            Err(RepositoryError::Conflict) => tracing::warn!(
+                "Roll ID {} of user {} was taken by another request (attempt {}/{}).",
+                roll_id,
+                &user_roll.user_id,
+                attempt,
+                MAX_ROLL_ID_ATTEMPTS
+            ),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(RepositoryError::Conflict)
+}
+
+/// Whether the roll is a duplicate among the user's rolls, i.e. it hasn't been spent and the user
+/// owns another unspent copy of its character. Only duplicates can be converted or spent on their
+/// character, so that the user always keeps one copy.
+pub fn is_duplicate(user_rolls: &[UserRoll], user_roll: &UserRoll) -> bool {
+    !user_roll.consumed
+        && user_rolls.iter().any(|other| {
+            other.id != user_roll.id
+                && other.mal_character_id == user_roll.mal_character_id
+                && !other.consumed
+        })
+}
+
+/// The user's rolls with the given IDs, or `None` if any of them doesn't exist, has been spent or
+/// is up for sale on the marketplace.
+pub async fn get_available_rolls(
+    repository: &dyn Repository,
+    user_id: &str,
+    roll_ids: &[i32],
+) -> Result<Option<Vec<UserRoll>>, RepositoryError> {
+    let listed_roll_ids = get_listed_roll_ids(repository, user_id).await?;
+    let mut user_rolls = Vec::with_capacity(roll_ids.len());
+    for roll_id in roll_ids {
+        if listed_roll_ids.contains(roll_id) {
+            return Ok(None);
+        }
+        match repository.get_user_roll(user_id, *roll_id).await? {
+            Some(user_roll) if !user_roll.consumed => user_rolls.push(user_roll),
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(user_rolls))
+}
+
+/// IDs of the user's rolls that are up for sale on the marketplace.
+pub async fn get_listed_roll_ids(
+    repository: &dyn Repository,
+    user_id: &str,
+) -> Result<Vec<i32>, RepositoryError> {
+    Ok(repository
+        .get_active_market_listings()
+        .await?
+        .into_iter()
+        .filter(|listing| listing.seller_id == user_id)
+        .map(|listing| listing.roll_id)
+        .collect())
+}
+
+/// Pairs each roll with the roll it becomes once the new owner has it, numbered after the new
+/// owner's own rolls.
+pub async fn get_transfers(
+    repository: &dyn Repository,
+    user_rolls: Vec<UserRoll>,
+    new_owner: &str,
+) -> Result<Vec<(UserRoll, UserRoll)>, RepositoryError> {
+    let last_roll_id = repository.get_max_roll_id(new_owner).await?;
+    Ok(user_rolls
+        .into_iter()
+        .zip(last_roll_id + 1..)
+        .map(|(previous, roll_id)| {
+            let user_roll = UserRoll {
+                roll_id,
+                user_id: new_owner.to_string(),
+                id: UserRoll::get_id(new_owner, roll_id),
+                ..previous.clone()
+            };
+            (previous, user_roll)
+        })
+        .collect())
+}
+
+/// The user's rolls made within the queried time range, along with their characters.
+pub async fn get_roll_results(
+    repository: &dyn Repository,
+    user_id: &str,
+    query: &RollHistoryQuery,
+) -> Result<Vec<GetRollResult>, RepositoryError> {
+    let all_user_rolls = repository.get_user_rolls(user_id).await?;
+    let user_rolls = all_user_rolls
+        .iter()
+        .filter(|user_roll| query.contains(user_roll))
+        .cloned()
+        .collect();
+    join_mal_characters(repository, user_rolls, &all_user_rolls).await
+}
+
+pub async fn get_roll_result(
+    repository: &dyn Repository,
+    user_id: &str,
+    roll_id: i32,
+) -> Result<Option<GetRollResult>, RepositoryError> {
+    let Some(user_roll) = repository.get_user_roll(user_id, roll_id).await? else {
+        return Ok(None);
+    };
+    let character_rolls = repository
+        .get_user_character_rolls(user_id, &[user_roll.mal_character_id])
+        .await?;
+    Ok(
+        join_mal_characters(repository, vec![user_roll], &character_rolls)
+            .await?
+            .pop(),
+    )
+}
+
+/// A page of the user's rolls made within the queried time range, along with their characters.
+pub async fn get_roll_history(
+    repository: &dyn Repository,
+    user_id: &str,
+    query: &RollHistoryQuery,
+    page_request: &PageRequest,
+) -> Result<Page<GetRollResult>, RepositoryError> {
+    let page = repository
+        .get_user_roll_history(user_id, query, page_request)
+        .await?;
+    let mal_character_ids = page
+        .items
+        .iter()
+        .map(|user_roll| user_roll.mal_character_id)
+        .collect::<Vec<_>>();
+    let character_rolls = repository
+        .get_user_character_rolls(user_id, &mal_character_ids)
+        .await?;
+    Ok(Page {
+        items: join_mal_characters(repository, page.items, &character_rolls).await?,
+        continuation: page.continuation,
+    })
+}
+
+/// Pairs each roll with its character, telling duplicates apart by the user's other rolls of the
+/// same characters.
+async fn join_mal_characters(
+    repository: &dyn Repository,
+    user_rolls: Vec<UserRoll>,
+    character_rolls: &[UserRoll],
+) -> Result<Vec<GetRollResult>, RepositoryError> {
+    let mal_character_ids = user_rolls
+        .iter()
+        .map(|user_roll| user_roll.mal_character_id)
+        .collect::<Vec<_>>();
+    let mal_characters = repository
+        .get_mal_characters(&mal_character_ids)
+        .await?
+        .into_iter()
+        .map(|mal_character| (mal_character.character_id, mal_character))
+        .collect::<BTreeMap<_, _>>();
+    Ok(user_rolls
+        .into_iter()
+        .map(|user_roll| {
+            let mal_character = mal_characters
+                .get(&user_roll.mal_character_id)
+                .cloned()
+                .unwrap_or_default();
+            let duplicate = is_duplicate(character_rolls, &user_roll);
+            GetRollResult {
+                user_roll,
+                rarity: mal_character.rarity,
+                mal_character,
+                duplicate,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_character(character_id: i32, rarity: Rarity) -> MalCharacter {
+        MalCharacter {
+            character_id,
+            rarity,
+            ..MalCharacter::default()
+        }
+    }
+
+    #[test]
+    fn guarantees_the_pity_rarity_at_hard_pity() {
+        let rules = &CONFIGURATION.roll_rules;
+        let characters = [
+            create_character(1, Rarity::Common),
+            create_character(2, rules.pity_rarity),
+        ];
+
+        let rarities = get_rollable_rarities(&characters, 0);
+        assert_eq!(rarities.len(), 2);
+        let rarities = get_rollable_rarities(&characters, rules.hard_pity - 1);
+        assert_eq!(rarities.len(), 1);
+        assert_eq!(rarities[0].0, rules.pity_rarity);
+        for _ in 0..20 {
+            let mal_character = pick_character(&characters, rules.hard_pity - 1, None);
+            assert_eq!(
+                mal_character.map(|character| character.character_id),
+                Some(2)
+            );
+        }
+
+        // Without a character of the pity rarity the guarantee can't be honored.
+        let characters = [create_character(1, Rarity::Common)];
+        let rarities = get_rollable_rarities(&characters, rules.hard_pity - 1);
+        assert_eq!(rarities.len(), 1);
+        assert_eq!(rarities[0].0, Rarity::Common);
+    }
+
+    #[test]
+    fn reports_rolls_until_hard_pity() {
+        let rules = &CONFIGURATION.roll_rules;
+        let pity_status = get_pity_status(0);
+        assert_eq!(pity_status.rolls_until_guaranteed, Some(rules.hard_pity));
+        assert_eq!(pity_status.pity_rarity, rules.pity_rarity);
+        let pity_status = get_pity_status(rules.hard_pity - 1);
+        assert_eq!(pity_status.rolls_until_guaranteed, Some(1));
+        let pity_status = get_pity_status(rules.hard_pity + 10);
+        assert_eq!(pity_status.rolls_until_guaranteed, Some(1));
+    }
+}
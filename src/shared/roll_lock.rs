@@ -0,0 +1,85 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Rolls are made, spent and moved one request per user at a time so that every roll of a user
+/// gets a distinct roll ID and no roll is spent twice. Requests for different users run
+/// concurrently. Other server instances are kept in check by the repositories' conditional writes.
+pub static ROLL_LOCKS: Lazy<RollLocks> = Lazy::new(RollLocks::default);
+
+#[derive(Default)]
+pub struct RollLocks {
+    locks: DashMap<String, Arc<Mutex<()>>>,
+}
+
+/// Holds the rolls of some users until it is dropped.
+pub struct RollGuard<'a> {
+    locks: &'a RollLocks,
+    guards: Vec<(String, OwnedMutexGuard<()>)>,
+}
+
+impl RollLocks {
+    /// Waits until nobody else holds the rolls of any of the given users, then holds all of them.
+    /// Users are always locked in the same order, so requests involving the same users can't
+    /// deadlock.
+    pub async fn lock<I, S>(&self, user_ids: I) -> RollGuard<'_>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let user_ids = user_ids
+            .into_iter()
+            .map(|user_id| user_id.as_ref().to_string())
+            .collect::<BTreeSet<_>>();
+        let mut guards = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids {
+            let lock = self.locks.entry(user_id.clone()).or_default().clone();
+            guards.push((user_id, lock.lock_owned().await));
+        }
+        RollGuard {
+            locks: self,
+            guards,
+        }
+    }
+}
+
+impl Drop for RollGuard<'_> {
+    fn drop(&mut self) {
+        for (user_id, guard) in self.guards.drain(..) {
+            drop(guard);
+            // Locks that nobody holds or waits for are dropped, so there is only one per busy user.
+            self.locks
+                .locks
+                .remove_if(&user_id, |_, lock| Arc::strong_count(lock) == 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn locks_users_independently() {
+        let locks = RollLocks::default();
+        let guard = locks.lock(["alice", "bob"]).await;
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), locks.lock(["carol"]))
+                .await
+                .is_ok()
+        );
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), locks.lock(["carol", "bob"]))
+                .await
+                .is_err()
+        );
+
+        drop(guard);
+        drop(locks.lock(["bob", "carol"]).await);
+        assert!(locks.locks.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+use time::{OffsetDateTime, PrimitiveDateTime, Time, UtcOffset, Weekday};
+use tz::datetime::FoundDateTimeKind;
+use tz::{DateTime, TimeZoneRef};
+
+/// An IANA time zone from the copy of the time zone database built into the server, so that
+/// resets don't depend on the zone files installed on the host.
+#[derive(Clone, Debug)]
+pub struct TimeZone {
+    time_zone: TimeZoneRef<'static>,
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        TimeZone {
+            time_zone: TimeZoneRef::utc(),
+        }
+    }
+
+    /// Looks up a time zone by its IANA name, e.g. `Asia/Tokyo`.
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        tzdb_data::find_tz(name.as_bytes())
+            .map(|time_zone| TimeZone {
+                time_zone: *time_zone,
+            })
+            .ok_or_else(|| anyhow::anyhow!("{} is not a known time zone name.", name))
+    }
+
+    /// The UTC offset in effect at the given instant.
+    pub fn get_offset(&self, instant: OffsetDateTime) -> UtcOffset {
+        self.time_zone
+            .find_local_time_type(instant.unix_timestamp())
+            .ok()
+            .and_then(|local_time_type| {
+                UtcOffset::from_whole_seconds(local_time_type.ut_offset()).ok()
+            })
+            .unwrap_or(UtcOffset::UTC)
+    }
+
+    pub fn to_local(&self, instant: OffsetDateTime) -> OffsetDateTime {
+        instant.to_offset(self.get_offset(instant))
+    }
+
+    /// Resolves a wall-clock time in this time zone. Times skipped by a DST gap are shifted forward by the gap,
+    /// and times repeated by a DST overlap resolve to the earlier instant.
+    pub fn resolve_local(&self, local: PrimitiveDateTime) -> OffsetDateTime {
+        let found = DateTime::find(
+            local.year(),
+            local.month() as u8,
+            local.day(),
+            local.hour(),
+            local.minute(),
+            local.second(),
+            local.nanosecond(),
+            self.time_zone,
+        )
+        .ok()
+        .and_then(|found| found.into_inner().into_iter().next());
+        let offset = match found {
+            // Repeated times are found in ascending order, so the first one is the earlier instant.
+            Some(FoundDateTimeKind::Normal(date_time)) => date_time.local_time_type().ut_offset(),
+            // In a gap, reading the wall clock with the earlier offset lands the same distance past the transition.
+            Some(FoundDateTimeKind::Skipped {
+                before_transition, ..
+            }) => before_transition.local_time_type().ut_offset(),
+            None => return local.assume_offset(self.get_offset(local.assume_utc())),
+        };
+        local.assume_offset(UtcOffset::from_whole_seconds(offset).unwrap_or(UtcOffset::UTC))
+    }
+
+    /// The first instant strictly after `after` at which the wall clock in this time zone reads `time`,
+    /// optionally restricted to the given weekday.
+    pub fn get_next_occurrence(
+        &self,
+        after: OffsetDateTime,
+        time: Time,
+        weekday: Option<Weekday>,
+    ) -> OffsetDateTime {
+        let mut date = self.to_local(after).date();
+        loop {
+            let candidate = self.resolve_local(PrimitiveDateTime::new(date, time));
+            if candidate > after && (weekday.is_none() || weekday == Some(date.weekday())) {
+                return candidate;
+            }
+            match date.next_day() {
+                Some(next_day) => date = next_day,
+                None => return candidate,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    fn local(year: i32, month: u8, day: u8, hour: u8, minute: u8) -> PrimitiveDateTime {
+        let month = Month::try_from(month).unwrap();
+        PrimitiveDateTime::new(
+            Date::from_calendar_date(year, month, day).unwrap(),
+            Time::from_hms(hour, minute, 0).unwrap(),
+        )
+    }
+
+    fn utc(year: i32, month: u8, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        local(year, month, day, hour, minute).assume_utc()
+    }
+
+    fn hours(hours: i8) -> UtcOffset {
+        UtcOffset::from_hms(hours, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn fixed_offset_zone() {
+        let tokyo = TimeZone::load("Asia/Tokyo").unwrap();
+        for instant in [
+            utc(1990, 1, 1, 0, 0),
+            utc(2026, 7, 1, 12, 0),
+            utc(2100, 12, 31, 23, 59),
+        ] {
+            assert_eq!(tokyo.get_offset(instant), hours(9));
+        }
+
+        let reset = tokyo.get_next_occurrence(utc(2026, 10, 18, 14, 59), Time::MIDNIGHT, None);
+        assert_eq!(reset, utc(2026, 10, 18, 15, 0));
+        // That reset was already midnight on Monday 19 October in Tokyo, so the next Monday is a week later.
+        let reset = tokyo.get_next_occurrence(reset, Time::MIDNIGHT, Some(Weekday::Monday));
+        assert_eq!(reset, utc(2026, 10, 18, 15, 0) + time::Duration::WEEK);
+    }
+
+    #[test]
+    fn daylight_saving_gap() {
+        let new_york = TimeZone::load("America/New_York").unwrap();
+        // Clocks jump from 02:00 EST to 03:00 EDT at 07:00 UTC.
+        assert_eq!(new_york.get_offset(utc(2026, 3, 8, 6, 59)), hours(-5));
+        assert_eq!(new_york.get_offset(utc(2026, 3, 8, 7, 0)), hours(-4));
+
+        assert_eq!(
+            new_york.resolve_local(local(2026, 3, 8, 1, 30)),
+            utc(2026, 3, 8, 6, 30)
+        );
+        assert_eq!(
+            new_york.resolve_local(local(2026, 3, 8, 2, 30)),
+            utc(2026, 3, 8, 7, 30)
+        );
+        assert_eq!(
+            new_york.resolve_local(local(2026, 3, 8, 3, 30)),
+            utc(2026, 3, 8, 7, 30)
+        );
+
+        let reset = Time::from_hms(2, 30, 0).unwrap();
+        let next = new_york.get_next_occurrence(utc(2026, 3, 7, 12, 0), reset, None);
+        assert_eq!(next, utc(2026, 3, 8, 7, 30));
+        let next = new_york.get_next_occurrence(next, reset, None);
+        assert_eq!(next, utc(2026, 3, 9, 6, 30));
+    }
+
+    #[test]
+    fn daylight_saving_overlap() {
+        let new_york = TimeZone::load("America/New_York").unwrap();
+        // Clocks fall back from 02:00 EDT to 01:00 EST at 06:00 UTC.
+        assert_eq!(new_york.get_offset(utc(2026, 11, 1, 5, 59)), hours(-4));
+        assert_eq!(new_york.get_offset(utc(2026, 11, 1, 6, 0)), hours(-5));
+
+        assert_eq!(
+            new_york.resolve_local(local(2026, 11, 1, 0, 30)),
+            utc(2026, 11, 1, 4, 30)
+        );
+        assert_eq!(
+            new_york.resolve_local(local(2026, 11, 1, 1, 30)),
+            utc(2026, 11, 1, 5, 30)
+        );
+        assert_eq!(
+            new_york.resolve_local(local(2026, 11, 1, 2, 30)),
+            utc(2026, 11, 1, 7, 30)
+        );
+
+        let reset = Time::from_hms(1, 30, 0).unwrap();
+        let next = new_york.get_next_occurrence(utc(2026, 10, 31, 12, 0), reset, None);
+        assert_eq!(next, utc(2026, 11, 1, 5, 30));
+        let next = new_york.get_next_occurrence(next, reset, None);
+        assert_eq!(next, utc(2026, 11, 2, 6, 30));
+    }
+
+    #[test]
+    fn far_future_instants() {
+        let new_york = TimeZone::load("America/New_York").unwrap();
+        // In 2100, DST runs from 14 March to 7 November.
+        assert_eq!(new_york.get_offset(utc(2100, 1, 15, 12, 0)), hours(-5));
+        assert_eq!(new_york.get_offset(utc(2100, 3, 14, 6, 59)), hours(-5));
+        assert_eq!(new_york.get_offset(utc(2100, 3, 14, 7, 0)), hours(-4));
+        assert_eq!(new_york.get_offset(utc(2100, 11, 7, 5, 59)), hours(-4));
+        assert_eq!(new_york.get_offset(utc(2100, 11, 7, 6, 0)), hours(-5));
+        assert_eq!(
+            new_york.resolve_local(local(2100, 3, 14, 2, 30)),
+            utc(2100, 3, 14, 7, 30)
+        );
+    }
+
+    #[test]
+    fn southern_hemisphere_zone() {
+        let sydney = TimeZone::load("Australia/Sydney").unwrap();
+        // DST ends at 03:00 AEDT on 5 April 2026 and starts at 02:00 AEST on 4 October 2026.
+        assert_eq!(sydney.get_offset(utc(2026, 1, 15, 0, 0)), hours(11));
+        assert_eq!(sydney.get_offset(utc(2026, 4, 4, 15, 59)), hours(11));
+        assert_eq!(sydney.get_offset(utc(2026, 4, 4, 16, 0)), hours(10));
+        assert_eq!(sydney.get_offset(utc(2026, 10, 3, 15, 59)), hours(10));
+        assert_eq!(sydney.get_offset(utc(2026, 10, 3, 16, 0)), hours(11));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for name in [
+            "",
+            "/etc/passwd",
+            "../zoneinfo/UTC",
+            "Asia//Tokyo",
+            "Asia/Tokyo ",
+        ] {
+            assert!(TimeZone::load(name).is_err(), "{name:?} should be rejected");
+        }
+        assert!(TimeZone::load("Not/AZone").is_err());
+        assert_eq!(
+            TimeZone::load("UTC")
+                .unwrap()
+                .get_offset(utc(2026, 7, 1, 0, 0)),
+            UtcOffset::UTC
+        );
+    }
+}
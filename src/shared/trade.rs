@@ -0,0 +1,350 @@
+use crate::db::repository::{Repository, RepositoryError};
+use crate::model::credit_transaction::CreditTransactionReason;
+use crate::model::errors::ServerError;
+use crate::model::trade::{PendingTradeOffers, TradeOffer, TradeOfferRequest, TradeStatus};
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::credit::{pay, CreditUpdateError};
+use crate::shared::roll;
+use crate::shared::roll_lock::ROLL_LOCKS;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::ops::Add;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum TradeError {
+    InvalidOffer(String),
+    OfferNotFound,
+    /// The user isn't the one who can make this response to the offer.
+    NotParticipant,
+    NotPending(TradeStatus),
+    /// Some of the rolls have been traded, converted or spent since the offer was made.
+    RollsUnavailable,
+    Credit(CreditUpdateError),
+    Repository(RepositoryError),
+}
+
+impl IntoResponse for TradeError {
+    fn into_response(self) -> Response {
+        let (status_code, error_message) = match self {
+            TradeError::InvalidOffer(error_message) => (StatusCode::BAD_REQUEST, error_message),
+            TradeError::OfferNotFound => (
+                StatusCode::NOT_FOUND,
+                "The specified trade offer is not found.".to_string(),
+            ),
+            TradeError::NotParticipant => (
+                StatusCode::FORBIDDEN,
+                "The specified user can't respond to this trade offer this way.".to_string(),
+            ),
+            TradeError::NotPending(status) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "The trade offer is no longer pending, it is {}.",
+                    status.as_str().to_lowercase()
+                ),
+            ),
+            TradeError::RollsUnavailable => (
+                StatusCode::CONFLICT,
+                "Some of the rolls in the trade offer are no longer available.".to_string(),
+            ),
+            TradeError::Credit(e) => return e.into_response(),
+            TradeError::Repository(e) => {
+                let error_message = format!("Failed to process the trade offer: {}", e);
+                tracing::error!("{}", &error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            }
+        };
+        (status_code, Json(ServerError { error_message })).into_response()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TradeResponse {
+    Accept,
+    Reject,
+    Cancel,
+}
+
+pub async fn create_trade_offer(
+    repository: &dyn Repository,
+    request: TradeOfferRequest,
+) -> Result<TradeOffer, TradeError> {
+    validate_request(&request).map_err(TradeError::InvalidOffer)?;
+
+    for user_id in [&request.from_user_id, &request.to_user_id] {
+        let user_credit = repository
+            .get_user_credit(user_id)
+            .await
+            .map_err(TradeError::Repository)?
+            .ok_or_else(|| {
+                TradeError::InvalidOffer(format!(
+                    "The user {}'s credit info is not found.",
+                    user_id
+                ))
+            })?;
+        if user_id == &request.from_user_id && user_credit.credits < request.offered_credits {
+            return Err(TradeError::Credit(CreditUpdateError::InsufficientCredits));
+        }
+    }
+
+    roll::get_available_rolls(repository, &request.from_user_id, &request.offered_roll_ids)
+        .await
+        .map_err(TradeError::Repository)?
+        .ok_or_else(|| {
+            TradeError::InvalidOffer("The offered rolls aren't all available.".to_string())
+        })?;
+    roll::get_available_rolls(repository, &request.to_user_id, &request.requested_roll_ids)
+        .await
+        .map_err(TradeError::Repository)?
+        .ok_or_else(|| {
+            TradeError::InvalidOffer("The requested rolls aren't all available.".to_string())
+        })?;
+
+    let now = OffsetDateTime::now_utc();
+    let trade_offer = TradeOffer {
+        id: Uuid::new_v4().to_string(),
+        from_user_id: request.from_user_id,
+        to_user_id: request.to_user_id,
+        offered_roll_ids: request.offered_roll_ids,
+        requested_roll_ids: request.requested_roll_ids,
+        offered_credits: request.offered_credits,
+        status: TradeStatus::Pending,
+        created_at: now,
+        expires_at: now.add(time::Duration::hours(
+            CONFIGURATION.trade_rules.offer_expiry_hours,
+        )),
+        resolved_at: None,
+    };
+    repository
+        .upsert_trade_offer(trade_offer.clone())
+        .await
+        .map_err(TradeError::Repository)?;
+    Ok(trade_offer)
+}
+
+fn validate_request(request: &TradeOfferRequest) -> Result<(), String> {
+    let max_rolls = CONFIGURATION.trade_rules.max_rolls_per_offer;
+    if request.from_user_id == request.to_user_id {
+        return Err("Users can't trade with themselves.".to_string());
+    }
+    if request.requested_roll_ids.is_empty() {
+        return Err("The offer needs to request at least one roll.".to_string());
+    }
+    if request.offered_roll_ids.is_empty() && request.offered_credits == 0 {
+        return Err("The offer needs to give at least one roll or some credits.".to_string());
+    }
+    if request.offered_credits < 0 {
+        return Err("The offered credits can't be negative.".to_string());
+    }
+    for roll_ids in [&request.offered_roll_ids, &request.requested_roll_ids] {
+        if roll_ids.len() > max_rolls {
+            return Err(format!(
+                "Each side of an offer can include at most {} rolls.",
+                max_rolls
+            ));
+        }
+        if roll_ids
+            .iter()
+            .enumerate()
+            .any(|(index, roll_id)| roll_ids[..index].contains(roll_id))
+        {
+            return Err("The same roll can't be included twice.".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_pending_trade_offers(
+    repository: &dyn Repository,
+    user_id: &str,
+) -> Result<PendingTradeOffers, RepositoryError> {
+    let now = OffsetDateTime::now_utc();
+    let (incoming, outgoing) = repository
+        .get_trade_offers(user_id)
+        .await?
+        .into_iter()
+        .filter(|trade_offer| trade_offer.get_status(now) == TradeStatus::Pending)
+        .partition(|trade_offer| trade_offer.to_user_id == user_id);
+    Ok(PendingTradeOffers { incoming, outgoing })
+}
+
+/// Accepts, rejects or cancels a pending offer. Only the user who received the offer can accept
+/// or reject it, and only the user who made it can cancel it.
+pub async fn respond_to_trade_offer(
+    repository: &dyn Repository,
+    offer_id: &str,
+    user_id: &str,
+    response: TradeResponse,
+    actor: &str,
+) -> Result<TradeOffer, TradeError> {
+    let participants = repository
+        .get_trade_offer(offer_id)
+        .await
+        .map_err(TradeError::Repository)?
+        .ok_or(TradeError::OfferNotFound)?;
+    let _guard = ROLL_LOCKS
+        .lock([&participants.from_user_id, &participants.to_user_id])
+        .await;
+
+    let trade_offer = repository
+        .get_trade_offer(offer_id)
+        .await
+        .map_err(TradeError::Repository)?
+        .ok_or(TradeError::OfferNotFound)?;
+    let responder = match response {
+        TradeResponse::Accept | TradeResponse::Reject => &trade_offer.to_user_id,
+        TradeResponse::Cancel => &trade_offer.from_user_id,
+    };
+    if responder != user_id {
+        return Err(TradeError::NotParticipant);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    match trade_offer.get_status(now) {
+        TradeStatus::Pending => {}
+        TradeStatus::Expired if trade_offer.status == TradeStatus::Pending => {
+            let expires_at = trade_offer.expires_at;
+            resolve(repository, trade_offer, TradeStatus::Expired, expires_at).await?;
+            return Err(TradeError::NotPending(TradeStatus::Expired));
+        }
+        status => return Err(TradeError::NotPending(status)),
+    }
+
+    match response {
+        TradeResponse::Accept => accept(repository, trade_offer, actor, now).await,
+        TradeResponse::Reject => resolve(repository, trade_offer, TradeStatus::Rejected, now).await,
+        TradeResponse::Cancel => {
+            resolve(repository, trade_offer, TradeStatus::Cancelled, now).await
+        }
+    }
+}
+
+async fn resolve(
+    repository: &dyn Repository,
+    trade_offer: TradeOffer,
+    status: TradeStatus,
+    resolved_at: OffsetDateTime,
+) -> Result<TradeOffer, TradeError> {
+    let trade_offer = TradeOffer {
+        status,
+        resolved_at: Some(resolved_at),
+        ..trade_offer
+    };
+    repository
+        .upsert_trade_offer(trade_offer.clone())
+        .await
+        .map_err(TradeError::Repository)?;
+    Ok(trade_offer)
+}
+
+/// Holds the offered credits from the sender, moves the rolls, and only then pays the credits to
+/// the recipient. The held credits go back to the sender if the rolls can't be moved. Failed
+/// payments are returned to the caller rather than logged, so no credits are minted or lost
+/// silently.
+async fn accept(
+    repository: &dyn Repository,
+    trade_offer: TradeOffer,
+    actor: &str,
+    now: OffsetDateTime,
+) -> Result<TradeOffer, TradeError> {
+    let offered_rolls = roll::get_available_rolls(
+        repository,
+        &trade_offer.from_user_id,
+        &trade_offer.offered_roll_ids,
+    )
+    .await
+    .map_err(TradeError::Repository)?
+    .ok_or(TradeError::RollsUnavailable)?;
+    let requested_rolls = roll::get_available_rolls(
+        repository,
+        &trade_offer.to_user_id,
+        &trade_offer.requested_roll_ids,
+    )
+    .await
+    .map_err(TradeError::Repository)?
+    .ok_or(TradeError::RollsUnavailable)?;
+
+    let mut transfers = roll::get_transfers(repository, offered_rolls, &trade_offer.to_user_id)
+        .await
+        .map_err(TradeError::Repository)?;
+    transfers.extend(
+        roll::get_transfers(repository, requested_rolls, &trade_offer.from_user_id)
+            .await
+            .map_err(TradeError::Repository)?,
+    );
+
+    let credits = trade_offer.offered_credits;
+    if credits > 0 {
+        pay(
+            repository,
+            &trade_offer.from_user_id,
+            -credits,
+            CreditTransactionReason::TradeSent,
+            actor,
+        )
+        .await
+        .map_err(TradeError::Credit)?;
+    }
+
+    let trade_offer = TradeOffer {
+        status: TradeStatus::Accepted,
+        resolved_at: Some(now),
+        ..trade_offer
+    };
+    if let Err(e) = repository
+        .complete_trade_offer(trade_offer.clone(), transfers)
+        .await
+    {
+        if credits > 0 {
+            if let Err(refund_error) = pay(
+                repository,
+                &trade_offer.from_user_id,
+                credits,
+                CreditTransactionReason::TradeRefund,
+                actor,
+            )
+            .await
+            {
+                tracing::error!(
+                    "Failed to move the rolls of trade offer {} ({}) or to give {} credits back \
+                     to user {}: {:?}",
+                    trade_offer.id,
+                    e,
+                    credits,
+                    trade_offer.from_user_id,
+                    refund_error
+                );
+                return Err(TradeError::Credit(refund_error));
+            }
+        }
+        return Err(match e {
+            RepositoryError::Conflict => TradeError::RollsUnavailable,
+            e => TradeError::Repository(e),
+        });
+    }
+
+    if credits > 0 {
+        if let Err(e) = pay(
+            repository,
+            &trade_offer.to_user_id,
+            credits,
+            CreditTransactionReason::TradeReceived,
+            actor,
+        )
+        .await
+        {
+            tracing::error!(
+                "Trade offer {} was accepted but {} credits couldn't be paid to user {}: {:?}",
+                trade_offer.id,
+                credits,
+                trade_offer.to_user_id,
+                e
+            );
+            return Err(TradeError::Credit(e));
+        }
+    }
+
+    Ok(trade_offer)
+}
@@ -1,17 +1,16 @@
-use crate::controller::credit_controller::USER_CREDITS;
 use crate::model::cosmos_db::CosmosDb;
-use crate::model::errors::ServerError;
-use crate::model::user_credit::{UserCredit, UserCreditUpdateInfo, UserCreditUpdateOpt};
+use crate::model::page::{Page, PageRequest};
 use crate::CONFIGURATION;
-use axum::http::StatusCode;
-use axum::response::{IntoResponse, Response};
-use axum::Json;
+use azure_core::headers::Header;
 use azure_data_cosmos::prelude::*;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-pub async fn get_documents<T, S>(database: &DatabaseClient, collection_name: S) -> Option<Vec<T>>
+pub async fn get_documents<T, S>(
+    database: &DatabaseClient,
+    collection_name: S,
+) -> azure_core::Result<Vec<T>>
 where
     T: DeserializeOwned + Send + Sync + Clone,
     S: Into<std::borrow::Cow<'static, str>>,
@@ -21,28 +20,59 @@ where
     collection
         .list_documents()
         .into_stream::<T>()
-        .collect::<Vec<_>>()
+        .try_collect::<Vec<_>>()
         .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| tracing::error!("Failed to retrieve all documents: {}", e))
-        .ok()
-        .and_then(|result| result.first().cloned())
-        .map(|response| {
-            response
-                .documents
+        .map(|responses| {
+            responses
                 .into_iter()
+                .flat_map(|response| response.documents)
                 .map(|document| document.document)
                 .collect::<Vec<_>>()
         })
 }
 
+pub async fn get_documents_page<T, S>(
+    database: &DatabaseClient,
+    collection_name: S,
+    page_request: &PageRequest,
+) -> azure_core::Result<Page<T>>
+where
+    T: DeserializeOwned + Send + Sync + Clone,
+    S: Into<std::borrow::Cow<'static, str>>,
+{
+    let collection = database.collection_client(collection_name);
+
+    let mut list_documents = collection
+        .list_documents()
+        .max_item_count(page_request.page_size() as i32);
+    if let Some(continuation) = page_request.continuation.clone() {
+        list_documents = list_documents.continuation(continuation);
+    }
+
+    let response = list_documents.into_stream::<T>().next().await.transpose()?;
+    Ok(response
+        .map(|response| Page {
+            items: response
+                .documents
+                .into_iter()
+                .map(|document| document.document)
+                .collect(),
+            continuation: response
+                .continuation_token
+                .map(|token| token.value().as_str().to_string()),
+        })
+        .unwrap_or(Page {
+            items: vec![],
+            continuation: None,
+        }))
+}
+
 pub async fn query_document<T, S, Q>(
     database: &DatabaseClient,
     collection_name: S,
     query: Q,
     cross_partition: bool,
-) -> Option<Vec<T>>
+) -> azure_core::Result<Vec<T>>
 where
     T: DeserializeOwned + Send + Sync + Clone,
     S: Into<std::borrow::Cow<'static, str>>,
@@ -56,31 +86,24 @@ pub async fn query_document_within_collection<T, Q>(
     collection: &CollectionClient,
     query: Q,
     cross_partition: bool,
-) -> Option<Vec<T>>
+) -> azure_core::Result<Vec<T>>
 where
     T: DeserializeOwned + Send + Sync + Clone,
     Q: Into<Query>,
 {
-    let documents: Option<Vec<T>> = collection
+    collection
         .query_documents(query)
         .query_cross_partition(cross_partition)
         .into_stream::<T>()
-        .collect::<Vec<_>>()
+        .try_collect::<Vec<_>>()
         .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| tracing::error!("Failed to retrieve document: {}", e))
-        .ok()
-        .and_then(|result| result.first().cloned())
-        .map(|response| {
-            response
-                .results
+        .map(|responses| {
+            responses
                 .into_iter()
+                .flat_map(|response| response.results)
                 .map(|(data, _attrs)| data)
-                .collect()
-        });
-
-    documents.filter(|document| !document.is_empty())
+                .collect::<Vec<_>>()
+        })
 }
 
 pub async fn add_document<S, D>(
@@ -108,65 +131,6 @@ pub async fn add_document_into_collection<D: Serialize + CosmosEntity + Send + '
         .await
 }
 
-pub async fn adjust_credit(
-    database: &DatabaseClient,
-    user_id: String,
-    request: UserCreditUpdateInfo,
-    opt: UserCreditUpdateOpt,
-) -> Response {
-    let collection = database.collection_client(USER_CREDITS);
-    adjust_credit_in_collection(&collection, user_id, request, opt).await
-}
-
-pub async fn adjust_credit_in_collection(
-    credit_collection: &CollectionClient,
-    user_id: String,
-    request: UserCreditUpdateInfo,
-    opt: UserCreditUpdateOpt,
-) -> Response {
-    let query = Query::with_params(
-        "SELECT * FROM UserCredits u WHERE u.user_id = @user_id".into(),
-        vec![Param::new("@user_id".into(), user_id)],
-    );
-
-    let query_result =
-        query_document_within_collection::<UserCredit, _>(credit_collection, query, true).await;
-    if query_result.is_none() {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ServerError {
-                error_message:
-                    "Cannot update user's credit because the specified user doesn't exist.".into(),
-            }),
-        )
-            .into_response();
-    }
-
-    let query_result = query_result
-        .and_then(|v| v.first().cloned())
-        .unwrap_or_default();
-    let new_document = UserCredit {
-        credits: match opt {
-            UserCreditUpdateOpt::Plus => query_result.credits + request.credit,
-            UserCreditUpdateOpt::Minus => query_result.credits - request.credit,
-        },
-        ..query_result
-    };
-
-    match add_document_into_collection(credit_collection, new_document.clone()).await {
-        Ok(_) => (StatusCode::OK, Json(new_document)).into_response(),
-        Err(e) => {
-            let error_message = format!("{}", e);
-            tracing::error!("{}", &error_message);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ServerError { error_message }),
-            )
-                .into_response()
-        }
-    }
-}
-
 pub fn initialize_clients() -> CosmosDb {
     let authorization_token = AuthorizationToken::primary_key(&CONFIGURATION.cosmos_db_primary_key)
         .map_err(|e| tracing::error!("Failed to generate authorization token for CosmosDB: {}", e))
@@ -0,0 +1,90 @@
+use super::TestApp;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+fn create_banner(name: &str, start_time: OffsetDateTime, end_time: OffsetDateTime) -> Value {
+    json!({
+        "name": name,
+        "start_time": start_time.format(&Rfc3339).unwrap_or_default(),
+        "end_time": end_time.format(&Rfc3339).unwrap_or_default(),
+        "featured_character_ids": [2],
+        "rate_up": 2.0,
+        "roll_cost": 3,
+    })
+}
+
+#[tokio::test]
+async fn lists_and_validates_banners() {
+    let app = TestApp::new().await;
+    let now = OffsetDateTime::now_utc();
+
+    let banner = create_banner("Reversed", now, now - Duration::HOUR);
+    let response = app.post("/banners", banner).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let banner = create_banner("Running", now - Duration::HOUR, now + Duration::HOUR);
+    let response = app.post("/banners", banner).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let banner_id = response.body["id"].as_str().unwrap_or_default().to_string();
+    assert!(!banner_id.is_empty());
+    let banner = create_banner("Ended", now - Duration::DAY, now - Duration::HOUR);
+    let response = app.post("/banners", banner).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+
+    let response = app.get("/banners").await;
+    assert_eq!(response.body.as_array().map(Vec::len), Some(2));
+    let response = app.get("/banners?active=true").await;
+    assert_eq!(response.body.as_array().map(Vec::len), Some(1));
+    assert_eq!(response.body[0]["id"], banner_id.as_str());
+
+    let response = app.get(&format!("/banners/{}", banner_id)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["name"], "Running");
+    let response = app.get("/banners/unknown").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rolls_on_running_banners_only() {
+    let app = TestApp::new().await;
+    let now = OffsetDateTime::now_utc();
+    app.add_user("alice", 10).await;
+    app.add_mal_character(2, "Common").await;
+
+    let banner = create_banner("Running", now - Duration::HOUR, now + Duration::HOUR);
+    let banner_id = app.post("/banners", banner).await.body["id"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let banner = create_banner("Ended", now - Duration::DAY, now - Duration::HOUR);
+    let ended_banner_id = app.post("/banners", banner).await.body["id"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let response = app
+        .post(
+            &format!("/user_roll/alice/roll?banner_id={}", ended_banner_id),
+            json!({}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app
+        .post("/user_roll/alice/roll?banner_id=unknown", json!({}))
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(app.get_credits("alice").await, 10);
+
+    // Banner rolls cost what the banner asks for instead of the configured roll cost.
+    let response = app
+        .post(
+            &format!("/user_roll/alice/roll?banner_id={}", banner_id),
+            json!({}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["user_roll"]["BannerId"], banner_id.as_str());
+    assert_eq!(app.get_credits("alice").await, 7);
+}
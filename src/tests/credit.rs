@@ -0,0 +1,124 @@
+use super::faulty_repository::Fault;
+use super::TestApp;
+use crate::shared::constants::CONTINUATION_HEADER;
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+#[tokio::test]
+async fn adds_and_adjusts_credits() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+
+    let response = app
+        .post(
+            "/credit",
+            json!({ "id": "alice", "username": "alice", "user_id": "alice", "credits": 5 }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let response = app
+        .patch("/credit/alice/plus", json!({ "credit": 50 }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let response = app
+        .patch("/credit/alice/minus", json!({ "credit": 30 }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(app.get_credits("alice").await, 120);
+
+    let response = app.get("/credit/alice/history").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let mut amounts = response
+        .body
+        .as_array()
+        .expect("The credit history isn't a list.")
+        .iter()
+        .map(|transaction| transaction["amount"].as_i64().unwrap_or_default())
+        .collect::<Vec<_>>();
+    amounts.sort_unstable();
+    assert_eq!(amounts, vec![-30, 50]);
+
+    let response = app.patch("/credit/bob/plus", json!({ "credit": 50 })).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_requests_without_a_token() {
+    let mut app = TestApp::new().await;
+    app.token = None;
+
+    let response = app.request(Method::GET, "/credit", None).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn gives_up_after_repeated_conflicts() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+    app.repository.fail("replace_user_credit", Fault::Conflict);
+
+    let response = app
+        .patch("/credit/alice/plus", json!({ "credit": 50 }))
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(app.get_credits("alice").await, 100);
+
+    let response = app.get("/credit/alice/history").await;
+    assert_eq!(response.body, json!([]));
+}
+
+#[tokio::test]
+async fn reports_storage_errors() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+
+    app.repository.fail("get_user_credit", Fault::Backend);
+    let response = app.get("/credit/alice").await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    app.repository.recover("get_user_credit");
+    assert_eq!(app.get_credits("alice").await, 100);
+}
+
+#[tokio::test]
+async fn pages_through_credits() {
+    let app = TestApp::new().await;
+    for user_id in ["alice", "bob", "carol"] {
+        app.add_user(user_id, 100).await;
+    }
+
+    let mut user_ids = vec![];
+    let mut uri = "/credit?limit=2".to_string();
+    loop {
+        let response = app.get(&uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+        user_ids.extend(
+            response
+                .body
+                .as_array()
+                .expect("The credits aren't a list.")
+                .iter()
+                .map(|user_credit| {
+                    user_credit["user_id"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+        );
+        match response.headers.get(CONTINUATION_HEADER) {
+            Some(continuation) => {
+                uri = format!(
+                    "/credit?limit=2&continuation={}",
+                    continuation.to_str().unwrap_or_default()
+                );
+            }
+            None => break,
+        }
+    }
+    assert_eq!(user_ids, vec!["alice", "bob", "carol"]);
+
+    let response = app.get("/credit").await;
+    assert_eq!(response.body.as_array().map(Vec::len), Some(3));
+    assert!(response.headers.get(CONTINUATION_HEADER).is_none());
+}
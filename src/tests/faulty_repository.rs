@@ -0,0 +1,176 @@
+use crate::db::in_memory::InMemoryRepository;
+use crate::db::repository::{
+    BannerRepository, CollectionRepository, CreditRepository, JackpotRepository, LedgerRepository,
+    LotteryDrawRepository, LotteryRepository, MalCharacterRepository, MarketRepository,
+    RepositoryError, RepositoryResult, RewardRepository, RollRepository, TradeRepository,
+    Versioned,
+};
+use crate::model::banner::Banner;
+use crate::model::collection::CharacterProgress;
+use crate::model::credit_transaction::{CreditHistoryQuery, CreditTransaction};
+use crate::model::lottery::draw::{LotteryCommitment, LotteryDraw, LotteryDrawResult};
+use crate::model::lottery::jackpot::LotteryJackpot;
+use crate::model::lottery::UserLottery;
+use crate::model::mal_character::{MalCharacter, RarityAssignment};
+use crate::model::market::MarketListing;
+use crate::model::page::{Page, PageRequest};
+use crate::model::rewards::Rewards;
+use crate::model::trade::TradeOffer;
+use crate::model::user_credit::UserCredit;
+use crate::model::user_roll::{CharacterRollCount, RollHistoryQuery, RollPity, UserRoll};
+use axum::async_trait;
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// How an operation of a [`FaultyRepository`] fails.
+#[derive(Copy, Clone, Debug)]
+pub enum Fault {
+    /// As if another request always modified the document first.
+    Conflict,
+    /// As if the database were unreachable.
+    Backend,
+}
+
+/// An in-memory repository whose operations can be made to fail, to exercise the paths that
+/// recover from storage errors.
+#[derive(Clone, Default)]
+pub struct FaultyRepository {
+    repository: InMemoryRepository,
+    faults: Arc<DashMap<&'static str, Fault>>,
+}
+
+impl FaultyRepository {
+    /// Makes every call of the repository operation with the given name fail until it recovers.
+    pub fn fail(&self, operation: &'static str, fault: Fault) {
+        self.faults.insert(operation, fault);
+    }
+
+    pub fn recover(&self, operation: &'static str) {
+        self.faults.remove(operation);
+    }
+
+    fn check(&self, operation: &'static str) -> RepositoryResult<()> {
+        match self.faults.get(operation).map(|fault| *fault) {
+            None => Ok(()),
+            Some(Fault::Conflict) => Err(RepositoryError::Conflict),
+            Some(Fault::Backend) => Err(RepositoryError::Backend(format!(
+                "{} failed on purpose.",
+                operation
+            ))),
+        }
+    }
+}
+
+/// Implements a repository trait by passing every call on to the in-memory repository, unless the
+/// operation has been made to fail.
+macro_rules! delegate {
+    ($trait:ident { $(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)* }) => {
+        #[async_trait]
+        impl $trait for FaultyRepository {
+            $(
+                async fn $name(&self $(, $arg: $ty)*) -> $ret {
+                    self.check(stringify!($name))?;
+                    self.repository.$name($($arg),*).await
+                }
+            )*
+        }
+    };
+}
+
+delegate!(CreditRepository {
+    fn get_all_user_credits(&self) -> RepositoryResult<Vec<UserCredit>>;
+    fn get_user_credits_page(&self, page_request: &PageRequest) -> RepositoryResult<Page<UserCredit>>;
+    fn get_user_credit(&self, user_id: &str) -> RepositoryResult<Option<UserCredit>>;
+    fn upsert_user_credit(&self, user_credit: UserCredit) -> RepositoryResult<()>;
+    fn get_versioned_user_credit(&self, user_id: &str) -> RepositoryResult<Option<Versioned<UserCredit>>>;
+    fn replace_user_credit(&self, user_credit: UserCredit, version: &str) -> RepositoryResult<()>;
+    fn delete_user_credit(&self, user_credit: &UserCredit) -> RepositoryResult<()>;
+});
+
+delegate!(LedgerRepository {
+    fn add_credit_transaction(&self, transaction: CreditTransaction) -> RepositoryResult<()>;
+    fn get_credit_transactions(&self, user_id: &str, query: &CreditHistoryQuery) -> RepositoryResult<Vec<CreditTransaction>>;
+});
+
+delegate!(LotteryRepository {
+    fn get_all_user_lotteries(&self) -> RepositoryResult<Vec<UserLottery>>;
+    fn get_user_lotteries_page(&self, page_request: &PageRequest) -> RepositoryResult<Page<UserLottery>>;
+    fn get_user_lottery(&self, user_id: &str) -> RepositoryResult<Option<UserLottery>>;
+    fn add_user_lotteries(&self, user_lottery: UserLottery) -> RepositoryResult<UserLottery>;
+    fn take_user_lotteries(&self, user_id: &str) -> RepositoryResult<Vec<Vec<u8>>>;
+});
+
+delegate!(LotteryDrawRepository {
+    fn get_all_lottery_draws(&self) -> RepositoryResult<Vec<LotteryDraw>>;
+    fn get_lottery_draws_page(&self, page_request: &PageRequest) -> RepositoryResult<Page<LotteryDraw>>;
+    fn get_lottery_draw(&self, draw_id: &str) -> RepositoryResult<Option<LotteryDraw>>;
+    fn add_lottery_draw(&self, lottery_draw: LotteryDraw, results: Vec<LotteryDrawResult>) -> RepositoryResult<()>;
+    fn get_lottery_draw_results(&self, draw_id: &str) -> RepositoryResult<Vec<LotteryDrawResult>>;
+    fn get_user_lottery_results(&self, user_id: &str) -> RepositoryResult<Vec<LotteryDrawResult>>;
+    fn get_unpaid_lottery_results(&self) -> RepositoryResult<Vec<LotteryDrawResult>>;
+    fn set_lottery_results_paid(&self, results: Vec<LotteryDrawResult>, paid: bool) -> RepositoryResult<()>;
+    fn get_lottery_commitment(&self) -> RepositoryResult<Option<LotteryCommitment>>;
+    fn upsert_lottery_commitment(&self, commitment: LotteryCommitment) -> RepositoryResult<()>;
+});
+
+delegate!(JackpotRepository {
+    fn get_jackpot(&self) -> RepositoryResult<Option<LotteryJackpot>>;
+    fn add_to_jackpot(&self, amount: i32, seed: i32) -> RepositoryResult<LotteryJackpot>;
+});
+
+delegate!(RewardRepository {
+    fn get_rewards(&self, user_id: &str) -> RepositoryResult<Option<Rewards>>;
+    fn get_versioned_rewards(&self, user_id: &str) -> RepositoryResult<Option<Versioned<Rewards>>>;
+    fn add_rewards(&self, rewards: Rewards) -> RepositoryResult<()>;
+    fn replace_rewards(&self, rewards: Rewards, version: &str) -> RepositoryResult<()>;
+});
+
+delegate!(RollRepository {
+    fn get_all_user_rolls(&self) -> RepositoryResult<Vec<UserRoll>>;
+    fn get_user_rolls_page(&self, page_request: &PageRequest) -> RepositoryResult<Page<UserRoll>>;
+    fn get_user_rolls(&self, user_id: &str) -> RepositoryResult<Vec<UserRoll>>;
+    fn get_user_roll(&self, user_id: &str, roll_id: i32) -> RepositoryResult<Option<UserRoll>>;
+    fn get_user_roll_history(&self, user_id: &str, query: &RollHistoryQuery, page_request: &PageRequest) -> RepositoryResult<Page<UserRoll>>;
+    fn get_user_character_rolls(&self, user_id: &str, mal_character_ids: &[i32]) -> RepositoryResult<Vec<UserRoll>>;
+    fn get_character_rolls(&self, mal_character_id: i32) -> RepositoryResult<Vec<UserRoll>>;
+    fn get_max_roll_id(&self, user_id: &str) -> RepositoryResult<i32>;
+    fn get_character_roll_counts(&self, user_id: &str) -> RepositoryResult<Vec<CharacterRollCount>>;
+    fn add_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()>;
+    fn update_user_roll(&self, user_roll: UserRoll) -> RepositoryResult<()>;
+    fn get_roll_pity(&self, user_id: &str) -> RepositoryResult<Option<RollPity>>;
+    fn upsert_roll_pity(&self, roll_pity: RollPity) -> RepositoryResult<()>;
+});
+
+delegate!(CollectionRepository {
+    fn get_character_progresses(&self, user_id: &str) -> RepositoryResult<Vec<CharacterProgress>>;
+    fn get_character_progress(&self, user_id: &str, mal_character_id: i32) -> RepositoryResult<Option<CharacterProgress>>;
+    fn upsert_character_progress(&self, progress: CharacterProgress) -> RepositoryResult<()>;
+});
+
+delegate!(TradeRepository {
+    fn get_trade_offer(&self, offer_id: &str) -> RepositoryResult<Option<TradeOffer>>;
+    fn get_trade_offers(&self, user_id: &str) -> RepositoryResult<Vec<TradeOffer>>;
+    fn upsert_trade_offer(&self, trade_offer: TradeOffer) -> RepositoryResult<()>;
+    fn complete_trade_offer(&self, trade_offer: TradeOffer, transfers: Vec<(UserRoll, UserRoll)>) -> RepositoryResult<()>;
+});
+
+delegate!(MarketRepository {
+    fn get_active_market_listings(&self) -> RepositoryResult<Vec<MarketListing>>;
+    fn get_market_listing(&self, listing_id: &str) -> RepositoryResult<Option<MarketListing>>;
+    fn upsert_market_listing(&self, listing: MarketListing) -> RepositoryResult<()>;
+    fn complete_market_listing(&self, listing: MarketListing, transfer: (UserRoll, UserRoll)) -> RepositoryResult<()>;
+});
+
+delegate!(BannerRepository {
+    fn get_banners(&self) -> RepositoryResult<Vec<Banner>>;
+    fn get_banner(&self, banner_id: &str) -> RepositoryResult<Option<Banner>>;
+    fn upsert_banner(&self, banner: Banner) -> RepositoryResult<()>;
+});
+
+delegate!(MalCharacterRepository {
+    fn get_all_mal_characters(&self) -> RepositoryResult<Vec<MalCharacter>>;
+    fn get_mal_character(&self, character_id: i32) -> RepositoryResult<Option<MalCharacter>>;
+    fn get_mal_characters(&self, character_ids: &[i32]) -> RepositoryResult<Vec<MalCharacter>>;
+    fn add_mal_character(&self, mal_character: MalCharacter) -> RepositoryResult<()>;
+    fn set_mal_character_rarities(&self, assignments: &[RarityAssignment]) -> RepositoryResult<Vec<i32>>;
+});
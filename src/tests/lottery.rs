@@ -0,0 +1,207 @@
+use super::faulty_repository::Fault;
+use super::TestApp;
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::lottery::{derive_numbers, get_lottery_commitment};
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn purchases_and_draws_lotteries() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.lottery_rules;
+    app.add_user("alice", 100).await;
+    let jackpot = app.get("/lottery/jackpot").await.body["amount"]
+        .as_i64()
+        .unwrap_or_default();
+
+    let tickets = json!({
+        "username": "alice",
+        "lotteries": [[6, 5, 4, 3, 2, 1], [7, 8, 9, 10, 11, 12]],
+    });
+    let response = app.post("/lottery/alice/new", tickets).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let response = app
+        .post(
+            "/lottery/alice/quickpick",
+            json!({ "username": "alice", "count": 3 }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["tickets"].as_array().map(Vec::len), Some(3));
+
+    let cost = i64::from(rules.ticket_price) * 5;
+    let credits = app.get_credits("alice").await;
+    assert_eq!(credits, 100 - cost);
+    let response = app.get("/lottery/jackpot").await;
+    assert_eq!(
+        response.body["amount"].as_i64(),
+        Some(jackpot + cost * i64::from(rules.jackpot_share_percent) / 100)
+    );
+
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let draw_id = response.body["id"].as_str().unwrap_or_default().to_string();
+    let results = response.body["results"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(results.len(), 5);
+    assert!(results.iter().all(|result| result["user_id"] == "alice"));
+    let prizes = results
+        .iter()
+        .map(|result| result["prize"].as_i64().unwrap_or_default())
+        .sum::<i64>();
+    assert_eq!(app.get_credits("alice").await, credits + prizes);
+
+    let response = app.get("/lottery/alice/results").await;
+    assert_eq!(response.body.as_array().map(Vec::len), Some(5));
+    let response = app.get(&format!("/lottery/draws/{}/verify", draw_id)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["seed_hash_matches"], true);
+    assert_eq!(response.body["winning_numbers_match"], true);
+
+    // The tickets took part in the draw, so the next one starts without them.
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["results"], json!([]));
+}
+
+#[tokio::test]
+async fn rejects_invalid_and_unaffordable_tickets() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 5).await;
+
+    let response = app
+        .post(
+            "/lottery/alice/new",
+            json!({ "username": "alice", "lotteries": [[1, 2, 3]] }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post(
+            "/lottery/alice/new",
+            json!({ "username": "alice", "lotteries": [[1, 2, 3, 4, 5, 6]] }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(app.get_credits("alice").await, 5);
+
+    let response = app
+        .post(
+            "/lottery/bob/quickpick",
+            json!({ "username": "bob", "count": 1 }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn retries_unpaid_prizes_with_the_next_draw() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.lottery_rules;
+    app.add_user("alice", 100).await;
+
+    let commitment = get_lottery_commitment(&app.repository)
+        .await
+        .expect("Failed to commit to a seed.");
+    let (winning_numbers, _) = derive_numbers(
+        &commitment.seed,
+        rules.numbers_per_ticket as usize,
+        rules.max_number,
+    );
+    let tickets = json!({ "username": "alice", "lotteries": [winning_numbers] });
+    let response = app.post("/lottery/alice/new", tickets).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let credits = app.get_credits("alice").await;
+
+    app.repository.fail("replace_user_credit", Fault::Conflict);
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let prize = response.body["results"][0]["prize"]
+        .as_i64()
+        .unwrap_or_default();
+    assert!(prize > 0);
+    assert_eq!(response.body["results"][0]["paid"], false);
+    app.repository.recover("replace_user_credit");
+    assert_eq!(app.get_credits("alice").await, credits);
+
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(app.get_credits("alice").await, credits + prize);
+    let response = app.get("/lottery/alice/results").await;
+    assert_eq!(response.body[0]["paid"], true);
+
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(app.get_credits("alice").await, credits + prize);
+}
+
+#[tokio::test]
+async fn splits_the_jackpot_among_winners() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.lottery_rules;
+    let commitment = get_lottery_commitment(&app.repository)
+        .await
+        .expect("Failed to commit to a seed.");
+    let (winning_numbers, _) = derive_numbers(
+        &commitment.seed,
+        rules.numbers_per_ticket as usize,
+        rules.max_number,
+    );
+    let jackpot_seed = app.get("/lottery/jackpot").await.body["amount"]
+        .as_i64()
+        .unwrap_or_default();
+
+    let winners = ["alice", "bob", "carol"];
+    for user_id in winners {
+        app.add_user(user_id, 100).await;
+        let tickets = json!({ "username": user_id, "lotteries": [winning_numbers] });
+        let response = app
+            .post(&format!("/lottery/{}/new", user_id), tickets)
+            .await;
+        assert_eq!(response.status, StatusCode::CREATED);
+    }
+    let mut jackpot = app.get("/lottery/jackpot").await.body["amount"]
+        .as_i64()
+        .unwrap_or_default();
+    if jackpot % winners.len() as i64 == 0 {
+        // Another ticket that can't win leaves a remainder to roll over.
+        let losing_numbers = (1..=rules.max_number)
+            .filter(|number| !winning_numbers.contains(number))
+            .take(rules.numbers_per_ticket as usize)
+            .collect::<Vec<_>>();
+        let tickets = json!({ "username": "alice", "lotteries": [losing_numbers] });
+        let response = app.post("/lottery/alice/new", tickets).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        jackpot = app.get("/lottery/jackpot").await.body["amount"]
+            .as_i64()
+            .unwrap_or_default();
+    }
+    let share = jackpot / winners.len() as i64;
+    let credits = app.get_credits("bob").await;
+
+    let response = app.post("/lottery/draw", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["jackpot"].as_i64(), Some(jackpot));
+    let jackpot_tier = response.body["tiers"]
+        .as_array()
+        .and_then(|tiers| {
+            tiers
+                .iter()
+                .find(|tier| tier["matches"] == json!(rules.numbers_per_ticket))
+        })
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(jackpot_tier["winners"], json!(winners.len()));
+    assert_eq!(jackpot_tier["prize"].as_i64(), Some(share));
+    assert_eq!(app.get_credits("bob").await, credits + share);
+
+    // The remainder that couldn't be split evenly stays in the reset pool.
+    let response = app.get("/lottery/jackpot").await;
+    assert_eq!(
+        response.body["amount"].as_i64(),
+        Some(jackpot_seed + jackpot % winners.len() as i64)
+    );
+}
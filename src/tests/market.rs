@@ -0,0 +1,249 @@
+use super::faulty_repository::Fault;
+use super::TestApp;
+use crate::db::repository::{MarketRepository, RollRepository};
+use crate::model::market::MarketListing;
+use crate::model::user_roll::UserRoll;
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::market::settle_ended_auctions;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+
+impl TestApp {
+    /// Lists the user's roll as an auction and has the other user bid on it.
+    async fn add_auction(&self, seller_id: &str, bidder_id: &str, amount: i32) -> String {
+        let listing = json!({
+            "seller_id": seller_id,
+            "roll_id": 1,
+            "listing_type": "Auction",
+            "price": amount,
+            "duration_hours": 1,
+        });
+        let response = self.post("/market", listing).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        let listing_id = response.body["id"].as_str().unwrap_or_default().to_string();
+        let response = self
+            .post(
+                &format!("/market/{}/bid", listing_id),
+                json!({ "user_id": bidder_id, "amount": amount }),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        listing_id
+    }
+
+    async fn end_auction(&self, listing_id: &str) {
+        let listing = self
+            .repository
+            .get_market_listing(listing_id)
+            .await
+            .expect("Failed to get listing.")
+            .expect("The listing is not found.");
+        self.repository
+            .upsert_market_listing(MarketListing {
+                ends_at: Some(OffsetDateTime::now_utc()),
+                ..listing
+            })
+            .await
+            .expect("Failed to update listing.");
+    }
+
+    /// Runs the settlement of ended auctions and returns the listing afterwards.
+    async fn settle_auction(&self, listing_id: &str) -> Value {
+        settle_ended_auctions(&self.repository, "test").await;
+        self.get(&format!("/market/{}", listing_id)).await.body
+    }
+}
+
+#[tokio::test]
+async fn sells_fixed_price_listings() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_user("bob", 50).await;
+    app.give_roll("alice", 1, 1).await;
+
+    let listing = json!({
+        "seller_id": "alice",
+        "roll_id": 1,
+        "listing_type": "FixedPrice",
+        "price": 40,
+    });
+    let response = app.post("/market", listing.clone()).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let listing_id = response.body["id"].as_str().unwrap_or_default().to_string();
+    let response = app.post("/market", listing).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app.get("/market").await;
+    assert_eq!(response.body.as_array().map(Vec::len), Some(1));
+
+    let buy_uri = format!("/market/{}/buy", listing_id);
+    let response = app.post(&buy_uri, json!({ "user_id": "alice" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app.post(&buy_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["status"], "Sold");
+
+    let fee = CONFIGURATION.market_rules.get_fee(40);
+    assert_eq!(app.get_credits("alice").await, i64::from(40 - fee));
+    assert_eq!(app.get_credits("bob").await, 10);
+    assert_eq!(app.get_owned_characters("alice").await, Vec::<i64>::new());
+    assert_eq!(app.get_owned_characters("bob").await, vec![1]);
+
+    let response = app.post(&buy_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    let response = app.get("/market").await;
+    assert_eq!(response.body, json!([]));
+}
+
+#[tokio::test]
+async fn holds_auction_bids() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_user("bob", 100).await;
+    app.add_user("carol", 100).await;
+    app.give_roll("alice", 1, 1).await;
+
+    let listing = json!({
+        "seller_id": "alice",
+        "roll_id": 1,
+        "listing_type": "Auction",
+        "price": 20,
+        "duration_hours": 1,
+    });
+    let response = app.post("/market", listing).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let listing_id = response.body["id"].as_str().unwrap_or_default().to_string();
+
+    let bid_uri = format!("/market/{}/bid", listing_id);
+    let response = app
+        .post(&bid_uri, json!({ "user_id": "bob", "amount": 15 }))
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app
+        .post(&bid_uri, json!({ "user_id": "bob", "amount": 20 }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(app.get_credits("bob").await, 80);
+
+    let response = app
+        .post(&bid_uri, json!({ "user_id": "carol", "amount": 30 }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["highest_bidder_id"], "carol");
+    assert_eq!(app.get_credits("bob").await, 100);
+    assert_eq!(app.get_credits("carol").await, 70);
+
+    let response = app
+        .post(
+            &format!("/market/{}/buy", listing_id),
+            json!({ "user_id": "bob" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app
+        .post(
+            &format!("/market/{}/cancel", listing_id),
+            json!({ "user_id": "alice" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(app.get_owned_characters("alice").await, vec![1]);
+}
+
+#[tokio::test]
+async fn keeps_the_bid_held_until_the_auction_settles() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_user("bob", 100).await;
+    app.give_roll("alice", 1, 1).await;
+    let listing_id = app.add_auction("alice", "bob", 40).await;
+
+    app.end_auction(&listing_id).await;
+    app.repository
+        .fail("complete_market_listing", Fault::Backend);
+    let listing = app.settle_auction(&listing_id).await;
+    assert_eq!(listing["status"], "Active");
+    assert_eq!(listing["highest_bidder_id"], "bob");
+    assert_eq!(app.get_credits("bob").await, 60);
+    assert_eq!(app.get_credits("alice").await, 0);
+
+    app.repository.recover("complete_market_listing");
+    let listing = app.settle_auction(&listing_id).await;
+    assert_eq!(listing["status"], "Sold");
+    let fee = CONFIGURATION.market_rules.get_fee(40);
+    assert_eq!(app.get_credits("alice").await, i64::from(40 - fee));
+    assert_eq!(app.get_credits("bob").await, 60);
+    assert_eq!(app.get_owned_characters("bob").await, vec![1]);
+}
+
+#[tokio::test]
+async fn pays_the_bid_back_once_the_auction_is_cancelled() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_user("bob", 100).await;
+    app.give_roll("alice", 1, 1).await;
+    let listing_id = app.add_auction("alice", "bob", 40).await;
+
+    // The roll is gone, but the listing can't be taken off the market yet.
+    app.end_auction(&listing_id).await;
+    let user_roll = app
+        .repository
+        .get_user_roll("alice", 1)
+        .await
+        .expect("Failed to get user roll.")
+        .expect("The user roll is not found.");
+    app.repository
+        .update_user_roll(UserRoll {
+            consumed: true,
+            ..user_roll
+        })
+        .await
+        .expect("Failed to update user roll.");
+    app.repository.fail("upsert_market_listing", Fault::Backend);
+    let listing = app.settle_auction(&listing_id).await;
+    assert_eq!(listing["status"], "Active");
+    assert_eq!(app.get_credits("bob").await, 60);
+
+    app.repository.recover("upsert_market_listing");
+    let listing = app.settle_auction(&listing_id).await;
+    assert_eq!(listing["status"], "Cancelled");
+    assert_eq!(app.get_credits("bob").await, 100);
+
+    let listing = app.settle_auction(&listing_id).await;
+    assert_eq!(listing["status"], "Cancelled");
+    assert_eq!(app.get_credits("bob").await, 100);
+    assert_eq!(app.get_credits("alice").await, 0);
+}
+
+#[tokio::test]
+async fn pays_the_buyer_back_when_the_sale_fails() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_user("bob", 50).await;
+    app.give_roll("alice", 1, 1).await;
+
+    let listing = json!({
+        "seller_id": "alice",
+        "roll_id": 1,
+        "listing_type": "FixedPrice",
+        "price": 40,
+    });
+    let response = app.post("/market", listing).await;
+    let buy_uri = format!(
+        "/market/{}/buy",
+        response.body["id"].as_str().unwrap_or_default()
+    );
+
+    app.repository
+        .fail("complete_market_listing", Fault::Backend);
+    let response = app.post(&buy_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(app.get_credits("alice").await, 0);
+    assert_eq!(app.get_credits("bob").await, 50);
+    assert_eq!(app.get_owned_characters("alice").await, vec![1]);
+
+    app.repository.recover("complete_market_listing");
+    let response = app.post(&buy_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(app.get_credits("bob").await, 10);
+}
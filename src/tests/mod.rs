@@ -0,0 +1,182 @@
+mod banner;
+mod credit;
+mod faulty_repository;
+mod lottery;
+mod market;
+mod reward;
+mod roll;
+mod trade;
+
+use crate::create_router;
+use crate::db::repository::RollRepository;
+use crate::model::app_state::AppState;
+use crate::model::user_roll::UserRoll;
+use crate::shared::configuration::CONFIGURATION;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use faulty_repository::FaultyRepository;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// A router backed by an in-memory repository, logged in as the bot user.
+struct TestApp {
+    router: Router,
+    repository: FaultyRepository,
+    token: Option<String>,
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let repository = FaultyRepository::default();
+        let router = create_router(AppState {
+            repository: Arc::new(repository.clone()),
+        });
+        let mut app = TestApp {
+            router,
+            repository,
+            token: None,
+        };
+
+        let login = json!({
+            "user_name": CONFIGURATION.bot_user_name,
+            "password": CONFIGURATION.bot_user_pass,
+        });
+        let response = app.request(Method::POST, "/login", Some(login)).await;
+        assert_eq!(response.status, StatusCode::OK);
+        app.token = response.body["token"].as_str().map(str::to_string);
+        app
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = &self.token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .expect("Failed to build request.");
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("Failed to send request.");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read response body.");
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+        };
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    async fn get(&self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    async fn patch(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PATCH, uri, Some(body)).await
+    }
+
+    async fn add_user(&self, user_id: &str, credits: i32) {
+        let user_credit = json!({
+            "id": user_id,
+            "username": user_id,
+            "user_id": user_id,
+            "credits": credits,
+        });
+        let response = self.post("/credit", user_credit).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+    }
+
+    async fn get_credits(&self, user_id: &str) -> i64 {
+        let response = self.get(&format!("/credit/{}", user_id)).await;
+        assert_eq!(response.status, StatusCode::OK);
+        response.body["credits"]
+            .as_i64()
+            .expect("The credit info has no credits.")
+    }
+
+    async fn add_mal_character(&self, character_id: i32, rarity: &str) {
+        let mal_character = json!({
+            "Id": character_id,
+            "Url": "",
+            "Name": format!("Character {}", character_id),
+            "NameKanji": "",
+            "ImageUrl": "",
+            "CreatedAt": "",
+            "About": "",
+            "Rarity": rarity,
+            "id": "",
+        });
+        let response = self.post("/mal_character", mal_character).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+    }
+
+    /// Gives the user a roll of the character without charging them for it.
+    async fn give_roll(&self, user_id: &str, roll_id: i32, mal_character_id: i32) {
+        let user_roll = UserRoll {
+            roll_id,
+            user_id: user_id.to_string(),
+            mal_character_id,
+            id: format!("{}:{}", user_id, roll_id),
+            ..UserRoll::default()
+        };
+        self.repository
+            .add_user_roll(user_roll)
+            .await
+            .expect("Failed to add user roll.");
+    }
+
+    /// The characters of the user's unspent rolls, in roll ID order.
+    async fn get_owned_characters(&self, user_id: &str) -> Vec<i64> {
+        let response = self.get(&format!("/user_roll/{}", user_id)).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let mut rolls = response
+            .body
+            .as_array()
+            .expect("The user's rolls aren't a list.")
+            .iter()
+            .filter(|roll| roll["user_roll"]["Consumed"] != json!(true))
+            .map(|roll| {
+                (
+                    roll["user_roll"]["Id"].as_i64().unwrap_or_default(),
+                    roll["user_roll"]["MalCharacterId"]
+                        .as_i64()
+                        .unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        rolls.sort_unstable();
+        rolls
+            .into_iter()
+            .map(|(_, mal_character_id)| mal_character_id)
+            .collect()
+    }
+}
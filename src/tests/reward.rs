@@ -0,0 +1,48 @@
+use super::TestApp;
+use crate::shared::configuration::CONFIGURATION;
+use axum::http::{header, StatusCode};
+
+#[tokio::test]
+async fn claims_rewards_once_per_cooldown() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.reward_rules;
+    app.add_user("alice", 0).await;
+
+    let response = app.get("/rewards/alice/daily").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.body["amount"].as_i64(),
+        Some(i64::from(rules.daily_reward))
+    );
+    assert_eq!(response.body["daily_streak"], 1);
+    let response = app.get("/rewards/alice/weekly").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        app.get_credits("alice").await,
+        i64::from(rules.daily_reward + rules.weekly_reward)
+    );
+
+    for uri in ["/rewards/alice/daily", "/lottery/alice/weekly"] {
+        let response = app.get(uri).await;
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.body["seconds_remaining"].as_i64() > Some(0));
+        assert!(response.headers.contains_key(header::RETRY_AFTER));
+    }
+    assert_eq!(
+        app.get_credits("alice").await,
+        i64::from(rules.daily_reward + rules.weekly_reward)
+    );
+
+    let response = app.get("/rewards/alice").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["daily"]["claimable"], false);
+    assert_eq!(response.body["weekly"]["claimable"], false);
+}
+
+#[tokio::test]
+async fn rejects_rewards_for_unknown_users() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/rewards/bob/daily").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
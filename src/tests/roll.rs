@@ -0,0 +1,301 @@
+use super::faulty_repository::Fault;
+use super::TestApp;
+use crate::db::repository::{RepositoryError, RollRepository};
+use crate::model::mal_character::Rarity;
+use crate::model::user_roll::{RollPity, UserRoll};
+use crate::shared::configuration::CONFIGURATION;
+use crate::shared::constants::CONTINUATION_HEADER;
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn rolls_characters_and_converts_duplicates() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.roll_rules;
+    app.add_user("alice", rules.roll_cost * 2 + 5).await;
+
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    app.add_mal_character(1, "Common").await;
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["user_roll"]["Id"], 1);
+    assert_eq!(response.body["mal_character"]["Id"], 1);
+    assert_eq!(response.body["duplicate"], false);
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["user_roll"]["Id"], 2);
+    assert_eq!(response.body["duplicate"], true);
+    assert_eq!(app.get_credits("alice").await, 5);
+
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(app.get_credits("alice").await, 5);
+
+    let response = app
+        .post(
+            "/user_roll/alice/2/duplicate",
+            json!({ "action": "convert" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let duplicate_credits = i64::from(rules.duplicate_credits[&Rarity::Common]);
+    assert_eq!(app.get_credits("alice").await, 5 + duplicate_credits);
+    assert_eq!(app.get_owned_characters("alice").await, vec![1]);
+
+    // The last copy of a character can't be spent.
+    let response = app
+        .post(
+            "/user_roll/alice/1/duplicate",
+            json!({ "action": "convert" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app.get("/user_roll/alice/1").await;
+    assert_eq!(response.body["duplicate"], false);
+}
+
+#[tokio::test]
+async fn refunds_rolls_that_cannot_be_stored() {
+    let app = TestApp::new().await;
+    app.add_user("alice", CONFIGURATION.roll_rules.roll_cost)
+        .await;
+    app.add_mal_character(1, "Common").await;
+
+    app.repository.fail("add_user_roll", Fault::Backend);
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        app.get_credits("alice").await,
+        i64::from(CONFIGURATION.roll_rules.roll_cost)
+    );
+    assert_eq!(app.get_owned_characters("alice").await, Vec::<i64>::new());
+}
+
+#[tokio::test]
+async fn rejects_taken_roll_ids() {
+    let app = TestApp::new().await;
+    app.add_user("alice", CONFIGURATION.roll_rules.roll_cost)
+        .await;
+    app.add_mal_character(1, "Common").await;
+    app.give_roll("alice", 1, 1).await;
+
+    let user_roll = UserRoll {
+        roll_id: 1,
+        user_id: "alice".to_string(),
+        id: "another".to_string(),
+        ..UserRoll::default()
+    };
+    let result = app.repository.add_user_roll(user_roll).await;
+    assert!(matches!(result, Err(RepositoryError::Conflict)));
+
+    app.repository.fail("add_user_roll", Fault::Conflict);
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        app.get_credits("alice").await,
+        i64::from(CONFIGURATION.roll_rules.roll_cost)
+    );
+
+    app.repository.recover("add_user_roll");
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["user_roll"]["Id"], 2);
+    assert_eq!(response.body["user_roll"]["id"], "alice:2");
+}
+
+#[tokio::test]
+async fn imports_rolls_under_the_next_roll_id() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.give_roll("alice", 1, 1).await;
+
+    let user_roll = json!({
+        "Id": 1,
+        "UserId": "bob",
+        "MalCharacterId": 2,
+        "CreatedAt": "",
+        "Consumed": true,
+        "id": "alice:1",
+    });
+    let response = app.post("/user_roll/alice/new", user_roll).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["Id"], 2);
+    assert_eq!(response.body["UserId"], "alice");
+    assert_eq!(response.body["id"], "alice:2");
+    assert_eq!(app.get_owned_characters("alice").await, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn tracks_pity_between_rolls() {
+    let app = TestApp::new().await;
+    let rules = &CONFIGURATION.roll_rules;
+    app.add_user("alice", rules.roll_cost * 2).await;
+    app.add_mal_character(1, "Common").await;
+
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["pity"]["misses"], 1);
+    assert_eq!(
+        response.body["pity"]["rolls_until_guaranteed"].as_u64(),
+        Some(u64::from(rules.hard_pity - 1))
+    );
+
+    app.add_mal_character(2, rules.pity_rarity.as_str()).await;
+    let roll_pity = RollPity {
+        misses: rules.hard_pity - 1,
+        ..RollPity::new("alice")
+    };
+    app.repository
+        .upsert_roll_pity(roll_pity)
+        .await
+        .expect("Failed to update roll pity.");
+    let response = app.post("/user_roll/alice/roll", json!({})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["mal_character"]["Id"], 2);
+    assert_eq!(response.body["pity"]["misses"], 0);
+}
+
+#[tokio::test]
+async fn summarizes_rolls_and_owners() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 0).await;
+    app.add_mal_character(1, "Common").await;
+    app.add_mal_character(2, "Common").await;
+    app.add_mal_character(3, "Rare").await;
+    app.give_roll("alice", 1, 1).await;
+    app.give_roll("alice", 2, 1).await;
+    app.give_roll("alice", 3, 3).await;
+    app.give_roll("bob", 1, 1).await;
+
+    let response = app.get("/user_roll/alice/summary").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["total_rolls"], 3);
+    assert_eq!(response.body["unique_characters"], 2);
+    assert_eq!(response.body["total_characters"], 3);
+    assert_eq!(response.body["duplicates"], 1);
+    let common = response.body["rarity_breakdown"]
+        .as_array()
+        .and_then(|breakdown| {
+            breakdown
+                .iter()
+                .find(|rarity| rarity["rarity"] == json!(Rarity::Common))
+        })
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(common["unique_characters"], 1);
+    assert_eq!(common["total_characters"], 2);
+    assert_eq!(common["completion_percentage"].as_f64(), Some(50.0));
+    assert_eq!(common["duplicates"], 1);
+
+    let response = app.get("/mal_character/1/owners").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.body["owners"],
+        json!([
+            { "user_id": "alice", "copies": 2 },
+            { "user_id": "bob", "copies": 1 },
+        ])
+    );
+
+    // Converted copies still count as rolls, but not as duplicates or owned copies.
+    let response = app
+        .post(
+            "/user_roll/alice/2/duplicate",
+            json!({ "action": "convert" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let response = app.get("/user_roll/alice/summary").await;
+    assert_eq!(response.body["total_rolls"], 3);
+    assert_eq!(response.body["duplicates"], 0);
+    let response = app.get("/mal_character/1/owners").await;
+    assert_eq!(response.body["owners"][0]["copies"], 1);
+
+    let response = app.get("/mal_character/4/owners").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn pages_through_roll_history() {
+    let app = TestApp::new().await;
+    app.add_mal_character(1, "Common").await;
+    for roll_id in 1..=5 {
+        let user_roll = UserRoll {
+            roll_id,
+            user_id: "alice".to_string(),
+            mal_character_id: 1,
+            created_at: format!("2026-10-0{}T12:00:00Z", roll_id),
+            id: UserRoll::get_id("alice", roll_id),
+            ..UserRoll::default()
+        };
+        app.repository
+            .add_user_roll(user_roll)
+            .await
+            .expect("Failed to add user roll.");
+    }
+    app.give_roll("bob", 1, 1).await;
+
+    let mut pages = vec![];
+    let mut uri = "/user_roll/alice?limit=2".to_string();
+    loop {
+        let response = app.get(&uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+        pages.push(
+            response
+                .body
+                .as_array()
+                .expect("The user's rolls aren't a list.")
+                .iter()
+                .map(|roll| {
+                    (
+                        roll["user_roll"]["Id"].as_i64().unwrap_or_default(),
+                        roll["duplicate"] == json!(true),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+        match response.headers.get(CONTINUATION_HEADER) {
+            Some(continuation) => {
+                uri = format!(
+                    "/user_roll/alice?limit=2&continuation={}",
+                    continuation.to_str().unwrap_or_default()
+                );
+            }
+            None => break,
+        }
+    }
+    // Duplicates are told apart by all of the user's copies, not just those on the same page.
+    assert_eq!(
+        pages,
+        vec![
+            vec![(1, true), (2, true)],
+            vec![(3, true), (4, true)],
+            vec![(5, true)],
+        ]
+    );
+
+    let response = app
+        .get("/user_roll/alice?limit=10&from=2026-10-02T00:00:00Z&to=2026-10-03T23:59:59Z")
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let roll_ids = response
+        .body
+        .as_array()
+        .map(|rolls| {
+            rolls
+                .iter()
+                .map(|roll| roll["user_roll"]["Id"].as_i64().unwrap_or_default())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    assert_eq!(roll_ids, vec![2, 3]);
+
+    let response = app.get("/user_roll/alice?limit=2&continuation=abc").await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = app
+        .get("/user_roll/alice?from=2026-10-03T00:00:00Z&to=2026-10-02T00:00:00Z")
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
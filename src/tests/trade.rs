@@ -0,0 +1,142 @@
+use super::faulty_repository::Fault;
+use super::TestApp;
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn accepts_trade_offers() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+    app.add_user("bob", 100).await;
+    app.give_roll("alice", 1, 1).await;
+    app.give_roll("bob", 1, 2).await;
+    app.give_roll("bob", 2, 3).await;
+
+    let offer = json!({
+        "from_user_id": "alice",
+        "to_user_id": "bob",
+        "offered_roll_ids": [1],
+        "requested_roll_ids": [1],
+        "offered_credits": 20,
+    });
+    let response = app.post("/trades", offer).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["status"], "Pending");
+    let offer_id = response.body["id"].as_str().unwrap_or_default().to_string();
+
+    let response = app.get("/trades/user/bob").await;
+    assert_eq!(response.body["incoming"].as_array().map(Vec::len), Some(1));
+    assert_eq!(response.body["outgoing"], json!([]));
+
+    let accept_uri = format!("/trades/{}/accept", offer_id);
+    let response = app.post(&accept_uri, json!({ "user_id": "alice" })).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let response = app.post(&accept_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["status"], "Accepted");
+
+    assert_eq!(app.get_owned_characters("alice").await, vec![2]);
+    assert_eq!(app.get_owned_characters("bob").await, vec![3, 1]);
+    assert_eq!(app.get_credits("alice").await, 80);
+    assert_eq!(app.get_credits("bob").await, 120);
+
+    let response = app.post(&accept_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn rejects_and_cancels_trade_offers() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+    app.add_user("bob", 100).await;
+    app.give_roll("alice", 1, 1).await;
+    app.give_roll("bob", 1, 2).await;
+
+    let offer = json!({
+        "from_user_id": "alice",
+        "to_user_id": "bob",
+        "offered_roll_ids": [2],
+        "requested_roll_ids": [1],
+    });
+    let response = app.post("/trades", offer).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let offer = json!({
+        "from_user_id": "alice",
+        "to_user_id": "bob",
+        "offered_roll_ids": [1],
+        "requested_roll_ids": [1],
+    });
+    let mut offer_ids = vec![];
+    for _ in 0..2 {
+        let response = app.post("/trades", offer.clone()).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        offer_ids.push(response.body["id"].as_str().unwrap_or_default().to_string());
+    }
+
+    let response = app
+        .post(
+            &format!("/trades/{}/reject", offer_ids[0]),
+            json!({ "user_id": "bob" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["status"], "Rejected");
+    let response = app
+        .post(
+            &format!("/trades/{}/cancel", offer_ids[1]),
+            json!({ "user_id": "bob" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let response = app
+        .post(
+            &format!("/trades/{}/cancel", offer_ids[1]),
+            json!({ "user_id": "alice" }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["status"], "Cancelled");
+
+    assert_eq!(app.get_owned_characters("alice").await, vec![1]);
+    assert_eq!(app.get_owned_characters("bob").await, vec![2]);
+    let response = app.get("/trades/user/bob").await;
+    assert_eq!(response.body["incoming"], json!([]));
+}
+
+#[tokio::test]
+async fn keeps_credits_when_the_rolls_cannot_move() {
+    let app = TestApp::new().await;
+    app.add_user("alice", 100).await;
+    app.add_user("bob", 100).await;
+    app.give_roll("alice", 1, 1).await;
+    app.give_roll("bob", 1, 2).await;
+
+    let offer = json!({
+        "from_user_id": "alice",
+        "to_user_id": "bob",
+        "offered_roll_ids": [1],
+        "requested_roll_ids": [1],
+        "offered_credits": 20,
+    });
+    let response = app.post("/trades", offer).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let accept_uri = format!(
+        "/trades/{}/accept",
+        response.body["id"].as_str().unwrap_or_default()
+    );
+
+    app.repository.fail("complete_trade_offer", Fault::Backend);
+    let response = app.post(&accept_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(app.get_credits("alice").await, 100);
+    assert_eq!(app.get_credits("bob").await, 100);
+    assert_eq!(app.get_owned_characters("alice").await, vec![1]);
+    assert_eq!(app.get_owned_characters("bob").await, vec![2]);
+
+    app.repository.recover("complete_trade_offer");
+    let response = app.post(&accept_uri, json!({ "user_id": "bob" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(app.get_credits("alice").await, 80);
+    assert_eq!(app.get_credits("bob").await, 120);
+}